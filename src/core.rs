@@ -5,32 +5,43 @@ pub mod builtins;
 pub mod data;
 pub mod history;
 pub mod jobtable;
+pub mod keymap;
 pub mod options;
+pub mod trap;
 
 use self::data::Data;
+use self::history::HistoryEntry;
+use self::keymap::KeyMap;
 use self::options::Options;
 use std::collections::HashMap;
-use std::os::fd::{FromRawFd, OwnedFd};
+use std::os::fd::{FromRawFd, OwnedFd, RawFd};
 use std::{io, env, path, process};
 use nix::{fcntl, unistd};
 use nix::sys::{resource, signal, wait};
 use nix::sys::resource::UsageWho;
-use nix::sys::signal::{Signal, SigHandler};
+use nix::sys::signal::{SigAction, Signal, SigHandler};
 use nix::sys::wait::{WaitPidFlag, WaitStatus};
 use nix::sys::time::{TimeSpec, TimeVal};
 use nix::time;
 use nix::time::ClockId;
 use nix::unistd::Pid;
 use crate::error_message;
+use crate::core::builtins::completion::CompSpec;
 use crate::core::jobtable::JobEntry;
+use crate::feeder::terminal;
+use crate::utils::git;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering::Relaxed;
 
 pub struct ShellCore {
     pub data: Data,
-    rewritten_history: HashMap<usize, String>,
-    pub history: Vec<String>,
+    pub history: Vec<HistoryEntry>, // the newest first
+    history_new_lines: usize, // not written to the file yet
+    history_file_lines: usize, // already read from or written to the file
+    pub command_number: usize, // \# in prompts
+    pub kill_ring: Vec<String>,
+    pub keymap: KeyMap,
     pub builtins: HashMap<String, fn(&mut ShellCore, &mut Vec<String>) -> i32>,
     pub sigint: Arc<AtomicBool>,
     pub read_stdin: bool,
@@ -41,12 +52,18 @@ pub struct ShellCore {
     pub eval_level: i32,
     pub loop_level: i32,
     pub break_counter: i32,
+    pub continue_flag: bool,
+    pub getopts_position: (usize, usize, String), // (OPTIND, the next char in the arg, the arg)
     pub return_flag: bool,
     pub tty_fd: Option<OwnedFd>,
     pub job_table: Vec<JobEntry>,
     pub job_table_priority: Vec<usize>,
+    pub process_substitution_fds: Vec<RawFd>,
+    pub process_substitution_pids: Vec<Pid>,
     current_dir: Option<path::PathBuf>, // the_current_working_directory
-    pub completion_functions: HashMap<String, String>,
+    pub completion_specs: HashMap<String, CompSpec>,
+    pub completion_options: Option<Vec<String>>, // changed by compopt in completion
+    pub git_caches: HashMap<path::PathBuf, git::Cache>,
     pub real_time: TimeSpec, 
     pub user_time: TimeVal, 
     pub sys_time: TimeVal, 
@@ -54,6 +71,10 @@ pub struct ShellCore {
     pub shopts: Options,
     pub suspend_e_option: bool,
     pub script_name: String,
+    pub traps: HashMap<i32, String>,
+    pub parent_traps: HashMap<i32, String>, // shown by trap -p in a subshell until traps are changed
+    trap_default_actions: HashMap<i32, SigAction>,
+    pub trap_running: bool,
 }
 
 fn ignore_signal(sig: Signal) {
@@ -70,8 +91,12 @@ impl ShellCore {
     pub fn new() -> ShellCore {
         let mut core = ShellCore{
             data: Data::new(),
-            rewritten_history: HashMap::new(),
             history: vec![],
+            history_new_lines: 0,
+            history_file_lines: 0,
+            command_number: 1,
+            kill_ring: vec![],
+            keymap: KeyMap::new(),
             builtins: HashMap::new(),
             sigint: Arc::new(AtomicBool::new(false)),
             word_eval_error: false,
@@ -82,12 +107,18 @@ impl ShellCore {
             eval_level: 0,
             loop_level: 0,
             break_counter: 0,
+            continue_flag: false,
+            getopts_position: (0, 0, String::new()),
             return_flag: false,
             tty_fd: None,
             job_table: vec![],
             job_table_priority: vec![],
+            process_substitution_fds: vec![],
+            process_substitution_pids: vec![],
             current_dir: None,
-            completion_functions: HashMap::new(),
+            completion_specs: HashMap::new(),
+            completion_options: None,
+            git_caches: HashMap::new(),
             real_time: TimeSpec::new(0, 0),
             user_time: TimeVal::new(0, 0),
             sys_time: TimeVal::new(0, 0),
@@ -95,6 +126,10 @@ impl ShellCore {
             shopts: Options::new_as_shopts(),
             suspend_e_option: false,
             script_name: "-".to_string(),
+            traps: HashMap::new(),
+            parent_traps: HashMap::new(),
+            trap_default_actions: HashMap::new(),
+            trap_running: false,
         };
 
         core.init_current_directory();
@@ -104,12 +139,19 @@ impl ShellCore {
         ignore_signal(Signal::SIGTSTP);
 
         core.data.set_param("PS4", "+ ");
+        core.data.flags += "hB";
+        core.options.set("hashall", true);
+        core.options.set("braceexpand", true);
 
         if unistd::isatty(0) == Ok(true) {
             const V: &'static str = env!("CARGO_PKG_VERSION");
             eprintln!("Rusty Bash (a.k.a. Sushi shell), version {}", V);
 
-            core.data.flags += "i";
+            core.data.flags += "imH";
+            core.options.set("monitor", true);
+            core.options.set("histexpand", true);
+            core.options.set("history", true);
+            core.options.set("emacs", true);
             core.read_stdin = false;
             core.data.set_param("PS1", "🍣 ");
             core.data.set_param("PS2", "> ");
@@ -121,6 +163,7 @@ impl ShellCore {
         let home = core.data.get_param("HOME").to_string();
         core.data.set_param("HISTFILE", &(home + "/.sush_history"));
         core.data.set_param("HISTFILESIZE", "2000");
+        core.data.set_param("HISTSIZE", "500");
 
         core
     }
@@ -222,10 +265,34 @@ impl ShellCore {
                       sys_diff.tv_sec()%60, sys_diff.tv_usec());
     }
 
+    /* fds of <(...) and >(...) stay open until the command using them finishes */
+    pub fn close_process_substitutions(&mut self, keep: usize) {
+        if keep >= self.process_substitution_fds.len() {
+            return;
+        }
+        for fd in self.process_substitution_fds.split_off(keep) {
+            let _ = unistd::close(fd);
+        }
+    }
+
+    /* nounset: a non-interactive shell exits */
+    pub fn unbound_variable(&mut self, name: &str) -> bool {
+        let msg = format!("{}: unbound variable", name);
+        error_message::print(&msg, self, true);
+        if ! self.data.flags.contains('i') || self.is_subshell {
+            self.data.set_layer_param("?", "127", 0);
+            self.exit();
+        }
+        false
+    }
+
     fn check_e_option(&mut self) {
-        if self.data.get_param("?") != "0" 
-        && self.data.flags.contains("e") 
-        && ! self.suspend_e_option {
+        if self.data.get_param("?") == "0" || self.suspend_e_option {
+            return;
+        }
+
+        self.run_trap(trap::ERR);
+        if self.data.flags.contains("e") {
             self.exit();
         }
     }
@@ -292,6 +359,7 @@ impl ShellCore {
     }
 
     pub fn exit(&mut self) -> ! {
+        self.run_exit_trap();
         self.write_history_to_file();
 
         let es_str = self.data.get_param("?");
@@ -327,11 +395,13 @@ impl ShellCore {
         restore_signal(Signal::SIGINT);
         restore_signal(Signal::SIGTSTP);
         restore_signal(Signal::SIGPIPE);
+        self.reset_traps_for_subshell();
 
         self.is_subshell = true;
         self.set_pgid(pid, pgid);
         self.set_subshell_parameters();
         self.job_table.clear();
+        self.process_substitution_pids.clear();
     }
 
     pub fn init_current_directory(&mut self) {
@@ -361,7 +431,11 @@ impl ShellCore {
     }
 
     pub fn get_ps4(&mut self) -> String {
-        let ps4 = self.data.get_param("PS4").trim_end().to_string();
+        let flags = self.data.flags.clone();
+        self.data.flags = flags.replace('x', ""); //not to trace the expansion
+        let ps4 = self.data.get_param("PS4");
+        let ps4 = terminal::expand_prompt(self, &ps4).trim_end().to_string();
+        self.data.flags = flags;
         let mut multi_ps4 = ps4.to_string();
         for _ in 0..(self.source_level + self.eval_level) {
             multi_ps4 += &ps4;
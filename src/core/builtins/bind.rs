@@ -0,0 +1,206 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::ShellCore;
+use crate::core::keymap;
+use crate::core::keymap::Binding;
+use termion::event::Key;
+
+/* the sequences bound to the binding, sorted */
+fn sequences(core: &ShellCore, binding: &Binding) -> Vec<String> {
+    let mut ans: Vec<String> = core.keymap.bindings.iter()
+        .filter(|(_, b)| *b == binding)
+        .map(|(k, _)| keymap::sequence_to_string(k))
+        .collect();
+    ans.sort();
+    ans
+}
+
+fn quote_text(text: &str) -> String {
+    text.chars().map(|c| keymap::key_to_string(&Key::Char(c))).collect()
+}
+
+fn print_functions(core: &ShellCore, readable: bool) {
+    let mut names = keymap::FUNCTIONS.to_vec();
+    names.sort();
+    for name in names {
+        let seqs = sequences(core, &Binding::Function(name.to_string()));
+        match (readable, seqs.len()) {
+            (false, 0) => println!("# {} (not bound)", name),
+            (false, _) => seqs.iter().for_each(|s| println!("\"{}\": {}", s, name)),
+            (true, 0)  => println!("{} is not bound to any keys", name),
+            (true, _)  => {
+                let seqs: Vec<String> = seqs.iter().map(|s| format!("\"{}\"", s)).collect();
+                println!("{} can be found on {}.", name, seqs.join(", "));
+            },
+        }
+    }
+}
+
+/* macros (-s, -S) or shell commands (-X) */
+fn print_texts(core: &ShellCore, commands: bool, readable: bool) {
+    let mut lines = vec![];
+    for (keys, binding) in &core.keymap.bindings {
+        let seq = keymap::sequence_to_string(keys);
+        match (binding, readable) {
+            (Binding::Macro(t), false) if ! commands => lines.push(format!("\"{}\": \"{}\"", seq, quote_text(t))),
+            (Binding::Macro(t), true) if ! commands => lines.push(format!("{} outputs {}", seq, t)),
+            (Binding::Command(c), _) if commands => lines.push(format!("\"{}\": \"{}\"", seq, c)),
+            _ => {},
+        }
+    }
+    lines.sort();
+    lines.iter().for_each(|l| println!("{}", l));
+}
+
+fn print_variables(core: &mut ShellCore, readable: bool) {
+    let mode = match core.options.query("vi") {
+        true  => "vi",
+        false => "emacs",
+    };
+    core.keymap.set_variable("editing-mode", mode);
+
+    let mut names: Vec<&String> = core.keymap.variables.keys().collect();
+    names.sort();
+    for name in names {
+        let value = &core.keymap.variables[name];
+        match readable {
+            false => println!("set {} {}", name, value),
+            true  => println!("{} is set to `{}'", name, value),
+        }
+    }
+}
+
+fn query(core: &ShellCore, name: &str) -> i32 {
+    if ! keymap::FUNCTIONS.contains(&name) {
+        eprintln!("sush: bind: `{}': unknown function name", name);
+        return 1;
+    }
+
+    let seqs = sequences(core, &Binding::Function(name.to_string()));
+    if seqs.is_empty() {
+        println!("{} is not bound to any keys.", name);
+        return 1;
+    }
+    let seqs: Vec<String> = seqs.iter().map(|s| format!("\"{}\"", s)).collect();
+    println!("{} can be invoked via {}.", name, seqs.join(", "));
+    0
+}
+
+fn unbind_function(core: &mut ShellCore, name: &str) -> i32 {
+    if ! keymap::FUNCTIONS.contains(&name) {
+        eprintln!("sush: bind: `{}': unknown function name", name);
+        return 1;
+    }
+    let binding = Binding::Function(name.to_string());
+    core.keymap.bindings.retain(|_, b| *b != binding);
+    0
+}
+
+fn remove(core: &mut ShellCore, seq: &str) -> i32 {
+    match keymap::parse_sequence(seq) {
+        Some(keys) => {
+            core.keymap.bindings.remove(&keys);
+            0
+        },
+        None => {
+            eprintln!("sush: bind: {}: cannot parse the key sequence", seq);
+            1
+        },
+    }
+}
+
+/* bind -x '"seq": shell-command' */
+fn bind_command(core: &mut ShellCore, spec: &str) -> i32 {
+    let (keys, command) = match keymap::split_binding(spec) {
+        Ok(b) => b,
+        Err(msg) => {
+            eprintln!("sush: bind: {}", msg);
+            return 1;
+        },
+    };
+
+    let command = match command.chars().next() {
+        Some(q) if (q == '"' || q == '\'') && command.len() > 1 && command.ends_with(q) => {
+            command[1..command.len()-1].to_string()
+        },
+        _ => command,
+    };
+    core.keymap.bindings.insert(keys, Binding::Command(command));
+    0
+}
+
+fn read_binding(core: &mut ShellCore, line: &str) -> i32 {
+    if let Err(msg) = core.keymap.read_line(line) {
+        eprintln!("sush: bind: {}", msg);
+        return 1;
+    }
+
+    let words: Vec<&str> = line.split_whitespace().collect();
+    if words.len() < 2 || words[0] != "set" || words[1] != "editing-mode" {
+        return 0;
+    }
+    let mode = core.keymap.variables["editing-mode"].clone();
+    if mode == "vi" || mode == "emacs" {
+        core.options.set("vi", mode == "vi");
+        core.options.set("emacs", mode == "emacs");
+    }
+    0
+}
+
+fn read_file(core: &mut ShellCore, path: &str) -> i32 {
+    let mode = match core.options.query("vi") {
+        true  => "vi",
+        false => "emacs",
+    };
+    match core.keymap.read_file(path, mode, 0) {
+        Ok(()) => 0,
+        Err(msg) => {
+            eprintln!("sush: bind: {}", msg);
+            1
+        },
+    }
+}
+
+pub fn bind(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut pos = 1;
+    let mut ans = 0;
+    while pos < args.len() && args[pos].starts_with("-") && args[pos].len() > 1 {
+        if args[pos] == "--" {
+            pos += 1;
+            break;
+        }
+
+        let opt = args[pos].clone();
+        pos += 1;
+        for c in opt[1..].chars() {
+            if "fmqurx".contains(c) && pos >= args.len() {
+                eprintln!("sush: bind: -{}: option requires an argument", c);
+                return 2;
+            }
+            ans |= match c {
+                'l' => { keymap::FUNCTIONS.iter().for_each(|f| println!("{}", f)); 0 },
+                'p' | 'P' => { print_functions(core, c == 'P'); 0 },
+                's' | 'S' => { print_texts(core, false, c == 'S'); 0 },
+                'v' | 'V' => { print_variables(core, c == 'V'); 0 },
+                'X' => { print_texts(core, true, false); 0 },
+                'm' => { pos += 1; 0 }, //only one keymap
+                'f' => { pos += 1; read_file(core, &args[pos-1]) },
+                'q' => { pos += 1; query(core, &args[pos-1]) },
+                'u' => { pos += 1; unbind_function(core, &args[pos-1]) },
+                'r' => { pos += 1; remove(core, &args[pos-1]) },
+                'x' => { pos += 1; bind_command(core, &args[pos-1]) },
+                _ => {
+                    eprintln!("sush: bind: -{}: invalid option", c);
+                    eprintln!("bind: usage: bind [-lpsvPSVX] [-m keymap] [-f filename] [-q name] [-u name] [-r keyseq] [-x keyseq:shell-command] [keyseq:readline-function or readline-command]");
+                    return 2;
+                },
+            };
+        }
+    }
+
+    for line in &args[pos..].to_vec() {
+        ans |= read_binding(core, line);
+    }
+    ans
+}
@@ -2,7 +2,7 @@
 //SPDX-FileCopyrightText: 2023 @caro@mi.shellgei.org
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::ShellCore;
+use crate::{file_check, ShellCore};
 use super::utils;
 
 pub fn cd(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
@@ -50,7 +50,38 @@ fn set_oldpwd(core: &mut ShellCore) {
     };
 }
 
+/* a directory found in CDPATH, and whether it is printed after cd.
+ * an empty entry means the current directory */
+fn cdpath_directory(core: &mut ShellCore, dir: &str) -> Option<(String, bool)> {
+    let first = dir.split('/').next().unwrap_or("");
+    if dir.starts_with('/') || first == "." || first == ".." {
+        return None;
+    }
+
+    let cdpath = core.data.get_param("CDPATH");
+    if cdpath.is_empty() {
+        return None;
+    }
+    cdpath.split(':')
+        .map(|d| match d {
+            "" => ("./".to_string() + dir, false),
+            _  => (d.trim_end_matches('/').to_string() + "/" + dir, true),
+        })
+        .find(|(p, _)| file_check::is_dir(p))
+}
+
 fn change_directory(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if let Some((dir, print)) = cdpath_directory(core, &args[1]) {
+        let path = utils::make_canonical_path(core, &dir);
+        if core.set_current_directory(&path).is_ok() {
+            core.data.set_layer_param("PWD", &path.display().to_string(), 0);
+            if print {
+                println!("{}", path.display());
+            }
+            return 0;
+        }
+    }
+
     let path = utils::make_canonical_path(core, &args[1]);
     if core.set_current_directory(&path).is_ok() {
         core.data.set_layer_param("PWD", &path.display().to_string(), 0);
@@ -1,32 +1,57 @@
 //SPDX-FileCopyrightText: 2023 Ryuichi Ueda <ryuichiueda@gmail.com>
 //SPDX-License-Identifier: BSD-3-Clause
 
-use crate::{file_check, ShellCore, Feeder};
+use crate::{file_check, ShellCore, Feeder, Script};
+use crate::core::keymap;
 use crate::elements::word::Word;
 use crate::utils;
-use crate::utils::directory;
+use crate::utils::{directory, escape, glob};
 use faccess;
 use faccess::PathExt;
+use nix::sys::signal::Signal;
 use std::collections::HashSet;
+use std::fs;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::Path;
-use rev_lines::RevLines;
-
-pub fn compgen_f(core: &mut ShellCore, args: &mut Vec<String>) -> Vec<String> {
-    let path = match args.len() {
-        2 => "".to_string(),
-        _ => {
-            match args[2].as_str() {
-                "--" => args[3].to_string(),
-                _ => args[2].to_string(),
-            }
-        },
-    }.replace("\\", "");
 
+/* -o options in the order of complete -p */
+pub const OPTIONS: [&str; 8] = ["bashdefault", "default", "dirnames", "filenames",
+                                "noquote", "nosort", "nospace", "plusdirs"];
+
+/* -A actions and their single-letter options; history (-h) is sush original */
+const ACTIONS: [(&str, char); 25] = [
+    ("alias", 'a'), ("builtin", 'b'), ("command", 'c'), ("directory", 'd'),
+    ("export", 'e'), ("file", 'f'), ("group", 'g'), ("job", 'j'),
+    ("keyword", 'k'), ("service", 's'), ("user", 'u'), ("variable", 'v'),
+    ("history", 'h'), ("arrayvar", ' '), ("binding", ' '), ("disabled", ' '),
+    ("enabled", ' '), ("function", ' '), ("helptopic", ' '), ("hostname", ' '),
+    ("running", ' '), ("setopt", ' '), ("shopt", ' '), ("signal", ' '),
+    ("stopped", ' '),
+];
+
+const KEYWORDS: [&str; 22] = ["!", "[[", "]]", "case", "coproc", "do", "done", "elif",
+                              "else", "esac", "fi", "for", "function", "if", "in",
+                              "select", "then", "time", "until", "while", "{", "}"];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompSpec {
+    pub actions: Vec<String>,
+    pub options: Vec<String>,
+    pub glob: Option<String>,     // -G
+    pub wordlist: Option<String>, // -W
+    pub function: Option<String>, // -F
+    pub command: Option<String>,  // -C
+    pub filter: Option<String>,   // -X
+    pub prefix: String,           // -P
+    pub suffix: String,           // -S
+}
+
+pub fn compgen_f(core: &mut ShellCore, cur: &str) -> Vec<String> {
+    let path = cur.replace("\\", "");
     let mut split: Vec<String> = path.split("/").map(|s| s.to_string()).collect();
     let key = match split.pop() {
-        Some(g) => g, 
+        Some(g) => g,
         _       => return vec![],
     };
 
@@ -44,25 +69,7 @@ pub fn compgen_f(core: &mut ShellCore, args: &mut Vec<String>) -> Vec<String> {
     ans
 }
 
-fn replace_args(args: &mut Vec<String>) -> bool {
-    if args.len() < 3 || args[1] != "-A" {
-        return true;
-    }
-
-    args.remove(1);
-    let replace = match args[1].as_str() {
-        "command" => "-c",
-        "directory" => "-d",
-        "file" => "-f",
-        "user" => "-u",
-        a => a,
-    };
-
-    args[1] = replace.to_string();
-    true
-}
-
-fn command_list(target: &String, core: &mut ShellCore) -> Vec<String> {
+fn command_list(target: &str, core: &mut ShellCore) -> Vec<String> {
 
     let mut comlist = HashSet::new();
     for path in core.data.get_param("PATH").to_string().split(":") {
@@ -85,58 +92,10 @@ fn command_list(target: &String, core: &mut ShellCore) -> Vec<String> {
     ans
 }
 
-pub fn compgen(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() <= 1 {
-        eprintln!("sush: {}: still unsupported", &args[0]);
-        return 1;
-    }
-
-    replace_args(args);
-
-    let ans = match args[1].as_str() {
-        "-c" => compgen_c(core, args),
-        "-d" => compgen_d(core, args),
-        "-f" => compgen_f(core, args),
-        "-h" => compgen_h(core, args), //history (sush original)
-        "-u" => compgen_u(core, args),
-        "-W" => {
-            if args.len() < 2 {
-                eprintln!("sush: compgen: -W: option requires an argument");
-                return 2;
-            }
-            compgen_large_w(core, args)
-        },
-        _ => {
-            eprintln!("sush: compgen: {}: invalid option", &args[1]);
-            return 2;
-        },
-    };
-
-    ans.iter().for_each(|a| println!("{}", &a));
-    0
-}
-
-fn get_head(args: &mut Vec<String>, pos: usize) -> String {
-    if args.len() > pos && args[pos] != "--" {
-        args[pos].clone()
-    }else if args.len() > pos+1 {
-        args[pos+1].clone()
-    }else{
-        "".to_string()
-    }
-}
-
-fn drop_unmatch(args: &mut Vec<String>, pos: usize, list: &mut Vec<String>) {
-    let head = get_head(args, pos);
-    if head != "" {
-        list.retain(|s| s.starts_with(&head));
-    }
-}
-
-pub fn compgen_c(core: &mut ShellCore, args: &mut Vec<String>) -> Vec<String> {
+pub fn compgen_c(core: &mut ShellCore, cur: &str) -> Vec<String> {
     let mut commands = vec![];
-    if args.len() > 2 {
-        commands.extend(compgen_f(core, args));
+    if ! cur.is_empty() {
+        commands.extend(compgen_f(core, cur));
     }
     commands.retain(|p| Path::new(p).executable() || file_check::is_dir(p));
 
@@ -147,93 +106,465 @@ pub fn compgen_c(core: &mut ShellCore, args: &mut Vec<String>) -> Vec<String> {
     let mut functions: Vec<String> = core.data.functions.clone().into_keys().collect();
     commands.append(&mut functions);
 
-    let head = get_head(args, 2);
-    if head != "" {
-        commands.retain(|a| a.starts_with(&head));
+    if ! cur.is_empty() {
+        commands.retain(|a| a.starts_with(cur));
     }
-    let mut command_in_paths = command_list(&head, core);
+    let mut command_in_paths = command_list(cur, core);
     commands.append(&mut command_in_paths);
     commands
 }
 
-fn compgen_d(core: &mut ShellCore, args: &mut Vec<String>) -> Vec<String> {
-    let mut paths = compgen_f(core, args);
+pub fn compgen_d(core: &mut ShellCore, cur: &str) -> Vec<String> {
+    let mut paths = compgen_f(core, cur);
     paths.retain(|p| file_check::is_dir(&p));
     paths
 }
 
-pub fn compgen_h(core: &mut ShellCore, _: &mut Vec<String>) -> Vec<String> {
-    let len = core.history.len();
-    if len >= 10 {
-        return core.history[0..10].to_vec();
-    }
-
-    let mut ans = core.history.to_vec();
-
-    if let Ok(hist_file) = File::open(core.data.get_param("HISTFILE")){
-        for h in RevLines::new(BufReader::new(hist_file)) {
-            match h {
-                Ok(s) => ans.push(s),
-                _     => {},
-            }
-
-            if ans.len() >= 10 {
-                return ans;
-            }
-        }
-    }
-
+pub fn compgen_h(core: &mut ShellCore) -> Vec<String> {
+    let mut ans: Vec<String> = core.history.iter().take(10).map(|h| h.line.clone()).collect();
     while ans.len() < 10 {
         ans.push("echo Hello World".to_string());
     }
     ans
 }
 
-fn compgen_large_w(core: &mut ShellCore, args: &mut Vec<String>) -> Vec<String> {
+fn compgen_large_w(core: &mut ShellCore, wordlist: &str, cur: &str) -> Vec<String> {
     let mut ans: Vec<String> = vec![];
-    let mut feeder = Feeder::new(&args[2]);
+    let mut feeder = Feeder::new(wordlist);
     while feeder.len() != 0 {
         match Word::parse(&mut feeder, core, false) {
-            Some(mut w) => {
-                w.make_unquoted_word();
-                ans.push(w.text)
-            },
+            Some(mut w) => ans.extend(w.eval(core).unwrap_or_default()),
             _ => {
                 let len = feeder.scanner_multiline_blank(core);
                 feeder.consume(len);
+                if len == 0 {
+                    break;
+                }
             },
         }
     }
 
-    drop_unmatch(args, 3, &mut ans);
+    ans.retain(|w| w.starts_with(cur));
     ans
 }
 
-fn compgen_u(_: &mut ShellCore, args: &mut Vec<String>) -> Vec<String> {
+/* the first fields of lines in /etc/passwd, /etc/group, /etc/services and so on */
+fn first_fields(file: &str, sep: char) -> Vec<String> {
     let mut ans = vec![];
 
-    if let Ok(f) = File::open("/etc/passwd") {
-        for line in BufReader::new(f).lines() {
-            match line {
-                Ok(line) => {
-                    let splits: Vec<&str> = line.split(':').collect();
-                    ans.push(splits[0].to_string());
+    if let Ok(f) = File::open(file) {
+        for line in BufReader::new(f).lines().map_while(Result::ok) {
+            let line = line.split('#').next().unwrap_or("").trim().to_string();
+            match line.split(sep).next() {
+                Some(w) if ! w.is_empty() => ans.push(w.to_string()),
+                _ => {},
+            }
+        }
+    }
+    ans
+}
+
+fn hostnames(core: &mut ShellCore) -> Vec<String> {
+    let file = match core.data.get_param("HOSTFILE") {
+        f if ! f.is_empty() => f,
+        _ => "/etc/hosts".to_string(),
+    };
+    let text = fs::read_to_string(file).unwrap_or_default();
+    text.lines()
+        .map(|l| l.split('#').next().unwrap_or(""))
+        .flat_map(|l| l.split_whitespace().skip(1).map(|s| s.to_string()).collect::<Vec<String>>())
+        .collect()
+}
+
+pub fn action_list(core: &mut ShellCore, action: &str, cur: &str) -> Vec<String> {
+    let mut ans: Vec<String> = match action {
+        "command"   => return compgen_c(core, cur),
+        "directory" => return compgen_d(core, cur),
+        "file"      => return compgen_f(core, cur),
+        "history"   => return compgen_h(core),
+        "alias"     => core.data.aliases.keys().cloned().collect(),
+        "arrayvar"  => core.data.get_keys().into_iter()
+                       .filter(|k| core.data.is_array(k))
+                       .collect(),
+        "binding"   => keymap::FUNCTIONS.iter().map(|f| f.to_string()).collect(),
+        "builtin" | "enabled" | "helptopic" => core.builtins.keys().cloned().collect(),
+        "export"    => core.data.get_exported_params().into_iter().map(|p| p.0).collect(),
+        "function"  => core.data.functions.keys().cloned().collect(),
+        "group"     => first_fields("/etc/group", ':'),
+        "hostname"  => hostnames(core),
+        "job"       => core.job_table.iter().map(|j| j.text.clone()).collect(),
+        "running"   => core.job_table.iter().filter(|j| ! j.is_stopped())
+                       .map(|j| j.text.clone()).collect(),
+        "stopped"   => core.job_table.iter().filter(|j| j.is_stopped())
+                       .map(|j| j.text.clone()).collect(),
+        "keyword"   => KEYWORDS.iter().map(|k| k.to_string()).collect(),
+        "service"   => first_fields("/etc/services", '\t')
+                       .iter().flat_map(|s| s.split_whitespace().next().map(|s| s.to_string()))
+                       .collect(),
+        "setopt"    => core.options.get_keys(),
+        "shopt"     => core.shopts.get_keys(),
+        "signal"    => Signal::iterator().map(|s| s.as_str().to_string()).collect(),
+        "user"      => first_fields("/etc/passwd", ':'),
+        "variable"  => core.data.get_keys(),
+        _ => vec![],
+    };
+
+    ans.retain(|a| a.starts_with(cur));
+    ans.sort();
+    ans.dedup();
+    ans
+}
+
+/* runs -F function with $1 $2 $3 and returns its exit status */
+fn call_function(core: &mut ShellCore, func: &str, args: &[String]) -> i32 {
+    core.data.set_array("COMPREPLY", &vec![]);
+    let mut command = func.to_string();
+    for a in args {
+        command += &(" ".to_owned() + &escape::single_quote(a));
+    }
+
+    let mut feeder = Feeder::new(&command);
+    if let Some(mut s) = Script::parse(&mut feeder, core, false) {
+        s.exec(core);
+    }
+    core.data.get_param("?").parse::<i32>().unwrap_or(1)
+}
+
+/* runs -C command with $1 $2 $3 and returns the lines of the output */
+fn call_command(core: &mut ShellCore, command: &str, args: &[String]) -> Vec<String> {
+    let mut command = command.to_string();
+    for a in args {
+        command += &(" ".to_owned() + &escape::single_quote(a));
+    }
+
+    let mut feeder = Feeder::new(&format!("\"$({})\"", command));
+    let ans = match Word::parse(&mut feeder, core, false) {
+        Some(w) => w.eval_as_value(core).unwrap_or_default(),
+        None => String::new(),
+    };
+    ans.lines().filter(|l| ! l.is_empty()).map(|l| l.to_string()).collect()
+}
+
+/* -X: & in the pattern is the word, ! at the head inverts the pattern */
+fn apply_filter(core: &mut ShellCore, list: &mut Vec<String>, filter: &str, cur: &str) {
+    let (invert, pattern) = match filter.strip_prefix("!") {
+        Some(p) => (true, p),
+        None => (false, filter),
+    };
+    let pattern = pattern.replace("\\&", "\x01").replace("&", cur).replace("\x01", "&");
+    let extglob = core.shopts.query("extglob");
+    list.retain(|c| glob::compare(c, &pattern, extglob) == invert);
+}
+
+/* candidates for the word cur; args are $1 $2 $3 of -F and -C.
+ * None: the function returned 124 to retry with a new compspec */
+pub fn generate(core: &mut ShellCore, spec: &CompSpec, cur: &str, args: &[String]) -> Option<Vec<String>> {
+    let mut ans = vec![];
+    for action in &spec.actions {
+        ans.extend(action_list(core, action, cur));
+    }
+
+    if let Some(g) = &spec.glob {
+        let (dir, key) = match g.rfind('/') {
+            Some(p) => (g[..p+1].to_string(), g[p+1..].to_string()),
+            None => (String::new(), g.to_string()),
+        };
+        let mut paths = directory::glob(&dir, &key, core.shopts.query("extglob"));
+        paths.iter_mut().for_each(|p| { p.pop(); });
+        paths.retain(|p| ! p.ends_with("/.") && ! p.ends_with("/..") && p != "." && p != "..");
+        paths.sort();
+        ans.extend(paths);
+    }
+    if let Some(w) = &spec.wordlist {
+        ans.extend(compgen_large_w(core, w, cur));
+    }
+    if let Some(f) = &spec.function {
+        if call_function(core, f, args) == 124 {
+            return None;
+        }
+        ans.extend(core.data.get_array_all("COMPREPLY"));
+    }
+    if let Some(c) = &spec.command {
+        ans.extend(call_command(core, c, args));
+    }
+
+    if let Some(f) = &spec.filter {
+        apply_filter(core, &mut ans, f, cur);
+    }
+    for a in ans.iter_mut() {
+        *a = format!("{}{}{}", spec.prefix, a, spec.suffix);
+    }
+
+    let options = match &core.completion_options {
+        Some(opts) => opts.clone(),
+        None => spec.options.clone(),
+    };
+    let has = |o: &str| options.iter().any(|x| x == o);
+    if ans.is_empty() && has("dirnames") {
+        ans = compgen_d(core, cur);
+    }
+    if has("plusdirs") {
+        ans.extend(compgen_d(core, cur));
+    }
+    Some(ans)
+}
+
+fn action_name(opt: char) -> Option<&'static str> {
+    ACTIONS.iter().find(|a| a.1 == opt).map(|a| a.0)
+}
+
+/* parses the options of complete and compgen. flags: p, r, D, E, I */
+fn parse_spec(name: &str, args: &[String], spec: &mut CompSpec,
+              flags: &mut String) -> Result<usize, i32> {
+    let mut pos = 1;
+    while pos < args.len() && args[pos].starts_with("-") && args[pos].len() > 1 {
+        if args[pos] == "--" {
+            return Ok(pos + 1);
+        }
+
+        let opt: Vec<char> = args[pos].chars().collect();
+        pos += 1;
+        for i in 1..opt.len() {
+            let c = opt[i];
+            if let Some(a) = action_name(c) {
+                spec.actions.push(a.to_string());
+                continue;
+            }
+            if "prDEI".contains(c) {
+                flags.push(c);
+                continue;
+            }
+            if ! "AoGWFCXPS".contains(c) {
+                eprintln!("sush: {}: -{}: invalid option", name, c);
+                eprintln!("{}: usage: {} [-abcdefgjksuv] [-pr] [-DEI] [-o option] [-A action] [-G globpat] [-W wordlist] [-F function] [-C command] [-X filterpat] [-P prefix] [-S suffix] [name ...]", name, name);
+                return Err(2);
+            }
+
+            let arg = match i + 1 < opt.len() {
+                true  => opt[i+1..].iter().collect::<String>(),
+                false => {
+                    if pos >= args.len() {
+                        eprintln!("sush: {}: -{}: option requires an argument", name, c);
+                        return Err(2);
+                    }
+                    pos += 1;
+                    args[pos-1].clone()
+                },
+            };
+
+            match c {
+                'A' if ACTIONS.iter().any(|a| a.0 == arg) => spec.actions.push(arg),
+                'A' => {
+                    eprintln!("sush: {}: {}: invalid action name", name, arg);
+                    return Err(2);
                 },
-                _ => return vec![],
+                'o' if OPTIONS.contains(&arg.as_str()) => spec.options.push(arg),
+                'o' => {
+                    eprintln!("sush: {}: {}: invalid option name", name, arg);
+                    return Err(2);
+                },
+                'G' => spec.glob = Some(arg),
+                'W' => spec.wordlist = Some(arg),
+                'F' => spec.function = Some(arg),
+                'C' => spec.command = Some(arg),
+                'X' => spec.filter = Some(arg),
+                'P' => spec.prefix = arg,
+                _   => spec.suffix = arg,
             }
+            break;
+        }
+    }
+    Ok(pos)
+}
+
+fn print_spec(name: &str, spec: &CompSpec) {
+    let mut words = vec!["complete".to_string()];
+    for opt in OPTIONS.iter().filter(|o| spec.options.iter().any(|x| x == *o)) {
+        words.push(format!("-o {}", opt));
+    }
+    for (action, c) in ACTIONS.iter().filter(|a| spec.actions.iter().any(|x| x == a.0)) {
+        match c {
+            ' ' => words.push(format!("-A {}", action)),
+            c => words.push(format!("-{}", c)),
+        }
+    }
+
+    let quoted = [("-G", &spec.glob), ("-W", &spec.wordlist),
+                  ("-P", &Some(spec.prefix.clone()).filter(|p| ! p.is_empty())),
+                  ("-S", &Some(spec.suffix.clone()).filter(|s| ! s.is_empty())),
+                  ("-X", &spec.filter), ("-C", &spec.command)];
+    for (opt, arg) in quoted {
+        if let Some(a) = arg {
+            words.push(format!("{} {}", opt, escape::single_quote(a)));
         }
     }
+    if let Some(f) = &spec.function {
+        words.push(format!("-F {}", f));
+    }
+
+    words.push(name.to_string());
+    println!("{}", words.join(" "));
+}
 
-    drop_unmatch(args, 2, &mut ans);
+/* the names given by -D, -E, -I or arguments */
+fn target_names(flags: &str, names: &[String]) -> Vec<String> {
+    let mut ans: Vec<String> = "DEI".chars().filter(|c| flags.contains(*c))
+                               .map(|c| format!("-{}", c)).collect();
+    ans.extend(names.iter().cloned());
     ans
 }
 
-pub fn complete(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    if args.len() < 4 || args[1] != "-F" {
+pub fn compgen(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() <= 1 {
         eprintln!("sush: {}: still unsupported", &args[0]);
         return 1;
     }
 
-    core.completion_functions.insert(args[3].clone(), args[2].clone());
+    let mut spec = CompSpec::default();
+    let pos = match parse_spec("compgen", args, &mut spec, &mut String::new()) {
+        Ok(p) => p,
+        Err(n) => return n,
+    };
+    let cur = args.get(pos).cloned().unwrap_or_default();
+
+    let args = ["".to_string(), cur.clone(), "".to_string()];
+    let mut ans = generate(core, &spec, &cur, &args).unwrap_or_default();
+    let default = spec.options.iter().any(|o| o == "default" || o == "bashdefault");
+    if ans.is_empty() && default {
+        ans = compgen_f(core, &cur);
+    }
+
+    ans.iter().for_each(|a| println!("{}", &a));
+    match ans.len() {
+        0 => 1,
+        _ => 0,
+    }
+}
+
+pub fn complete(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut spec = CompSpec::default();
+    let mut flags = String::new();
+    let pos = match parse_spec("complete", args, &mut spec, &mut flags) {
+        Ok(p) => p,
+        Err(n) => return n,
+    };
+    let names = target_names(&flags, &args[pos..]);
+
+    if flags.contains('r') {
+        if names.is_empty() {
+            core.completion_specs.clear();
+        }
+        for name in &names {
+            core.completion_specs.remove(name);
+        }
+        return 0;
+    }
+
+    if flags.contains('p') || (names.is_empty() && spec == CompSpec::default()) {
+        let mut keys: Vec<String> = match names.len() {
+            0 => core.completion_specs.keys().cloned().collect(),
+            _ => names,
+        };
+        keys.sort();
+
+        let mut ans = 0;
+        for name in keys {
+            match core.completion_specs.get(&name) {
+                Some(spec) => print_spec(&name, spec),
+                None => {
+                    eprintln!("sush: complete: {}: no completion specification", name);
+                    ans = 1;
+                },
+            }
+        }
+        return ans;
+    }
+
+    if names.is_empty() {
+        eprintln!("sush: complete: no names given");
+        return 2;
+    }
+    for name in names {
+        core.completion_specs.insert(name, spec.clone());
+    }
     0
 }
+
+fn print_options(name: &str, options: &[String]) {
+    let opts: Vec<String> = OPTIONS.iter().map(|o| match options.iter().any(|x| x == o) {
+        true  => format!("-o {}", o),
+        false => format!("+o {}", o),
+    }).collect();
+    println!("{}", format!("compopt {} {}", opts.join(" "), name).trim_end());
+}
+
+fn change_options(options: &mut Vec<String>, changes: &[(bool, String)]) {
+    for (on, opt) in changes {
+        options.retain(|o| o != opt);
+        if *on {
+            options.push(opt.clone());
+        }
+    }
+}
+
+pub fn compopt(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut changes = vec![];
+    let mut flags = String::new();
+    let mut pos = 1;
+    while pos < args.len() && (args[pos].starts_with("-") || args[pos].starts_with("+")) {
+        let arg = args[pos].clone();
+        pos += 1;
+        if arg == "--" {
+            break;
+        }
+        if arg == "-o" || arg == "+o" {
+            match args.get(pos) {
+                Some(o) if OPTIONS.contains(&o.as_str()) => changes.push((arg == "-o", o.clone())),
+                Some(o) => {
+                    eprintln!("sush: compopt: {}: invalid option name", o);
+                    return 2;
+                },
+                None => {
+                    eprintln!("sush: compopt: {}: option requires an argument", arg);
+                    return 2;
+                },
+            }
+            pos += 1;
+        }else if arg.len() > 1 && arg[1..].chars().all(|c| "DEI".contains(c)) && arg.starts_with("-") {
+            flags += &arg[1..];
+        }else{
+            eprintln!("sush: compopt: {}: invalid option", arg);
+            eprintln!("compopt: usage: compopt [-o|+o option] [-DEI] [name ...]");
+            return 2;
+        }
+    }
+
+    let names = target_names(&flags, &args[pos..]);
+    if names.is_empty() {
+        let options = match core.completion_options.as_mut() {
+            Some(o) => o,
+            None => {
+                eprintln!("sush: compopt: not currently executing completion function");
+                return 1;
+            },
+        };
+        match changes.len() {
+            0 => print_options("", options),
+            _ => change_options(options, &changes),
+        }
+        return 0;
+    }
+
+    let mut ans = 0;
+    for name in names {
+        match core.completion_specs.get_mut(&name) {
+            Some(spec) if changes.is_empty() => print_options(&name, &spec.options),
+            Some(spec) => change_options(&mut spec.options, &changes),
+            None => {
+                eprintln!("sush: compopt: {}: no completion specification", name);
+                ans = 1;
+            },
+        }
+    }
+    ans
+}
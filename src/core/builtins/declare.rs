@@ -0,0 +1,323 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{ShellCore, Feeder};
+use crate::core::data::Value;
+use crate::elements::command::Command;
+use crate::elements::substitution::Substitution;
+use crate::utils::escape::double_quote;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+
+const ATTRIBUTES: &str = "aAilnrux";
+
+fn quote_key(key: &str) -> String {
+    match ! key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        true  => key.to_string(),
+        false => double_quote(key),
+    }
+}
+
+pub fn declaration(name: &str, core: &mut ShellCore) -> Option<String> {
+    let flags = core.data.get_attribute_flags(name);
+    let flags = match flags.as_str() {
+        "" => "-".to_string(),
+        f  => f.to_string(),
+    };
+
+    let value = match core.data.get_value(name) {
+        Some(Value::EvaluatedSingle(v)) => double_quote(&v),
+        Some(Value::EvaluatedArray(a)) => {
+            let elems: Vec<String> = a.iter()
+                                      .map(|(i, v)| format!("[{}]={}", i, double_quote(v)))
+                                      .collect();
+            format!("({})", elems.join(" "))
+        },
+        Some(Value::AssocArray(a)) if a.is_empty() => {
+            return Some(format!("declare -{} {}", flags, name));
+        },
+        Some(Value::AssocArray(a)) => {
+            let mut elems: Vec<String> = a.iter()
+                                          .map(|(k, v)| format!("[{}]={} ", quote_key(k), double_quote(v)))
+                                          .collect();
+            elems.sort();
+            format!("({})", elems.concat())
+        },
+        _ if core.data.has_value(name) => double_quote(&core.data.get_param(name)),
+        _ => match core.data.get_attributes(name).is_empty() {
+            true  => return None,
+            false => return Some(format!("declare -{} {}", flags, name)),
+        },
+    };
+
+    Some(format!("declare -{} {}={}", flags, name, value))
+}
+
+fn print_vars(core: &mut ShellCore, names: &[String], attrs: &str) -> i32 {
+    let is_name = |n: &String| ! n.starts_with(|c: char| c.is_ascii_digit())
+                               && n.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let names = match names.len() {
+        0 => core.data.get_keys().into_iter().filter(is_name).collect(),
+        _ => names.to_vec(),
+    };
+
+    let mut ans = 0;
+    for name in names {
+        let flags = core.data.get_attribute_flags(&name);
+        if ! attrs.chars().all(|a| flags.contains(a)) {
+            continue;
+        }
+
+        match declaration(&name, core) {
+            Some(d) => println!("{}", d),
+            None => {
+                eprintln!("sush: declare: {}: not found", name);
+                ans = 1;
+            },
+        }
+    }
+    ans
+}
+
+fn print_functions(core: &mut ShellCore, names: &[String], only_names: bool) -> i32 {
+    let mut names = match names.len() {
+        0 => core.data.functions.keys().cloned().collect(),
+        _ => names.to_vec(),
+    };
+    names.sort();
+
+    let mut ans = 0;
+    for name in names {
+        match core.data.functions.get(&name) {
+            Some(_) if only_names => println!("declare -f {}", name),
+            Some(f) => println!("{}", f.get_text().trim_end()),
+            None    => ans = 1,
+        }
+    }
+    ans
+}
+
+fn set_attributes(core: &mut ShellCore, name: &str, attrs: &str, layer: usize) -> bool {
+    if attrs.contains('A') && ! core.data.is_assoc(name) {
+        if core.data.is_array(name) {
+            eprintln!("sush: declare: {}: cannot convert indexed to associative array", name);
+            return false;
+        }
+        if ! core.data.set_layer_assoc(name, &HashMap::new(), layer) {
+            return false;
+        }
+    }else if attrs.contains('a') && core.data.is_assoc(name) {
+        eprintln!("sush: declare: {}: cannot convert associative to indexed array", name);
+        return false;
+    }else if attrs.contains('a') && ! core.data.is_array(name) {
+        let value = match core.data.has_value(name) {
+            true  => vec![core.data.get_param(name)],
+            false => vec![],
+        };
+        if ! core.data.set_layer_array(name, &value, layer) {
+            return false;
+        }
+    }
+
+    let attrs: String = attrs.chars().filter(|a| "ilux".contains(*a)).collect();
+    core.data.set_layer_attributes(name, &attrs, layer);
+    true
+}
+
+fn declare_var(core: &mut ShellCore, cmd: &str, arg: &str, attrs: &str,
+               remove: &str, layer: usize) -> bool {
+    let mut sub = match arg.find('=') {
+        Some(_) => match Substitution::parse(&mut Feeder::new(arg), core) {
+            Some(s) => Some(s),
+            None => {
+                eprintln!("sush: {}: `{}': not a valid identifier", cmd, arg);
+                return false;
+            },
+        },
+        None => None,
+    };
+
+    let name = match sub.as_ref() {
+        Some(s) => s.key.clone(),
+        None    => arg.to_string(),
+    };
+
+    let changes_value = sub.is_some() || attrs.chars().any(|a| ! "rx".contains(a)) || remove.contains('r');
+    if core.data.has_attribute(&name, 'r') && changes_value {
+        eprintln!("sush: {}: {}: readonly variable", cmd, name);
+        return false;
+    }
+
+    if attrs.contains('n') || remove.contains('n') {
+        core.data.unset_layer_attributes(&name, "n", layer);
+    }
+    core.data.unset_layer_attributes(&name, remove, layer);
+    if ! set_attributes(core, &name, attrs, layer) {
+        return false;
+    }
+
+    if let Some(s) = sub.as_mut() {
+        let value = match (s.eval(core), attrs.contains('a')) {
+            (Value::None, _) => return false,
+            (Value::EvaluatedSingle(v), true) if s.index.is_none() => {
+                Value::EvaluatedArray(BTreeMap::from([(0, v)]))
+            },
+            (v, _) => v,
+        };
+        if ! core.data.set_value(&name, s.index.as_deref(), &value, Some(layer)) {
+            return false;
+        }
+    }
+
+    let attrs: String = attrs.chars().filter(|a| "nr".contains(*a)).collect();
+    core.data.set_layer_attributes(&name, &attrs, layer);
+    true
+}
+
+pub fn declare_vars(core: &mut ShellCore, args: &[String], layer: usize) -> i32 {
+    let mut attrs = String::new();
+    let mut remove = String::new();
+    let mut names = vec![];
+    let mut end_of_options = false;
+
+    for arg in args {
+        if end_of_options || arg.contains('=') || ! (arg.starts_with("-") || arg.starts_with("+")) {
+            names.push(arg.clone());
+            end_of_options = true;
+            continue;
+        }
+        if arg == "--" {
+            end_of_options = true;
+            continue;
+        }
+
+        for c in arg[1..].chars() {
+            if ATTRIBUTES.find(c).is_none() && "fFpg".find(c).is_none() {
+                eprintln!("sush: declare: -{}: invalid option", c);
+                eprintln!("declare: usage: declare [-aAfFgilnrux] [-p] [name[=value] ...]");
+                return 2;
+            }
+            match arg.starts_with("-") {
+                true  => attrs.push(c),
+                false => remove.push(c),
+            }
+        }
+    }
+
+    if attrs.contains('f') || attrs.contains('F') {
+        return print_functions(core, &names, attrs.contains('F'));
+    }
+
+    let print = attrs.contains('p');
+    let attrs: String = attrs.chars().filter(|a| ATTRIBUTES.contains(*a)).collect();
+    if names.is_empty() || print {
+        return print_vars(core, &names, &attrs);
+    }
+
+    match names.iter().all(|n| declare_var(core, "declare", n, &attrs, &remove, layer)) {
+        true  => 0,
+        false => 1,
+    }
+}
+
+pub fn declare(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let global = args[1..].iter()
+                 .take_while(|a| a.starts_with("-") && *a != "--" && ! a.contains('='))
+                 .any(|a| a.contains('g'));
+
+    let layer = match core.data.function_layers.last() {
+        Some(n) if ! global => *n,
+        _ => 0,
+    };
+
+    declare_vars(core, &args[1..], layer)
+}
+
+fn parse_options(cmd: &str, args: &[String], options: &str,
+                 attrs: &mut String, remove: &mut String) -> Result<Vec<String>, i32> {
+    let mut names = vec![];
+    let mut end_of_options = false;
+    for arg in args {
+        if end_of_options || arg.contains('=') || ! (arg.starts_with("-") || arg.starts_with("+")) {
+            names.push(arg.clone());
+            end_of_options = true;
+            continue;
+        }
+        if arg == "--" {
+            end_of_options = true;
+            continue;
+        }
+
+        for c in arg[1..].chars() {
+            if options.find(c).is_none() {
+                eprintln!("sush: {}: -{}: invalid option", cmd, c);
+                eprintln!("{0}: usage: {0} [-{1}] [name[=value] ...]", cmd, options);
+                return Err(2);
+            }
+            match arg.starts_with("-") {
+                true  => attrs.push(c),
+                false => remove.push(c),
+            }
+        }
+    }
+
+    Ok(names)
+}
+
+/* export and readonly work on the layer where the variable already exists */
+fn declare_vars_in_place(core: &mut ShellCore, cmd: &str, names: &[String],
+                         attrs: &str, remove: &str) -> i32 {
+    let top = core.data.get_layer_num() - 1; //for the builtin itself
+    let mut ans = 0;
+    for name in names {
+        let key = match name.find(['=', '[']) {
+            Some(n) => &name[..n],
+            None    => name,
+        };
+        let layer = match core.data.get_layer(key) {
+            Some(n) if n < top => n,
+            _ => 0,
+        };
+
+        if remove.contains('x') {
+            core.data.get_param(key); //keeps the value inherited from the environment
+            env::remove_var(key);
+        }
+        if ! declare_var(core, cmd, name, attrs, remove, layer) {
+            ans = 1;
+        }
+    }
+    ans
+}
+
+pub fn export(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let (mut opts, mut remove) = (String::new(), String::new());
+    let names = match parse_options("export", &args[1..], "np", &mut opts, &mut remove) {
+        Ok(names) => names,
+        Err(n)    => return n,
+    };
+
+    if names.is_empty() || (opts.contains('p') && ! opts.contains('n')) {
+        return print_vars(core, &names, "x");
+    }
+
+    match opts.contains('n') {
+        true  => declare_vars_in_place(core, "export", &names, "", "x"),
+        false => declare_vars_in_place(core, "export", &names, "x", ""),
+    }
+}
+
+pub fn readonly(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let (mut opts, mut remove) = (String::new(), String::new());
+    let names = match parse_options("readonly", &args[1..], "aAp", &mut opts, &mut remove) {
+        Ok(names) => names,
+        Err(n)    => return n,
+    };
+
+    let attrs: String = opts.chars().filter(|c| "aA".contains(*c)).chain("r".chars()).collect();
+    if names.is_empty() || opts.contains('p') {
+        return print_vars(core, &names, &attrs);
+    }
+
+    declare_vars_in_place(core, "readonly", &names, &attrs, "")
+}
@@ -0,0 +1,39 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::ShellCore;
+use crate::utils::escape;
+use super::utils;
+
+pub fn echo(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut escape = core.shopts.query("xpg_echo");
+    let mut newline = true;
+
+    let mut pos = 1;
+    while pos < args.len() && args[pos].len() > 1 && args[pos].starts_with("-")
+    && args[pos][1..].chars().all(|c| "neE".contains(c)) {
+        for c in args[pos][1..].chars() {
+            match c {
+                'n' => newline = false,
+                'e' => escape = true,
+                _   => escape = false,
+            }
+        }
+        pos += 1;
+    }
+
+    let mut output = args[pos..].join(" ");
+    if escape {
+        let (s, cut) = escape::expand(&output, escape::Mode::Echo);
+        output = s;
+        newline &= ! cut;
+    }
+    if newline {
+        output.push('\n');
+    }
+
+    match utils::write_stdout("echo", &output) {
+        true  => 0,
+        false => 1,
+    }
+}
@@ -0,0 +1,103 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{error_message, ShellCore};
+use nix::errno::Errno;
+use nix::sys::signal;
+use nix::sys::signal::{SigHandler, Signal};
+use nix::unistd;
+use std::env;
+use std::ffi::CString;
+
+fn set_environment_variables(core: &mut ShellCore, clear: bool) {
+    if clear {
+        for (k, _) in env::vars_os() {
+            env::remove_var(k);
+        }
+    }
+
+    for (k, v) in core.data.get_exported_params() {
+        env::set_var(&k, &v);
+    }
+
+    let layer = core.data.get_layer_num() - 1; //assignments before exec
+    for (k, v) in core.data.get_layer_params(layer) {
+        env::set_var(&k, &v);
+    }
+}
+
+/* signals ignored by the shell stay ignored over execve */
+fn set_ignored_signals(handler: SigHandler) {
+    for sig in [Signal::SIGPIPE, Signal::SIGTSTP] {
+        unsafe { signal::signal(sig, handler) }
+            .expect("sush(fatal): cannot set signal handler");
+    }
+}
+
+fn failure(core: &mut ShellCore, msg: &str, status: i32) -> i32 {
+    error_message::print(&format!("exec: {}", msg), core, true);
+    if core.read_stdin {
+        core.data.set_layer_param("?", &status.to_string(), 0);
+        core.exit();
+    }
+    status
+}
+
+pub fn exec(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut argv0 = None;
+    let mut clear = false;
+    let mut login = false;
+
+    let mut pos = 1;
+    while pos < args.len() && args[pos].starts_with("-") {
+        match args[pos].as_str() {
+            "--" => { pos += 1; break; },
+            "-a" if pos + 1 < args.len() => {
+                argv0 = Some(args[pos+1].clone());
+                pos += 1;
+            },
+            "-c" => clear = true,
+            "-l" => login = true,
+            opt => {
+                eprintln!("sush: exec: {}: invalid option", opt);
+                eprintln!("exec: usage: exec [-cl] [-a name] [command [argument ...]] [redirection ...]");
+                return 2;
+            },
+        }
+        pos += 1;
+    }
+
+    if pos >= args.len() { //only redirections, which are applied by SimpleCommand
+        return 0;
+    }
+
+    let mut cargs: Vec<String> = args[pos..].to_vec();
+    let command = cargs[0].clone();
+    if let Some(name) = argv0 {
+        cargs[0] = name;
+    }
+    if login {
+        cargs[0] = format!("-{}", cargs[0]);
+    }
+
+    let cargs: Vec<CString> = match cargs.iter().map(|a| CString::new(a.as_str())).collect() {
+        Ok(c) => c,
+        Err(_) => return failure(core, &format!("{}: invalid argument", &command), 126),
+    };
+    let cmd = match CString::new(command.as_str()) {
+        Ok(c) => c,
+        Err(_) => return failure(core, &format!("{}: invalid argument", &command), 126),
+    };
+
+    set_environment_variables(core, clear);
+    set_ignored_signals(SigHandler::SigDfl);
+    let err = unistd::execvp(&cmd, &cargs);
+    set_ignored_signals(SigHandler::SigIgn);
+
+    match err {
+        Err(Errno::EACCES) => failure(core, &format!("{}: Permission denied", &command), 126),
+        Err(Errno::ENOENT) => failure(core, &format!("{}: not found", &command), 127),
+        Err(e) => failure(core, &format!("{}: {}", &command, e.desc()), 126),
+        _ => error_message::internal("never come here"),
+    }
+}
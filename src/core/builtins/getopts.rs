@@ -0,0 +1,108 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::ShellCore;
+
+fn print_error(core: &mut ShellCore, silent: bool, msg: &str, opt: char) {
+    if silent || core.data.get_param("OPTERR") == "0" {
+        return;
+    }
+    eprintln!("{}: {} -- {}", core.data.get_param("0"), msg, opt);
+}
+
+fn finish(core: &mut ShellCore, name: &str, optind: usize) -> i32 {
+    core.data.set_param(name, "?");
+    core.data.set_param("OPTIND", &optind.to_string());
+    core.getopts_position = (0, 0, String::new());
+    1
+}
+
+pub fn getopts(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.len() < 3 {
+        eprintln!("sush: getopts: usage: getopts optstring name [arg ...]");
+        return 2;
+    }
+
+    let optstring = args[1].clone();
+    let name = args[2].clone();
+    let targets = match args.len() {
+        3 => core.data.get_position_params(),
+        _ => args[3..].to_vec(),
+    };
+
+    let mut optind = match core.data.get_param("OPTIND").parse::<usize>() {
+        Ok(n) if n > 0 => n,
+        _ => 1,
+    };
+    if optind > targets.len() {
+        return finish(core, &name, optind);
+    }
+
+    let arg: Vec<char> = targets[optind-1].chars().collect();
+    let mut pos = match &core.getopts_position {
+        (i, p, a) if *i == optind && *p > 0 && *p < arg.len()
+                     && *a == targets[optind-1] => *p,
+        _ => 1,
+    };
+    if pos == 1 {
+        if targets[optind-1] == "--" {
+            return finish(core, &name, optind + 1);
+        }
+        if arg.len() < 2 || arg[0] != '-' {
+            return finish(core, &name, optind);
+        }
+    }
+
+    let silent = optstring.starts_with(':');
+    let opt = arg[pos];
+    pos += 1;
+
+    let found = match opt == ':' {
+        true  => None,
+        false => optstring.find(opt),
+    };
+    let takes_arg = found.map(|n| optstring[n+opt.len_utf8()..].starts_with(':'));
+
+    match takes_arg {
+        None => {
+            print_error(core, silent, "illegal option", opt);
+            core.data.set_param(&name, "?");
+            match silent {
+                true  => core.data.set_param("OPTARG", &opt.to_string()),
+                false => { core.data.unset_var("OPTARG"); },
+            }
+        },
+        Some(true) => {
+            if pos < arg.len() {
+                let optarg: String = arg[pos..].iter().collect();
+                core.data.set_param("OPTARG", &optarg);
+                core.data.set_param(&name, &opt.to_string());
+            }else if optind < targets.len() {
+                core.data.set_param("OPTARG", &targets[optind]);
+                core.data.set_param(&name, &opt.to_string());
+                optind += 1;
+            }else if silent {
+                core.data.set_param("OPTARG", &opt.to_string());
+                core.data.set_param(&name, ":");
+            }else{
+                print_error(core, silent, "option requires an argument", opt);
+                core.data.unset_var("OPTARG");
+                core.data.set_param(&name, "?");
+            }
+            pos = arg.len();
+        },
+        Some(false) => {
+            core.data.unset_var("OPTARG");
+            core.data.set_param(&name, &opt.to_string());
+        },
+    }
+
+    if pos >= arg.len() {
+        optind += 1;
+        pos = 1;
+    }
+    core.data.set_param("OPTIND", &optind.to_string());
+    let next_arg = targets.get(optind-1).cloned().unwrap_or_default();
+    core.getopts_position = (optind, pos, next_arg);
+    0
+}
@@ -2,32 +2,336 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use crate::ShellCore;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::core::history::HistoryEntry;
 
-pub fn history(core: &mut ShellCore, _: &mut Vec<String>) -> i32 {
-    let mut number = 1;
+fn history_list(core: &mut ShellCore, args: &[String]) -> i32 {
+    let len = core.history.len();
+    let num = match args.first().map(|a| a.parse::<usize>()) {
+        None        => len,
+        Some(Ok(n)) => n.min(len),
+        Some(_)     => {
+            eprintln!("sush: history: {}: numeric argument required", &args[0]);
+            return 1;
+        },
+    };
 
-    let filename = core.data.get_param("HISTFILE");
-    if filename == "" {
-        return 0;
+    let timeformat = core.data.get_param("HISTTIMEFORMAT");
+    for (i, h) in core.history[..num].iter().enumerate().rev() {
+        let time = match (timeformat.as_str(), h.time) {
+            ("", _) => String::new(),
+            (_, 0)  => "??".to_string(),
+            (f, t)  => super::printf::strftime(f, t),
+        };
+        println!("{:5}  {}{}", len - i, time, h.line.replace("↵ \0", "\n"));
+    }
+    0
+}
+
+/* an offset (negative ones count back from the end) to the position from the newest entry */
+fn history_pos(core: &ShellCore, spec: &str) -> Option<usize> {
+    let len = core.history.len() as i64;
+    match spec.parse::<i64>() {
+        Ok(n) if n > 0 && n <= len => Some((len - n) as usize),
+        Ok(n) if n < 0 && -n <= len => Some((-n - 1) as usize),
+        _ => None,
+    }
+}
+
+fn history_delete(core: &mut ShellCore, spec: &str) -> i32 {
+    let range = match spec.parse::<i64>() {
+        Ok(_) => history_pos(core, spec).map(|p| (p, p)),
+        Err(_) => match spec[1..].find('-') {
+            Some(n) => {
+                let from = history_pos(core, &spec[..n+1]);
+                let to = history_pos(core, &spec[n+2..]);
+                match (from, to) {
+                    (Some(f), Some(t)) if f >= t => Some((t, f)),
+                    _ => None,
+                }
+            },
+            None => None,
+        },
+    };
+
+    match range {
+        Some((newer, older)) => {
+            for _ in newer..=older {
+                core.delete_history(newer);
+            }
+            0
+        },
+        None => {
+            eprintln!("sush: history: {}: history position out of range", spec);
+            1
+        },
+    }
+}
+
+fn history_print_expansion(core: &mut ShellCore, args: &[String]) -> i32 {
+    if core.options.query("history") && ! core.history.is_empty() {
+        core.delete_history(0); //history -p itself
+    }
+    for arg in args {
+        match core.expand_history(arg) {
+            Ok(Some((expanded, _))) => println!("{}", expanded),
+            Ok(None) => println!("{}", arg),
+            Err(msg) => {
+                eprintln!("sush: {}", msg);
+                return 1;
+            },
+        }
+    }
+    0
+}
+
+fn history_store(core: &mut ShellCore, args: &[String]) -> i32 {
+    if core.options.query("history") && ! core.history.is_empty() {
+        core.delete_history(0); //history -s itself
     }
+    if ! args.is_empty() {
+        core.push_history(HistoryEntry::new(&args.join(" ")));
+    }
+    0
+}
 
-    let file = match File::open(&filename) {
-        Ok(f) => f,
-        _     => return 0,
+fn history_file(core: &mut ShellCore, opt: char, args: &[String]) -> i32 {
+    let filename = args.first().map(|a| a.as_str()).unwrap_or("");
+    let result = match opt {
+        'a' => core.append_new_history(filename),
+        'n' => core.load_history(filename, false),
+        'r' => core.load_history(filename, true),
+        _   => core.write_all_history(filename),
     };
 
-    let f = BufReader::new(file);
-    for line in f.lines() {
-        println!("{:5} {}", number, &line.unwrap());
-        number += 1;
+    match result {
+        Ok(()) => 0,
+        Err(msg) => {
+            eprintln!("sush: history: {}", msg);
+            1
+        },
+    }
+}
+
+pub fn history(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut opts = String::new();
+    let mut pos = 1;
+    while pos < args.len() && args[pos].starts_with("-") && args[pos].len() > 1 {
+        if args[pos] == "--" {
+            pos += 1;
+            break;
+        }
+        if args[pos][1..].chars().any(|c| ! "cdanrwps".contains(c)) {
+            eprintln!("sush: history: {}: invalid option", &args[pos]);
+            eprintln!("history: usage: history [-c] [-d offset] [n] or history -anrw [filename] or history -ps arg [arg...]");
+            return 2;
+        }
+        opts += &args[pos][1..];
+        pos += 1;
+        if opts.contains('d') {
+            break;
+        }
+    }
+    let rest = &args[pos..].to_vec();
+
+    if opts.contains('c') {
+        core.clear_history();
+    }
+    if opts.contains('d') {
+        return match rest.first() {
+            Some(spec) => history_delete(core, spec),
+            None => {
+                eprintln!("sush: history: -d: option requires an argument");
+                2
+            },
+        };
+    }
+
+    for opt in ['a', 'n', 'r', 'w'] {
+        if opts.contains(opt) {
+            return history_file(core, opt, rest);
+        }
+    }
+
+    if opts.contains('p') {
+        return history_print_expansion(core, rest);
+    }
+    if opts.contains('s') {
+        return history_store(core, rest);
+    }
+    if opts.contains('c') {
+        return 0;
+    }
+
+    history_list(core, rest)
+}
+
+/* a number, a negative offset or the prefix of a command to an index of entries */
+fn fc_index(spec: &str, entries: &[String]) -> Option<usize> {
+    let len = entries.len() as i64;
+    match spec.parse::<i64>() {
+        Ok(n) if n < 0 => Some((len + n).max(0) as usize),
+        Ok(n) if n > 0 => Some((n.min(len) - 1) as usize),
+        Ok(_) => None,
+        Err(_) => entries.iter().rposition(|e| e.starts_with(spec)),
     }
+}
+
+fn fc_range(specs: &[String], entries: &[String], default_first: &str) -> Option<(usize, usize)> {
+    let first = specs.first().map(|s| s.as_str()).unwrap_or(default_first);
+    let from = fc_index(first, entries)?;
+    let to = match specs.get(1) {
+        Some(s) => fc_index(s, entries)?,
+        None if specs.is_empty() => entries.len() - 1,
+        None => match default_first {
+            "-1" => from,
+            _    => entries.len() - 1,
+        },
+    };
+    Some((from, to))
+}
 
-    for h in core.history.iter().rev() {
-        println!("{:5} {}", number, &h);
-        number += 1;
+fn fc_list(entries: &[String], specs: &[String], number: bool, reverse: bool) -> i32 {
+    let (from, to) = match fc_range(specs, entries, "-16") {
+        Some(r) => r,
+        None    => {
+            eprintln!("sush: fc: history specification out of range");
+            return 1;
+        },
+    };
+
+    let mut nums: Vec<usize> = match from <= to {
+        true  => (from..=to).collect(),
+        false => (to..=from).rev().collect(),
+    };
+    if reverse {
+        nums.reverse();
     }
 
+    for n in nums {
+        match number {
+            true  => println!("{}\t {}", n + 1, &entries[n]),
+            false => println!("\t {}", &entries[n]),
+        }
+    }
     0
 }
+
+fn fc_run(core: &mut ShellCore, command: &str) -> i32 {
+    eprintln!("{}", command.trim_end());
+    super::eval(core, &mut vec!["eval".to_string(), command.to_string()])
+}
+
+fn fc_substitute(core: &mut ShellCore, entries: &[String], args: &[String]) -> i32 {
+    let mut subs = vec![];
+    let mut pos = 0;
+    while pos < args.len() && args[pos].contains('=') {
+        let (pat, rep) = args[pos].split_once('=').unwrap();
+        subs.push((pat.to_string(), rep.to_string()));
+        pos += 1;
+    }
+
+    let mut command = match fc_index(args.get(pos).map(|s| s.as_str()).unwrap_or("-1"), entries) {
+        Some(n) => entries[n].clone(),
+        None    => {
+            eprintln!("sush: fc: no command found");
+            return 1;
+        },
+    };
+    for (pat, rep) in subs {
+        command = command.replace(&pat, &rep);
+    }
+    fc_run(core, &command)
+}
+
+fn fc_edit(core: &mut ShellCore, entries: &[String], specs: &[String], editor: &str) -> i32 {
+    let (from, to) = match fc_range(specs, entries, "-1") {
+        Some(r) => r,
+        None    => {
+            eprintln!("sush: fc: history specification out of range");
+            return 1;
+        },
+    };
+    let lines: Vec<String> = match from <= to {
+        true  => entries[from..=to].to_vec(),
+        false => entries[to..=from].iter().rev().cloned().collect(),
+    };
+
+    let path = format!("/tmp/sush-fc-{}", std::process::id());
+    if std::fs::write(&path, lines.join("\n") + "\n").is_err() {
+        eprintln!("sush: fc: {}: cannot create a file", &path);
+        return 1;
+    }
+
+    let editor = match editor {
+        "" => match (core.data.get_param("FCEDIT"), core.data.get_param("EDITOR")) {
+            (e, _) if ! e.is_empty() => e,
+            (_, e) if ! e.is_empty() => e,
+            _ => "vi".to_string(),
+        },
+        e => e.to_string(),
+    };
+
+    let mut edit = vec!["eval".to_string(), format!("{} {}", editor, &path)];
+    let result = match super::eval(core, &mut edit) {
+        0 => std::fs::read_to_string(&path).ok(),
+        _ => None,
+    };
+    let _ = std::fs::remove_file(&path);
+
+    match result {
+        Some(commands) => fc_run(core, &commands),
+        None => 1,
+    }
+}
+
+pub fn fc(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut entries = core.history_entries();
+    if core.options.query("history") {
+        entries.pop(); //fc itself
+    }
+
+    let (mut list, mut number, mut reverse, mut editor) = (false, true, false, String::new());
+    let mut pos = 1;
+    while pos < args.len() && args[pos].starts_with("-") && args[pos].parse::<i64>().is_err() {
+        if args[pos] == "--" {
+            pos += 1;
+            break;
+        }
+        for c in args[pos][1..].chars() {
+            match c {
+                'l' => list = true,
+                'n' => number = false,
+                'r' => reverse = true,
+                's' => editor = "-".to_string(),
+                'e' => {
+                    pos += 1;
+                    match args.get(pos) {
+                        Some(e) => editor = e.clone(),
+                        None    => {
+                            eprintln!("sush: fc: -e: option requires an argument");
+                            return 2;
+                        },
+                    }
+                },
+                _ => {
+                    eprintln!("sush: fc: -{}: invalid option", c);
+                    eprintln!("fc: usage: fc [-e ename] [-lnr] [first] [last] or fc -s [pat=rep] [command]");
+                    return 2;
+                },
+            }
+        }
+        pos += 1;
+    }
+
+    if entries.is_empty() {
+        eprintln!("sush: fc: history specification out of range");
+        return 1;
+    }
+
+    let specs = &args[pos..];
+    match (list, editor.as_str()) {
+        (true, _)  => fc_list(&entries, specs, number, reverse),
+        (_, "-")   => fc_substitute(core, &entries, specs),
+        (_, e)     => fc_edit(core, &entries, specs, e),
+    }
+}
@@ -5,6 +5,7 @@ use crate::ShellCore;
 use crate::core::JobEntry;
 use crate::core::{ignore_signal, restore_signal};
 use nix::sys::signal::Signal;
+use nix::sys::wait::{waitpid, WaitStatus};
 use nix::unistd;
 use nix::unistd::Pid;
 
@@ -111,14 +112,33 @@ pub fn jobs(core: &mut ShellCore, _: &mut Vec<String>) -> i32 {
     0
 }
 
+fn wait_process_substitution(core: &mut ShellCore, pid: Pid) -> i32 {
+    core.process_substitution_pids.retain(|p| *p != pid);
+    match waitpid(pid, None) {
+        Ok(WaitStatus::Exited(_, es)) => es,
+        Ok(WaitStatus::Signaled(_, sig, _)) => sig as i32 + 128,
+        _ => 127,
+    }
+}
+
 pub fn wait(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     if args.len() <= 1 {
         for job in core.job_table.iter_mut() {
             job.update_status(true);
         }
+        for pid in core.process_substitution_pids.clone() {
+            wait_process_substitution(core, pid);
+        }
         return 0;
     }
 
+    if let Ok(n) = args[1].parse::<i32>() {
+        let pid = Pid::from_raw(n);
+        if core.process_substitution_pids.contains(&pid) {
+            return wait_process_substitution(core, pid);
+        }
+    }
+
     let id = arg_to_id(&args[1], &core.job_table_priority);
     match id_to_job(id, &mut core.job_table) {
         Some(job) => {job.update_status(true);},
@@ -3,6 +3,7 @@
 
 use crate::{error_message, ShellCore};
 use crate::core::data::Value;
+use crate::core::options::Options;
 
 fn print_data(k: &str, core: &mut ShellCore) {
     match core.data.get_value(k) {
@@ -12,7 +13,7 @@ fn print_data(k: &str, core: &mut ShellCore) {
         Some(Value::EvaluatedArray(a)) => {
             let mut formatted = String::new();
             formatted += "(";
-            for (i, v) in a.iter().enumerate() {
+            for (i, v) in a.iter() {
                 formatted += &format!("[{}]=\"{}\" ", i, v).clone();
             }
             if formatted.ends_with(" ") {
@@ -41,77 +42,119 @@ pub fn set_parameters(core: &mut ShellCore, args: &[String]) -> i32 {
     0
 }
 
-fn set_option(core: &mut ShellCore, opt: char, pm: char) {
-    if pm == '+' {
-        core.data.flags.retain(|e| e != opt);
-    }else{
-        if ! core.data.flags.contains(opt) {
-            core.data.flags.push(opt);
-        }
+pub fn shift(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let n = match args.len() {
+        1 => 1,
+        _ => match args[1].parse::<i64>() {
+            Ok(n) if n >= 0 => n as usize,
+            Ok(_) => {
+                eprintln!("sush: shift: {}: shift count out of range", args[1]);
+                return 1;
+            },
+            Err(_) => {
+                eprintln!("sush: shift: {}: numeric argument required", args[1]);
+                return 1;
+            },
+        },
+    };
+
+    let params = match core.data.position_parameters.last_mut() {
+        Some(p) => p,
+        None    => error_message::internal("empty param stack"),
+    };
+
+    if n >= params.len() {
+        return 1;
     }
+    params.drain(1..n+1);
+    0
 }
 
-fn set_options(core: &mut ShellCore, args: &[String]) -> i32 {
-    for a in args {
-        if a.starts_with("--") {
-            return 0;
-        }
-        let pm = a.chars().nth(0).unwrap();
-        for ch in a[1..].chars() {
-            if "xve".find(ch).is_none() {
-                eprintln!("sush: set: {}{}: invalid option", &pm, &ch);
-                return 2;
-            }
-            set_option(core, ch, pm);
-        }
+pub fn set_flag(core: &mut ShellCore, flag: char, on: bool) {
+    core.data.flags.retain(|f| f != flag);
+    if on {
+        core.data.flags.push(flag);
+    }
+
+    if let Some(name) = Options::flag_to_name(flag) {
+        core.options.set(name, on);
     }
-    0
 }
 
-pub fn set(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
-    match args.len() {
-        0 => panic!("never come here"),
-        1 => {
-            match args[0] == "set" {
-                true  => print(core),
-                false => set_parameters(core, args),
-            }
-        },
-        _ => {
-            if args[1].starts_with("--") {
-                args.remove(0);
-                return set_parameters(core, args)
-            }
+fn set_long_option(core: &mut ShellCore, name: &str, on: bool) -> bool {
+    if ! core.options.exist(name) {
+        eprintln!("sush: set: {}: invalid option name", name);
+        return false;
+    }
 
-            if args[1] == "-o" {
-                if args.len() == 2 {
-                    core.options.print_all();
-                    return 0;
-                }else{
-                    match core.options.set(&args[2], true) {
-                        true  => return 0,
-                        false => return 2,
-                    }
+    match Options::name_to_flag(name) {
+        Some(flag) => set_flag(core, flag, on),
+        None       => { core.options.set(name, on); },
+    }
+
+    match (name, on) { //the editing modes are exclusive
+        ("vi", true)    => { core.options.set("emacs", false); },
+        ("emacs", true) => { core.options.set("vi", false); },
+        _ => {},
+    }
+    true
+}
+
+fn set_options(core: &mut ShellCore, args: &[String]) -> i32 {
+    let mut pos = 0;
+    while pos < args.len() {
+        let arg = &args[pos];
+        if arg == "--" || arg == "-" || ! (arg.starts_with("-") || arg.starts_with("+")) {
+            break;
+        }
+
+        let on = arg.starts_with("-");
+        for ch in arg[1..].chars() {
+            if ch != 'o' {
+                if Options::flag_to_name(ch).is_none() {
+                    eprintln!("sush: set: {}{}: invalid option", &arg[..1], &ch);
+                    return 2;
                 }
+                set_flag(core, ch, on);
+                continue;
             }
 
-            if args[1] == "+o" {
-                if args.len() == 2 {
-                    core.options.print_all2();
-                    return 0;
-                }else{
-                    match core.options.set(&args[2], false) {
-                        true  => return 0,
-                        false => return 2,
+            pos += 1;
+            match args.get(pos) {
+                Some(name) => {
+                    if ! set_long_option(core, name, on) {
+                        return 2;
                     }
-                }
+                },
+                None if on => core.options.print_all(),
+                None       => core.options.print_all2(),
             }
+        }
+        pos += 1;
+    }
 
-            match args[1].starts_with("-") || args[1].starts_with("+") {
-                true  => set_options(core, &args[1..]),
-                false => set_parameters(core, args),
-            }
-        },
+    if pos >= args.len() {
+        return 0;
+    }
+
+    if args[pos] == "-" { // "set -" turns off -x and -v
+        set_flag(core, 'x', false);
+        set_flag(core, 'v', false);
+    }
+    let mut params = vec![core.data.get_param("0")];
+    match args[pos] == "--" || args[pos] == "-" {
+        true  => params.extend(args[pos+1..].to_vec()),
+        false => params.extend(args[pos..].to_vec()),
+    }
+    set_parameters(core, &params)
+}
+
+pub fn set(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    match args.len() {
+        0 => panic!("never come here"),
+        1 if args[0] == "set" => print(core),
+        1 => 0,
+        _ => set_options(core, &args[1..]),
     }
 }
 
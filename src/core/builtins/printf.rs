@@ -0,0 +1,430 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::ShellCore;
+use crate::utils::escape;
+use nix::libc;
+use std::ffi::CString;
+use super::utils;
+
+#[derive(Debug, Default)]
+struct Spec {
+    flags: String,
+    width: Option<String>,
+    precision: Option<String>,
+    time_format: Option<String>,
+    conv: char,
+}
+
+struct Printf {
+    args: Vec<String>,
+    pos: usize,
+    consumed: bool,
+    error: bool,
+}
+
+/* parses a number in the same way as strtoimax with base 0 */
+fn parse_integer(s: &str) -> (i64, bool) {
+    let mut chars = s.trim_start().chars().peekable();
+
+    if let Some(q) = chars.peek().copied() {
+        if q == '\'' || q == '"' {
+            chars.next();
+            return (chars.next().map(|c| c as i64).unwrap_or(0), true);
+        }
+    }
+
+    let mut sign = 1;
+    match chars.peek() {
+        Some('-') => { sign = -1; chars.next(); },
+        Some('+') => { chars.next(); },
+        _ => {},
+    }
+
+    let rest: String = chars.collect();
+    let (radix, digits) = if rest.starts_with("0x") || rest.starts_with("0X") {
+        (16, &rest[2..])
+    }else if rest.starts_with("0") && rest.len() > 1 {
+        (8, &rest[1..])
+    }else{
+        (10, &rest[..])
+    };
+
+    let valid: String = digits.chars().take_while(|c| c.is_digit(radix)).collect();
+    let n = i64::from_str_radix(&valid, radix).unwrap_or(0);
+    let ok = valid.len() == digits.len() && (! valid.is_empty() || radix == 8);
+    (sign * n, ok)
+}
+
+fn parse_float(s: &str) -> (f64, bool) {
+    let t = s.trim_start();
+    if t.starts_with("'") || t.starts_with("\"") {
+        return (t.chars().nth(1).map(|c| c as u32 as f64).unwrap_or(0.0), true);
+    }
+
+    match t.parse::<f64>() {
+        Ok(f) => (f, true),
+        _ => {
+            let (n, ok) = parse_integer(t);
+            (n as f64, ok)
+        },
+    }
+}
+
+/* quotes a string so that the shell can read it again */
+fn shell_quote(s: &str) -> String {
+    if s.is_empty() {
+        return "''".to_string();
+    }
+
+    if s.chars().any(|c| c.is_control()) {
+        let mut ans = "$'".to_string();
+        for c in s.chars() {
+            match c {
+                '\n' => ans += "\\n",
+                '\t' => ans += "\\t",
+                '\r' => ans += "\\r",
+                '\x1b' => ans += "\\E",
+                '\'' | '\\' => { ans.push('\\'); ans.push(c); },
+                c if c.is_control() => ans += &format!("\\{:03o}", c as u32),
+                c => ans.push(c),
+            }
+        }
+        return ans + "'";
+    }
+
+    let mut ans = String::new();
+    for (i, c) in s.chars().enumerate() {
+        if " \t'\"\\|&;()<>!{}*[]?^$`,".contains(c) || (i == 0 && (c == '~' || c == '#')) {
+            ans.push('\\');
+        }
+        ans.push(c);
+    }
+    ans
+}
+
+pub fn strftime(format: &str, time: i64) -> String {
+    let format = match format {
+        "" => "%X",
+        f  => f,
+    };
+    let cformat = match CString::new(format) {
+        Ok(s) => s,
+        _     => return String::new(),
+    };
+
+    let mut buf = vec![0u8; 1024];
+    let len = unsafe {
+        let t: libc::time_t = time as libc::time_t;
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&t, &mut tm);
+        libc::strftime(buf.as_mut_ptr() as *mut libc::c_char, buf.len(), cformat.as_ptr(), &tm)
+    };
+    String::from_utf8_lossy(&buf[..len]).to_string()
+}
+
+/* formats a number with snprintf(3) as bash does */
+fn c_format(print: impl Fn(*mut libc::c_char, usize) -> libc::c_int) -> String {
+    let mut buf = vec![0u8; 512];
+    loop {
+        let len = print(buf.as_mut_ptr() as *mut libc::c_char, buf.len());
+        if len < 0 {
+            return String::new();
+        }
+        if (len as usize) < buf.len() {
+            return String::from_utf8_lossy(&buf[..len as usize]).to_string();
+        }
+        buf.resize(len as usize + 1, 0);
+    }
+}
+
+fn pad(s: &str, spec: &Spec, width: Option<i64>, precision: Option<i64>) -> String {
+    let mut s: String = match precision {
+        Some(p) if p >= 0 => s.chars().take(p as usize).collect(),
+        _ => s.to_string(),
+    };
+
+    let (width, left) = match width {
+        Some(w) if w < 0 => (-w as usize, true),
+        Some(w) => (w as usize, spec.flags.contains('-')),
+        None    => (0, false),
+    };
+
+    let len = s.chars().count();
+    if len < width {
+        let space = " ".repeat(width - len);
+        s = match left {
+            true  => s + &space,
+            false => space + &s,
+        };
+    }
+    s
+}
+
+impl Printf {
+    fn next_arg(&mut self) -> Option<String> {
+        self.consumed = true;
+        let ans = self.args.get(self.pos).cloned();
+        self.pos += 1;
+        ans
+    }
+
+    fn next_integer(&mut self) -> i64 {
+        let arg = match self.next_arg() {
+            Some(a) => a,
+            None    => return 0,
+        };
+        let (n, ok) = parse_integer(&arg);
+        if ! ok {
+            eprintln!("sush: printf: {}: invalid number", &arg);
+            self.error = true;
+        }
+        n
+    }
+
+    fn next_float(&mut self) -> f64 {
+        let arg = match self.next_arg() {
+            Some(a) => a,
+            None    => return 0.0,
+        };
+        let (f, ok) = parse_float(&arg);
+        if ! ok {
+            eprintln!("sush: printf: {}: invalid number", &arg);
+            self.error = true;
+        }
+        f
+    }
+
+    fn star_value(&mut self, v: &Option<String>) -> Option<i64> {
+        match v.as_deref() {
+            Some("*") => Some(self.next_integer()),
+            Some(n)   => Some(n.parse::<i64>().unwrap_or(0)),
+            None      => None,
+        }
+    }
+
+    /* the flag in the return value is true when \c appears in the argument of %b */
+    fn convert(&mut self, spec: &Spec) -> (String, bool) {
+        let width = self.star_value(&spec.width);
+        let precision = self.star_value(&spec.precision);
+
+        let mut cspec = format!("%{}", &spec.flags);
+        if let Some(w) = width {
+            cspec += &w.to_string();
+        }
+        if let Some(p) = precision {
+            cspec += &format!(".{}", p);
+        }
+
+        let ans = match spec.conv {
+            'd' | 'i' => {
+                let n = self.next_integer() as libc::c_longlong;
+                let cspec = CString::new(cspec + "lld").unwrap_or_default();
+                c_format(|buf, len| unsafe { libc::snprintf(buf, len, cspec.as_ptr(), n) })
+            },
+            'o' | 'u' | 'x' | 'X' => {
+                let n = self.next_integer() as libc::c_ulonglong;
+                let cspec = CString::new(format!("{}ll{}", cspec, spec.conv)).unwrap_or_default();
+                c_format(|buf, len| unsafe { libc::snprintf(buf, len, cspec.as_ptr(), n) })
+            },
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' | 'a' | 'A' => {
+                let f = self.next_float() as libc::c_double;
+                let cspec = CString::new(format!("{}{}", cspec, spec.conv)).unwrap_or_default();
+                c_format(|buf, len| unsafe { libc::snprintf(buf, len, cspec.as_ptr(), f) })
+            },
+            'c' => {
+                let s = self.next_arg().unwrap_or_default();
+                pad(&s.chars().take(1).collect::<String>(), spec, width, None)
+            },
+            's' => pad(&self.next_arg().unwrap_or_default(), spec, width, precision),
+            'q' => pad(&shell_quote(&self.next_arg().unwrap_or_default()), spec, width, precision),
+            'b' => {
+                let (s, cut) = escape::expand(&self.next_arg().unwrap_or_default(), escape::Mode::B);
+                return (pad(&s, spec, width, precision), cut);
+            },
+            'T' => {
+                let time = match self.next_arg() {
+                    Some(a) if ! a.is_empty() => {
+                        let (n, ok) = parse_integer(&a);
+                        if ! ok {
+                            eprintln!("sush: printf: {}: invalid number", &a);
+                            self.error = true;
+                        }
+                        n
+                    },
+                    _ => -1,
+                };
+                let time = match time {
+                    -1 | -2 => unsafe { libc::time(std::ptr::null_mut()) as i64 },
+                    t => t,
+                };
+                let format = spec.time_format.clone().unwrap_or_default();
+                pad(&strftime(&format, time), spec, width, precision)
+            },
+            _ => String::new(),
+        };
+
+        (ans, false)
+    }
+
+    fn parse_spec(chars: &[char], pos: &mut usize) -> Result<Spec, String> {
+        let mut spec = Spec::default();
+        let start = *pos;
+
+        while *pos < chars.len() && "-+ #0".contains(chars[*pos]) {
+            spec.flags.push(chars[*pos]);
+            *pos += 1;
+        }
+
+        let number = |pos: &mut usize| -> Option<String> {
+            if *pos < chars.len() && chars[*pos] == '*' {
+                *pos += 1;
+                return Some("*".to_string());
+            }
+            let digits: String = chars[*pos..].iter().take_while(|c| c.is_ascii_digit()).collect();
+            *pos += digits.len();
+            match digits.len() {
+                0 => None,
+                _ => Some(digits),
+            }
+        };
+
+        spec.width = number(pos);
+        if *pos < chars.len() && chars[*pos] == '.' {
+            *pos += 1;
+            spec.precision = Some(number(pos).unwrap_or("0".to_string()));
+        }
+
+        if *pos < chars.len() && chars[*pos] == '(' {
+            let close = chars[*pos..].iter().position(|c| *c == ')');
+            match close {
+                Some(n) => {
+                    spec.time_format = Some(chars[*pos+1..*pos+n].iter().collect());
+                    *pos += n + 1;
+                },
+                None => return Err("`(': missing `)'".to_string()),
+            }
+        }
+
+        /* length modifiers are accepted and ignored */
+        while *pos < chars.len() && "hlLjzt".contains(chars[*pos]) {
+            *pos += 1;
+        }
+
+        let conv = match chars.get(*pos) {
+            Some(c) => *c,
+            None    => return Err(format!("`{}': missing format character",
+                                          chars[start-1..].iter().collect::<String>())),
+        };
+        *pos += 1;
+
+        if spec.time_format.is_some() && conv != 'T'
+        || "diouxXfFeEgGaAcsqbT".find(conv).is_none() {
+            return Err(format!("`{}': invalid format character", conv));
+        }
+
+        spec.conv = conv;
+        Ok(spec)
+    }
+
+    /* returns false when the output must stop */
+    fn format(&mut self, format: &str, out: &mut String) -> Result<bool, String> {
+        let chars: Vec<char> = format.chars().collect();
+        let mut pos = 0;
+        let mut literal = String::new();
+
+        while pos < chars.len() {
+            if chars[pos] != '%' {
+                literal.push(chars[pos]);
+                pos += 1;
+                continue;
+            }
+
+            if chars.get(pos+1) == Some(&'%') {
+                literal.push('%');
+                pos += 2;
+                continue;
+            }
+
+            *out += &escape::expand(&literal, escape::Mode::Format).0;
+            literal.clear();
+
+            pos += 1;
+            let spec = Self::parse_spec(&chars, &mut pos)?;
+            let (s, cut) = self.convert(&spec);
+            *out += &s;
+            if cut {
+                return Ok(false);
+            }
+        }
+
+        *out += &escape::expand(&literal, escape::Mode::Format).0;
+        Ok(true)
+    }
+}
+
+fn output(core: &mut ShellCore, var: Option<&String>, s: &str) -> bool {
+    let var = match var {
+        Some(v) => v,
+        None => {
+            return utils::write_stdout("printf", s);
+        },
+    };
+
+    match var.find('[') {
+        Some(n) if var.ends_with("]") => {
+            core.data.set_array_elem(&var[..n], &var[n+1..var.len()-1], s)
+        },
+        _ => {
+            core.data.set_param(var, s);
+            true
+        },
+    }
+}
+
+pub fn printf(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut args = args[1..].to_vec();
+    let mut var = None;
+
+    if ! args.is_empty() && args[0] == "-v" {
+        if args.len() < 2 {
+            eprintln!("sush: printf: -v: option requires an argument");
+            return 2;
+        }
+        var = Some(args[1].clone());
+        args.drain(..2);
+    }
+    if ! args.is_empty() && args[0] == "--" {
+        args.remove(0);
+    }
+    if args.is_empty() {
+        eprintln!("printf: usage: printf [-v var] format [arguments]");
+        return 2;
+    }
+
+    let format = args.remove(0);
+    let mut printf = Printf { args, pos: 0, consumed: false, error: false };
+    let mut out = String::new();
+
+    loop {
+        printf.consumed = false;
+        match printf.format(&format, &mut out) {
+            Ok(true) => {},
+            Ok(false) => break,
+            Err(msg) => {
+                eprintln!("sush: printf: {}", msg);
+                output(core, var.as_ref(), &out);
+                return 1;
+            },
+        }
+
+        if ! printf.consumed || printf.pos >= printf.args.len() {
+            break;
+        }
+    }
+
+    match output(core, var.as_ref(), &out) && ! printf.error {
+        true  => 0,
+        false => 1,
+    }
+}
@@ -2,6 +2,7 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use crate::ShellCore;
+use nix::unistd;
 
 fn is_varname(s :&String) -> bool {
     if s.len() == 0 {
@@ -19,6 +20,20 @@ fn is_varname(s :&String) -> bool {
     s.chars().position(|c| !name_c(c)) == None
 }
 
+pub fn read_line(line: &mut String) -> usize { // unbuffered not to take data beyond the line
+    let mut bytes = vec![];
+    let mut ch = [0; 1];
+    while let Ok(1) = unistd::read(0, &mut ch) {
+        bytes.push(ch[0]);
+        if ch[0] == b'\n' {
+            break;
+        }
+    }
+
+    *line = String::from_utf8_lossy(&bytes).to_string();
+    bytes.len()
+}
+
 pub fn read(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     if args.len() <= 1 {
         return 0;
@@ -34,9 +49,7 @@ pub fn read(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     }
 
     let mut line = String::new();
-    let len = std::io::stdin()
-        .read_line(&mut line)
-        .expect("SUSHI INTERNAL ERROR: Failed to read line");
+    let len = read_line(&mut line);
 
     let mut pos = 1;
     let mut overflow = String::new();
@@ -23,30 +23,55 @@ pub fn return_(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     }
 }
 
+fn loop_count(name: &str, args: &[String]) -> Result<i32, i32> {
+    if args.len() < 2 {
+        return Ok(1);
+    }
+
+    match args[1].parse::<i32>() {
+        Ok(n) if n > 0 => Ok(n),
+        Ok(_)  => {
+            eprintln!("sush: {}: {}: loop count out of range", name, args[1]);
+            Err(1)
+        },
+        Err(_) => {
+            eprintln!("sush: {}: {}: numeric argument required", name, args[1]);
+            Err(128)
+        },
+    }
+}
+
 pub fn break_(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     if core.loop_level <= 0 {
         eprintln!("sush: break: only meaningful in a `for', `while', or `until' loop");
         return 0;
     }
 
-    core.break_counter += 1;
-    if args.len() < 2 {
+    match loop_count("break", args) {
+        Ok(n)  => core.break_counter += n,
+        Err(e) => {
+            core.break_counter += 1;
+            return e;
+        },
+    }
+    0
+}
+
+pub fn continue_(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if core.loop_level <= 0 {
+        eprintln!("sush: continue: only meaningful in a `for', `while', or `until' loop");
         return 0;
     }
 
-    match args[1].parse::<i32>() {
+    match loop_count("continue", args) {
         Ok(n)  => {
-            if n > 0 {
-                core.break_counter += n - 1;
-            }else{
-                eprintln!("sush: break: {}: loop count out of range", args[1]);
-                return 1;
-            }
+            core.break_counter += std::cmp::min(n, core.loop_level) - 1;
+            core.continue_flag = true;
         },
-        Err(_) => {
-            eprintln!("sush: break: {}: numeric argument required", args[1]);
-            return 128;
+        Err(e) => {
+            core.break_counter += 1;
+            return e;
         },
-    };
+    }
     0
 }
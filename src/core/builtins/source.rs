@@ -2,6 +2,7 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use crate::{file_check, Script, ShellCore, Feeder};
+use crate::core::trap;
 use crate::elements::io;
 use std::fs::File;
 use std::os::fd::IntoRawFd;
@@ -52,9 +53,10 @@ pub fn source(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
     }
 
     io::replace(backup, 0);
+    core.return_flag = false;
+    core.run_trap(trap::RETURN);
     core.source_function_level -= 1;
     core.source_level -= 1;
-    core.return_flag = false;
     core.read_stdin = read_stdin_backup;
     core.data.get_param("?").parse::<i32>()
         .expect("SUSH INTERNAL ERROR: BAD EXIT STATUS")
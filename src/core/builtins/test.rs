@@ -0,0 +1,172 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::ShellCore;
+use crate::elements::expr::conditional::ConditionalExpr;
+
+fn is_unary_op(s: &str) -> bool {
+    s.len() == 2 && s.starts_with("-") && "abcdefghknoprstuvwxzGLNOS".contains(&s[1..])
+}
+
+fn is_binary_op(s: &str) -> bool {
+    matches!(s, "=" | "==" | "!=" | "<" | ">" | "-eq" | "-ne" | "-lt" | "-le" | "-gt" | "-ge"
+              | "-nt" | "-ot" | "-ef")
+}
+
+fn to_integer(s: &str) -> Result<i64, String> {
+    match s.trim().parse::<i64>() {
+        Ok(n) => Ok(n),
+        _     => Err(format!("{}: integer expression expected", s)),
+    }
+}
+
+fn unary(op: &str, operand: &str, core: &mut ShellCore) -> Result<bool, String> {
+    ConditionalExpr::unary_test(op, operand, core)
+}
+
+fn binary(left: &str, op: &str, right: &str, core: &mut ShellCore) -> Result<bool, String> {
+    match op {
+        "-a" => Ok(! left.is_empty() && ! right.is_empty()),
+        "-o" => Ok(! left.is_empty() || ! right.is_empty()),
+        "-eq" | "-ne" | "-lt" | "-le" | "-gt" | "-ge" => {
+            let l = to_integer(left)?.to_string();
+            let r = to_integer(right)?.to_string();
+            ConditionalExpr::binary_test(op, &l, &r, core)
+        },
+        _ => ConditionalExpr::binary_test(op, left, right, core),
+    }
+}
+
+/* POSIX rules that decide the meaning of args by their number */
+fn eval_by_number(args: &[String], core: &mut ShellCore) -> Option<Result<bool, String>> {
+    let ans = match args.len() {
+        0 => Ok(false),
+        1 => Ok(! args[0].is_empty()),
+        2 if args[0] == "!" => Ok(args[1].is_empty()),
+        2 if is_unary_op(&args[0]) => unary(&args[0], &args[1], core),
+        2 => Err(format!("{}: unary operator expected", &args[0])),
+        3 if is_binary_op(&args[1]) || args[1] == "-a" || args[1] == "-o" => {
+            binary(&args[0], &args[1], &args[2], core)
+        },
+        3 if args[0] == "!" => eval_by_number(&args[1..], core)?.map(|b| !b),
+        3 if args[0] == "(" && args[2] == ")" => Ok(! args[1].is_empty()),
+        3 => Err(format!("{}: binary operator expected", &args[1])),
+        4 if args[0] == "!" => eval_by_number(&args[1..], core)?.map(|b| !b),
+        4 if args[0] == "(" && args[3] == ")" => eval_by_number(&args[1..3], core)?,
+        _ => return None,
+    };
+    Some(ans)
+}
+
+struct Parser<'a> {
+    args: &'a [String],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self, offset: usize) -> Option<&str> {
+        self.args.get(self.pos + offset).map(|s| s.as_str())
+    }
+
+    fn or_expr(&mut self, core: &mut ShellCore) -> Result<bool, String> {
+        let mut ans = self.and_expr(core)?;
+        while self.peek(0) == Some("-o") {
+            self.pos += 1;
+            let right = self.and_expr(core)?;
+            ans = ans || right;
+        }
+        Ok(ans)
+    }
+
+    fn and_expr(&mut self, core: &mut ShellCore) -> Result<bool, String> {
+        let mut ans = self.not_expr(core)?;
+        while self.peek(0) == Some("-a") {
+            self.pos += 1;
+            let right = self.not_expr(core)?;
+            ans = ans && right;
+        }
+        Ok(ans)
+    }
+
+    fn not_expr(&mut self, core: &mut ShellCore) -> Result<bool, String> {
+        if self.peek(0) == Some("!") && self.peek(1).is_some() {
+            self.pos += 1;
+            return self.not_expr(core).map(|b| !b);
+        }
+        self.primary(core)
+    }
+
+    fn primary(&mut self, core: &mut ShellCore) -> Result<bool, String> {
+        let first = match self.peek(0) {
+            Some(s) => s.to_string(),
+            None    => return Err("argument expected".to_string()),
+        };
+
+        if first == "(" && self.peek(1).is_some() {
+            self.pos += 1;
+            let ans = self.or_expr(core)?;
+            if self.peek(0) != Some(")") {
+                return Err("`)' expected".to_string());
+            }
+            self.pos += 1;
+            return Ok(ans);
+        }
+
+        if let (Some(op), Some(right)) = (self.peek(1), self.peek(2)) {
+            if is_binary_op(op) {
+                let (op, right) = (op.to_string(), right.to_string());
+                self.pos += 3;
+                return binary(&first, &op, &right, core);
+            }
+        }
+
+        if is_unary_op(&first) {
+            if let Some(operand) = self.peek(1) {
+                let operand = operand.to_string();
+                self.pos += 2;
+                return unary(&first, &operand, core);
+            }
+        }
+
+        self.pos += 1;
+        Ok(! first.is_empty())
+    }
+}
+
+fn eval(args: &[String], core: &mut ShellCore) -> Result<bool, String> {
+    if let Some(ans) = eval_by_number(args, core) {
+        return ans;
+    }
+
+    let mut parser = Parser { args, pos: 0 };
+    let ans = parser.or_expr(core)?;
+    match parser.peek(0) {
+        Some(s) => Err(format!("{}: too many arguments", s)),
+        None    => Ok(ans),
+    }
+}
+
+fn exec(name: &str, args: &[String], core: &mut ShellCore) -> i32 {
+    match eval(args, core) {
+        Ok(true)  => 0,
+        Ok(false) => 1,
+        Err(msg)  => {
+            eprintln!("sush: {}: {}", name, msg);
+            2
+        },
+    }
+}
+
+pub fn test(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    exec("test", &args[1..], core)
+}
+
+pub fn bracket(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    if args.last().map(|s| s.as_str()) != Some("]") {
+        eprintln!("sush: [: missing `]'");
+        return 2;
+    }
+
+    let len = args.len();
+    exec("[", &args[1..len-1], core)
+}
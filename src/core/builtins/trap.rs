@@ -0,0 +1,83 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::ShellCore;
+use crate::core::trap;
+use nix::sys::signal::Signal;
+
+fn print_trap(core: &ShellCore, num: i32) {
+    if let Some(com) = core.traps.get(&num).or(core.parent_traps.get(&num)) {
+        println!("trap -- '{}' {}", com.replace("'", "'\\''"), trap::to_name(num));
+    }
+}
+
+fn print_traps(core: &mut ShellCore, specs: &[String]) -> i32 {
+    if specs.is_empty() {
+        let mut nums: Vec<i32> = core.traps.keys().chain(core.parent_traps.keys()).cloned().collect();
+        nums.sort();
+        nums.dedup();
+        nums.iter().for_each(|n| print_trap(core, *n));
+        return 0;
+    }
+
+    let mut exit_status = 0;
+    for spec in specs {
+        match trap::to_number(spec) {
+            Some(n) => print_trap(core, n),
+            None    => exit_status = invalid_spec(spec),
+        }
+    }
+    exit_status
+}
+
+fn print_signal_list() -> i32 {
+    let list: Vec<String> = Signal::iterator()
+                            .map(|s| format!("{:2}) {}", s as i32, s.as_str())).collect();
+    for line in list.chunks(5) {
+        println!("{}", line.join("\t"));
+    }
+    0
+}
+
+fn invalid_spec(spec: &str) -> i32 {
+    eprintln!("sush: trap: {}: invalid signal specification", spec);
+    1
+}
+
+pub fn trap(core: &mut ShellCore, args: &mut Vec<String>) -> i32 {
+    let mut args = args[1..].to_vec();
+
+    match args.first().map(|a| a.as_str()) {
+        None       => return print_traps(core, &[]),
+        Some("-p") => return print_traps(core, &args[1..]),
+        Some("-l") => return print_signal_list(),
+        Some("--") => { args.remove(0); },
+        _ => {},
+    }
+
+    if args.is_empty() {
+        return print_traps(core, &[]);
+    }
+
+    /* a lone signal spec or a leading "-" resets the signals to the defaults */
+    let action = match args[0].as_str() {
+        "-" => None,
+        a if args.len() == 1 || a.parse::<u32>().is_ok() => {
+            if trap::to_number(a).is_none() {
+                return invalid_spec(a);
+            }
+            args.insert(0, "-".to_string());
+            None
+        },
+        a => Some(a.to_string()),
+    };
+
+    let mut exit_status = 0;
+    for spec in &args[1..] {
+        match trap::to_number(spec) {
+            Some(n) => { core.set_trap(n, action.as_deref()); },
+            None    => exit_status = invalid_spec(spec),
+        }
+    }
+    exit_status
+}
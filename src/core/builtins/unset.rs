@@ -3,14 +3,35 @@
 
 use crate::ShellCore;
 
+fn to_status(ok: bool) -> i32 {
+    match ok {
+        true  => 0,
+        false => 1,
+    }
+}
+
+fn unset_array_elem(core: &mut ShellCore, name: &str) -> Option<i32> {
+    let pos = name.find('[')?;
+    if ! name.ends_with("]") {
+        return None;
+    }
+
+    let key = &name[pos+1..name.len()-1];
+    Some(to_status(core.data.unset_array_elem(&name[..pos], key)))
+}
+
 fn unset_all(core: &mut ShellCore, name: &str) -> i32 {
-    core.data.unset(name);
-    0
+    if let Some(es) = unset_array_elem(core, name) {
+        return es;
+    }
+    to_status(core.data.unset(name))
 }
 
 fn unset_var(core: &mut ShellCore, name: &str) -> i32 {
-    core.data.unset_var(name);
-    0
+    if let Some(es) = unset_array_elem(core, name) {
+        return es;
+    }
+    to_status(core.data.unset_var(name))
 }
 
 fn unset_function(core: &mut ShellCore, name: &str) -> i32 {
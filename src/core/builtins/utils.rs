@@ -3,6 +3,9 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use crate::ShellCore;
+use nix::errno::Errno;
+use nix::unistd;
+use std::io::{stdout, Write};
 use std::path::{Path, PathBuf, Component};
 
 pub fn make_absolute_path(core: &mut ShellCore, path_str: &str) -> PathBuf {
@@ -46,3 +49,20 @@ pub fn make_canonical_path(core: &mut ShellCore, path_str: &str) -> PathBuf {
     }
     canonical
 }
+
+/* writes without the buffer of stdout, which would keep the text that failed to be written */
+pub fn write_stdout(cmd: &str, s: &str) -> bool {
+    let _ = stdout().flush();
+    let mut buf = s.as_bytes();
+    while ! buf.is_empty() {
+        match unistd::write(stdout(), buf) {
+            Ok(n) => buf = &buf[n..],
+            Err(Errno::EINTR) => {},
+            Err(e) => {
+                eprintln!("sush: {}: write error: {}", cmd, e.desc());
+                return false;
+            },
+        }
+    }
+    true
+}
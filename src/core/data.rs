@@ -1,11 +1,12 @@
 //SPDXFileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDXLicense-Identifier: BSD-3-Clause
 
+use crate::core::options::FLAG_ORDER;
 use crate::elements::array::Array;
 use crate::elements::word::Word;
 use crate::elements::command::function_def::FunctionDefinition;
 use std::env;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 #[derive(Debug, Clone)]
 pub enum Value {
@@ -13,14 +14,17 @@ pub enum Value {
     Single(Word),
     EvaluatedSingle(String),
     Array(Array),
-    EvaluatedArray(Vec<String>),
+    EvaluatedArray(BTreeMap<usize, String>), // indexes can be sparse
+    AssocArray(HashMap<String, String>),
 }
 
 #[derive(Debug)]
 pub struct Data {
     pub flags: String,
     parameters: Vec<HashMap<String, Value>>,
+    attributes: Vec<HashMap<String, String>>,
     pub position_parameters: Vec<Vec<String>>,
+    pub function_layers: Vec<usize>, // the layers of the running functions
     pub aliases: HashMap<String, String>,
     pub functions: HashMap<String, FunctionDefinition>,
     pub alias_memo: Vec<(String, String)>,
@@ -31,7 +35,9 @@ impl Data {
         Data {
             flags: String::new(),
             parameters: vec![HashMap::new()],
+            attributes: vec![HashMap::new()],
             position_parameters: vec![vec![]],
+            function_layers: vec![],
             aliases: HashMap::new(),
             functions: HashMap::new(),
             alias_memo: vec![],
@@ -40,7 +46,7 @@ impl Data {
 
     pub fn get_param(&mut self, key: &str) -> String {
         if key == "-" {
-            return self.flags.clone();
+            return FLAG_ORDER.chars().filter(|f| self.flags.contains(*f)).collect();
         }
 
         if key == "@" || key == "*" {
@@ -55,35 +61,55 @@ impl Data {
             return self.position_parameters[layer-1][n].to_string();
         }
 
+        let key = &self.resolve_nameref(key);
         match self.get_value(key) {
             Some(Value::EvaluatedSingle(v)) => return v.to_string(),
-            Some(Value::EvaluatedArray(a)) => {
-                match a.len() {
-                    0 => return "".to_string(),
-                    _ => return a[0].to_string(),
-                }
-            },
+            Some(Value::EvaluatedArray(a)) => return a.get(&0).cloned().unwrap_or_default(),
+            Some(Value::AssocArray(a)) => return a.get("0").cloned().unwrap_or_default(),
             _  => {},
         }
 
         match env::var(key) {
             Ok(v) => {
-                self.set_layer_param(key, &v, 0);
+                self.parameters[0].insert(key.to_string(), Value::EvaluatedSingle(v.clone()));
                 v
             },
             _ => "".to_string()
         }
     }
 
+    pub fn has_value(&mut self, key: &str) -> bool {
+        if key == "@" || key == "*" {
+            return ! self.get_position_params().is_empty();
+        }
+        if key.len() == 1 && "$?-!_#".find(key).is_some() {
+            return true;
+        }
+        if let Ok(n) = key.parse::<usize>() {
+            let layer = self.position_parameters.len();
+            return n < self.position_parameters[layer-1].len();
+        }
+
+        let key = &self.resolve_nameref(key);
+        self.get_value(key).is_some() || env::var(key).is_ok()
+    }
+
     pub fn get_array(&mut self, key: &str, pos: &str) -> String {
+        let key = &self.resolve_nameref(key);
         match self.get_value(key) {
             Some(Value::EvaluatedArray(a)) => {
-                if pos == "@" {
-                    return a.join(" ");
+                if pos == "@" || pos == "*" {
+                    return a.values().cloned().collect::<Vec<String>>().join(" ");
                 } else if let Ok(n) = pos.parse::<usize>() {
-                    if n < a.len() {
-                        return a[n].clone();
-                    }
+                    return a.get(&n).cloned().unwrap_or_default();
+                }
+            },
+            Some(Value::AssocArray(_)) => {
+                if pos == "@" || pos == "*" {
+                    return self.get_array_all(key).join(" ");
+                }
+                if let Some(Value::AssocArray(a)) = self.get_value(key) {
+                    return a.get(pos).cloned().unwrap_or_default();
                 }
             },
             Some(Value::EvaluatedSingle(v)) => {
@@ -110,19 +136,58 @@ impl Data {
     }
 
     pub fn get_array_len(&mut self, key: &str) -> usize {
+        let key = &self.resolve_nameref(key);
         match self.get_value(key) {
             Some(Value::EvaluatedArray(a)) => a.len(),
+            Some(Value::AssocArray(a)) => a.len(),
             _ => 0,
         }
     }
 
     pub fn get_array_all(&mut self, key: &str) -> Vec<String> {
+        let key = &self.resolve_nameref(key);
+        match self.get_value(key) {
+            Some(Value::EvaluatedArray(a)) => a.values().cloned().collect(),
+            Some(Value::AssocArray(a)) => {
+                self.get_array_keys(key).iter().map(|k| a[k].clone()).collect()
+            },
+            _ => vec![],
+        }
+    }
+
+    pub fn get_array_keys(&mut self, key: &str) -> Vec<String> {
+        let key = &self.resolve_nameref(key);
         match self.get_value(key) {
-            Some(Value::EvaluatedArray(a)) => a.clone(),
+            Some(Value::EvaluatedArray(a)) => a.keys().map(|i| i.to_string()).collect(),
+            Some(Value::AssocArray(a)) => {
+                let mut keys: Vec<String> = a.keys().cloned().collect();
+                keys.sort();
+                keys
+            },
             _ => vec![],
         }
     }
 
+    /* the last index of an indexed array plus one */
+    pub fn get_array_end(&mut self, key: &str) -> usize {
+        let key = &self.resolve_nameref(key);
+        match self.get_value(key) {
+            Some(Value::EvaluatedArray(a)) => a.keys().last().map(|n| n + 1).unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    /* true also for empty arrays */
+    pub fn is_array(&mut self, key: &str) -> bool {
+        let key = &self.resolve_nameref(key);
+        matches!(self.get_value(key), Some(Value::EvaluatedArray(_)) | Some(Value::AssocArray(_)))
+    }
+
+    pub fn is_assoc(&mut self, key: &str) -> bool {
+        let key = &self.resolve_nameref(key);
+        matches!(self.get_value(key), Some(Value::AssocArray(_)))
+    }
+
     pub fn get_position_params(&self) -> Vec<String> {
         match self.position_parameters.last() {
             Some(v) => v[1..].to_vec(),
@@ -144,17 +209,147 @@ impl Data {
         }
     }
 
-    pub fn set_layer_param(&mut self, key: &str, val: &str, layer: usize) {
+    /* the innermost layer that has the variable or its attributes */
+    pub fn get_layer(&self, key: &str) -> Option<usize> {
+        let num = self.parameters.len();
+        (0..num).rev().find(|layer| self.parameters[*layer].contains_key(key)
+                                    || self.attributes[*layer].contains_key(key))
+    }
+
+    pub fn get_attributes(&self, key: &str) -> String {
+        match self.get_layer(key) {
+            Some(layer) => self.attributes[layer].get(key).cloned().unwrap_or_default(),
+            None        => String::new(),
+        }
+    }
+
+    pub fn has_attribute(&self, key: &str, attr: char) -> bool {
+        self.get_attributes(key).contains(attr)
+    }
+
+    pub fn set_layer_attributes(&mut self, key: &str, attrs: &str, layer: usize) {
+        let current = self.attributes[layer].entry(key.to_string()).or_default();
+        for attr in attrs.chars() {
+            match attr {
+                'l' => current.retain(|a| a != 'u'),
+                'u' => current.retain(|a| a != 'l'),
+                _   => {},
+            }
+            if ! current.contains(attr) {
+                current.push(attr);
+            }
+        }
+    }
+
+    pub fn unset_layer_attributes(&mut self, key: &str, attrs: &str, layer: usize) {
+        if let Some(current) = self.attributes[layer].get_mut(key) {
+            current.retain(|a| ! attrs.contains(a));
+        }
+    }
+
+    /* attribute letters in the order of declare -p */
+    pub fn get_attribute_flags(&mut self, key: &str) -> String {
+        let attrs = self.get_attributes(key);
+        let mut ans = match self.get_value(key) {
+            Some(Value::EvaluatedArray(_)) => "a".to_string(),
+            Some(Value::AssocArray(_)) => "A".to_string(),
+            _ => String::new(),
+        };
+        ans.extend("inrxlu".chars().filter(|c| attrs.contains(*c)));
+        ans
+    }
+
+    pub fn resolve_nameref(&self, key: &str) -> String {
+        let mut name = key.to_string();
+        for _ in 0..8 {
+            if ! self.has_attribute(&name, 'n') {
+                break;
+            }
+            let layer = self.get_layer(&name).unwrap_or(0);
+            match self.parameters[layer].get(&name) {
+                Some(Value::EvaluatedSingle(v)) if ! v.is_empty() && *v != name => name = v.clone(),
+                _ => break,
+            }
+        }
+        name
+    }
+
+    /* the name which a nameref variable refers to */
+    pub fn get_nameref_target(&self, key: &str) -> Option<String> {
+        if ! self.has_attribute(key, 'n') {
+            return None;
+        }
+        let layer = self.get_layer(key).unwrap_or(0);
+        match self.parameters[layer].get(key) {
+            Some(Value::EvaluatedSingle(v)) if ! v.is_empty() => Some(v.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn get_exported_params(&mut self) -> Vec<(String, String)> {
+        let mut names = HashSet::new();
+        for layer in &self.attributes {
+            names.extend(layer.iter().filter(|(_, a)| a.contains('x')).map(|(k, _)| k.clone()));
+        }
+
+        let mut ans = vec![];
+        for name in names {
+            if self.has_attribute(&name, 'x') && self.has_value(&name) {
+                let value = self.get_param(&name);
+                ans.push((name, value));
+            }
+        }
+        ans
+    }
+
+    fn check_writable(&self, key: &str) -> bool {
+        if self.has_attribute(key, 'r') {
+            eprintln!("sush: {}: readonly variable", key);
+            return false;
+        }
+        true
+    }
+
+    fn convert_case(&self, key: &str, val: &str) -> String {
+        let attrs = self.get_attributes(key);
+        if attrs.contains('u') {
+            val.to_uppercase()
+        }else if attrs.contains('l') {
+            val.to_lowercase()
+        }else{
+            val.to_string()
+        }
+    }
+
+    pub fn set_layer_param(&mut self, key: &str, val: &str, layer: usize) -> bool {
+        let key = &self.resolve_nameref(key);
+        if ! self.check_writable(key) {
+            return false;
+        }
+        let val = &self.convert_case(key, val);
+
         match env::var(key) {
             Ok(_) => env::set_var(key, val),
             _     => {},
         }
 
-        self.parameters[layer].insert(key.to_string(), Value::EvaluatedSingle(val.to_string()));
+        match self.parameters[layer].get_mut(key) {
+            Some(Value::EvaluatedArray(a)) => {
+                a.insert(0, val.to_string());
+            },
+            Some(Value::AssocArray(a)) => {
+                a.insert("0".to_string(), val.to_string());
+            },
+            _ => {
+                self.parameters[layer].insert(key.to_string(), Value::EvaluatedSingle(val.to_string()));
+            },
+        }
+        true
     }
 
     pub fn set_param(&mut self, key: &str, val: &str) {
-        self.set_layer_param(key, val, 0);
+        let layer = self.get_layer(&self.resolve_nameref(key)).unwrap_or(0);
+        self.set_layer_param(key, val, layer);
     }
 
     pub fn set_local_param(&mut self, key: &str, val: &str) {
@@ -162,12 +357,25 @@ impl Data {
         self.set_layer_param(key, val, layer-1);
     }
 
-    pub fn set_layer_array(&mut self, key: &str, vals: &Vec<String>, layer: usize) {
-        self.parameters[layer].insert(key.to_string(), Value::EvaluatedArray(vals.to_vec()));
+    pub fn set_layer_array(&mut self, key: &str, vals: &[String], layer: usize) -> bool {
+        let vals = vals.iter().cloned().enumerate().collect();
+        self.set_layer_indexed(key, &vals, layer)
+    }
+
+    pub fn set_layer_indexed(&mut self, key: &str, vals: &BTreeMap<usize, String>, layer: usize) -> bool {
+        let key = &self.resolve_nameref(key);
+        if ! self.check_writable(key) {
+            return false;
+        }
+
+        let vals = vals.iter().map(|(i, v)| (*i, self.convert_case(key, v))).collect();
+        self.parameters[layer].insert(key.to_string(), Value::EvaluatedArray(vals));
+        true
     }
 
     pub fn set_array(&mut self, key: &str, vals: &Vec<String>) {
-        self.set_layer_array(key, vals, 0);
+        let layer = self.get_layer(&self.resolve_nameref(key)).unwrap_or(0);
+        self.set_layer_array(key, vals, layer);
     }
 
     pub fn set_local_array(&mut self, key: &str, vals: &Vec<String>) {
@@ -175,18 +383,87 @@ impl Data {
         self.set_layer_array(key, vals, layer-1);
     }
 
+    pub fn set_layer_assoc(&mut self, key: &str, vals: &HashMap<String, String>, layer: usize) -> bool {
+        let key = &self.resolve_nameref(key);
+        if ! self.check_writable(key) {
+            return false;
+        }
+
+        let vals = vals.iter().map(|(k, v)| (k.clone(), self.convert_case(key, v))).collect();
+        self.parameters[layer].insert(key.to_string(), Value::AssocArray(vals));
+        true
+    }
+
+    pub fn set_value(&mut self, key: &str, index: Option<&str>,
+                     value: &Value, layer: Option<usize>) -> bool {
+        let layer = match layer {
+            Some(n) => n,
+            None    => self.get_layer(&self.resolve_nameref(key)).unwrap_or(0),
+        };
+
+        match (index, value) {
+            (Some(i), Value::EvaluatedSingle(v)) => self.set_array_elem(key, i, v),
+            (None, Value::EvaluatedSingle(v)) => self.set_layer_param(key, v, layer),
+            (None, Value::EvaluatedArray(a))  => self.set_layer_indexed(key, a, layer),
+            (None, Value::AssocArray(a))      => self.set_layer_assoc(key, a, layer),
+            _ => false,
+        }
+    }
+
+    pub fn set_array_elem(&mut self, key: &str, pos: &str, val: &str) -> bool {
+        let key = &self.resolve_nameref(key);
+        if ! self.check_writable(key) {
+            return false;
+        }
+        let val = self.convert_case(key, val);
+        let layer = self.get_layer(key).unwrap_or(0);
+
+        let value = self.parameters[layer].entry(key.to_string())
+                        .or_insert(Value::EvaluatedArray(BTreeMap::new()));
+        if let Value::AssocArray(a) = value {
+            a.insert(pos.to_string(), val);
+            return true;
+        }
+
+        let n = match pos.parse::<usize>() {
+            Ok(n) => n,
+            _ => {
+                eprintln!("sush: {}: bad array subscript", pos);
+                return false;
+            },
+        };
+
+        if let Value::EvaluatedSingle(s) = value {
+            *value = Value::EvaluatedArray(BTreeMap::from([(0, s.clone())]));
+        }
+        if let Value::EvaluatedArray(a) = value {
+            a.insert(n, val);
+        }
+        true
+    }
+
     pub fn push_local(&mut self) {
         self.parameters.push(HashMap::new());
+        self.attributes.push(HashMap::new());
     }
 
     pub fn pop_local(&mut self) {
         self.parameters.pop();
+        self.attributes.pop();
     }
 
     pub fn get_layer_num(&mut self) -> usize {
         self.parameters.len()
     }
 
+    pub fn get_layer_params(&self, layer: usize) -> Vec<(String, String)> {
+        self.parameters[layer].iter()
+            .filter_map(|(k, v)| match v {
+                Value::EvaluatedSingle(s) => Some((k.clone(), s.clone())),
+                _ => None,
+            }).collect()
+    }
+
     pub fn get_keys(&mut self) -> Vec<String> {
         let mut output = HashSet::new();
         for layer in &self.parameters {
@@ -194,6 +471,11 @@ impl Data {
                 output.insert(k);
             }
         }
+        for layer in &self.attributes {
+            for k in layer.keys() {
+                output.insert(k);
+            }
+        }
         let mut ans: Vec<String> = output.iter().map(|c| c.to_string()).collect();
         ans.sort();
         ans
@@ -236,18 +518,52 @@ impl Data {
         }
     }
 
-    pub fn unset_var(&mut self, key: &str) {
+    pub fn unset_var(&mut self, key: &str) -> bool {
+        if self.has_attribute(key, 'r') {
+            eprintln!("sush: unset: {}: cannot unset: readonly variable", key);
+            return false;
+        }
+
         for layer in &mut self.parameters {
             layer.remove(key);
         }
+        for layer in &mut self.attributes {
+            layer.remove(key);
+        }
+        env::remove_var(key);
+        true
+    }
+
+    pub fn unset_array_elem(&mut self, key: &str, pos: &str) -> bool {
+        let key = &self.resolve_nameref(key);
+        if ! self.check_writable(key) {
+            return false;
+        }
+
+        let layer = match self.get_layer(key) {
+            Some(layer) => layer,
+            None        => return true,
+        };
+        match self.parameters[layer].get_mut(key) {
+            Some(Value::AssocArray(a)) => {
+                a.remove(pos);
+            },
+            Some(Value::EvaluatedArray(a)) => {
+                if let Ok(n) = pos.parse::<usize>() {
+                    a.remove(&n);
+                }
+            },
+            _ => {},
+        }
+        true
     }
 
     pub fn unset_function(&mut self, key: &str) {
         self.functions.remove(key);
     }
 
-    pub fn unset(&mut self, key: &str) {
-        self.unset_var(key);
+    pub fn unset(&mut self, key: &str) -> bool {
         self.unset_function(key);
+        self.unset_var(key)
     }
 }
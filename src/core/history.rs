@@ -1,77 +1,273 @@
 //SPDXFileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDXLicense-Identifier: BSD-3-Clause
 
+mod expansion;
+
 use crate::ShellCore;
-use rev_lines::RevLines;
-use std::fs::File;
-use std::io::{BufReader, BufWriter, Write};
-use std::fs::OpenOptions;
+use crate::utils::glob;
+use nix::fcntl::{Flock, FlockArg};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Default)]
+pub struct HistoryEntry {
+    pub line: String,
+    pub time: i64, // 0: unknown
+}
+
+impl HistoryEntry {
+    pub fn new(line: &str) -> Self {
+        let time = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as i64,
+            _     => 0,
+        };
+        HistoryEntry { line: line.to_string(), time }
+    }
+}
+
+/* the entries in a history file, the oldest first. "#<epoch>" lines are timestamps */
+fn parse_history_file(text: &str) -> Vec<HistoryEntry> {
+    let mut ans = vec![];
+    let mut time = 0;
+    for line in text.lines() {
+        if let Some(t) = line.strip_prefix('#').and_then(|t| t.parse::<i64>().ok()) {
+            time = t;
+            continue;
+        }
+        ans.push(HistoryEntry { line: line.to_string(), time });
+        time = 0;
+    }
+    ans
+}
 
 impl ShellCore {
     pub fn fetch_history(&mut self, pos: usize, prev: usize, prev_str: String) -> String {
         if prev < self.history.len() {
-            self.history[prev] = prev_str;
-        }else{
-            self.rewritten_history.insert(prev + 1 - self.history.len(), prev_str);
+            self.history[prev].line = prev_str;
         }
 
-        if pos < self.history.len() {
-            self.history[pos].clone()
-        }else{
-            self.fetch_history_file(pos + 1 - self.history.len())
+        match self.history.get(pos) {
+            Some(h) => h.line.clone(),
+            None    => String::new(),
         }
     }
 
-    pub fn fetch_history_file(&mut self, pos: usize) -> String {
-        if let Some(s) = self.rewritten_history.get(&pos) {
-            return s.to_string();
+    /* this session including the lines read from the history file, the oldest first */
+    pub fn history_entries(&mut self) -> Vec<String> {
+        self.history.iter().rev().map(|h| h.line.replace("↵ \0", "\n")).collect()
+    }
+
+    /* Ok(Some((line, true))) for the :p modifier, which prints the line without running it */
+    pub fn expand_history(&mut self, line: &str) -> Result<Option<(String, bool)>, String> {
+        if ! line.contains('!') && ! line.starts_with('^') {
+            return Ok(None);
+        }
+
+        let entries = self.history_entries();
+        expansion::expand(line, &entries)
+    }
+
+    fn history_control(&mut self, name: &str) -> bool {
+        let control = self.data.get_param("HISTCONTROL");
+        control.split(':').any(|c| c == name || (c == "ignoreboth" && name != "erasedups"))
+    }
+
+    /* HISTIGNORE: colon-separated patterns. "&" stands for the previous entry */
+    fn history_ignored(&mut self, line: &str) -> bool {
+        let ignore = self.data.get_param("HISTIGNORE");
+        if ignore.is_empty() {
+            return false;
         }
-        if pos == 0 {
-            return String::new();
+
+        let mut patterns = vec![String::new()];
+        let mut chars = ignore.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some(':') => patterns.last_mut().unwrap().push(':'),
+                    Some(d)   => { patterns.last_mut().unwrap().push('\\'); patterns.last_mut().unwrap().push(d) },
+                    None      => patterns.last_mut().unwrap().push('\\'),
+                },
+                ':' => patterns.push(String::new()),
+                _   => patterns.last_mut().unwrap().push(c),
+            }
         }
 
-        let mut file_line = pos - 1;
-        if let Ok(n) = self.data.get_param("HISTFILESIZE").parse::<usize>() {
-            file_line %= n;
+        let extglob = self.shopts.query("extglob");
+        let line = line.to_string();
+        patterns.iter().filter(|p| ! p.is_empty()).any(|p| match p.as_str() {
+            "&" => self.history.first().map(|h| h.line == line).unwrap_or(false),
+            _   => glob::compare(&line, p, extglob),
+        })
+    }
+
+    /* registers an accepted command line under HISTCONTROL and HISTIGNORE */
+    pub fn add_history(&mut self, command: &str) {
+        let line = command.trim_end().replace("\n", "↵ \0");
+        if line.is_empty() || self.history_ignored(&line)
+        || (line.starts_with(' ') && self.history_control("ignorespace"))
+        || (self.history.first().map(|h| h.line == line).unwrap_or(false)
+            && self.history_control("ignoredups")) {
+            return;
+        }
+
+        if self.history_control("erasedups") {
+            self.history.retain(|h| h.line != line);
         }
+        self.push_history(HistoryEntry::new(&line));
+    }
 
-        if let Ok(hist_file) = File::open(self.data.get_param("HISTFILE")){
-            let mut rev_lines = RevLines::new(BufReader::new(hist_file));
-            if let Some(Ok(s)) = rev_lines.nth(file_line) {
-                return s;
+    pub fn push_history(&mut self, entry: HistoryEntry) {
+        self.history.insert(0, entry);
+        self.history_new_lines += 1;
+        self.trim_history();
+    }
+
+    /* HISTSIZE: the number of entries kept in memory */
+    pub fn trim_history(&mut self) {
+        if let Ok(n) = self.data.get_param("HISTSIZE").parse::<i64>() {
+            if n >= 0 {
+                self.history.truncate(n as usize);
             }
         }
+        self.history_new_lines = self.history_new_lines.min(self.history.len());
+    }
 
-        String::new()
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+        self.history_new_lines = 0;
     }
 
-    pub fn write_history_to_file(&mut self) {
-        if ! self.data.flags.contains('i') || self.is_subshell {
+    /* pos: the position from the newest entry */
+    pub fn delete_history(&mut self, pos: usize) {
+        self.history.remove(pos);
+        if pos < self.history_new_lines {
+            self.history_new_lines -= 1;
+        }
+    }
+
+    fn open_history_file(filename: &str, write: bool) -> Result<Flock<File>, String> {
+        let file = match write {
+            true  => OpenOptions::new().create(true).truncate(false).read(true).write(true).open(filename),
+            false => File::open(filename),
+        };
+        let file = file.map_err(|e| format!("{}: {}", filename, e))?;
+        let arg = match write {
+            true  => FlockArg::LockExclusive,
+            false => FlockArg::LockShared,
+        };
+        Flock::lock(file, arg).map_err(|(_, e)| format!("{}: {}", filename, e))
+    }
+
+    fn history_file_name(&mut self, filename: &str) -> Result<String, String> {
+        match (filename, self.data.get_param("HISTFILE")) {
+            ("", f) if f.is_empty() => Err("HISTFILE is not set".to_string()),
+            ("", f) => Ok(f),
+            (f, _)  => Ok(f.to_string()),
+        }
+    }
+
+    fn read_history_file(&mut self, filename: &str) -> Result<Vec<HistoryEntry>, String> {
+        let filename = self.history_file_name(filename)?;
+        let file = Self::open_history_file(&filename, false)?;
+        let mut text = String::new();
+        for line in BufReader::new(&*file).lines() {
+            match line {
+                Ok(ln) => { text += &ln; text.push('\n') },
+                Err(e) => return Err(format!("{}: {}", &filename, e)),
+            }
+        }
+        Ok(parse_history_file(&text))
+    }
+
+    /* all: false -> only the entries not read yet (history -n) */
+    pub fn load_history(&mut self, filename: &str, all: bool) -> Result<(), String> {
+        let entries = self.read_history_file(filename)?;
+        let skip = match all {
+            true  => 0,
+            false => self.history_file_lines.min(entries.len()),
+        };
+        self.history_file_lines = entries.len();
+
+        for e in entries.into_iter().skip(skip) {
+            self.history.insert(0, e);
+        }
+        self.trim_history();
+        Ok(())
+    }
+
+    /* append: false -> replaces the file with the entries */
+    pub fn write_history(&mut self, filename: &str, entries: &[HistoryEntry], append: bool) -> Result<(), String> {
+        let filename = self.history_file_name(filename)?;
+        let mut file = Self::open_history_file(&filename, true)?;
+        let error = |e: std::io::Error| format!("{}: {}", &filename, e);
+
+        let mut lines = vec![];
+        if append {
+            let mut text = String::new();
+            file.read_to_string(&mut text).map_err(error)?;
+            lines = parse_history_file(&text);
+        }
+        lines.extend(entries.iter().rev().cloned());
+
+        if let Ok(n) = self.data.get_param("HISTFILESIZE").parse::<i64>() {
+            if n >= 0 && lines.len() > n as usize {
+                lines.drain(..lines.len() - n as usize);
+            }
+        }
+
+        let stamp = self.data.has_value("HISTTIMEFORMAT");
+        let mut text = String::new();
+        for h in lines.iter().filter(|h| ! h.line.is_empty()) {
+            if stamp && h.time > 0 {
+                text += &format!("#{}\n", h.time);
+            }
+            text += &h.line;
+            text.push('\n');
+        }
+
+        file.set_len(0).map_err(error)?;
+        file.seek(SeekFrom::Start(0)).map_err(error)?;
+        file.write_all(text.as_bytes()).map_err(error)?;
+        self.history_file_lines = lines.len();
+        Ok(())
+    }
+
+    /* history -a and the histappend shopt */
+    pub fn append_new_history(&mut self, filename: &str) -> Result<(), String> {
+        let entries = self.history[..self.history_new_lines].to_vec();
+        self.write_history(filename, &entries, true)?;
+        self.history_new_lines = 0;
+        Ok(())
+    }
+
+    pub fn write_all_history(&mut self, filename: &str) -> Result<(), String> {
+        let entries = self.history.clone();
+        self.write_history(filename, &entries, false)?;
+        self.history_new_lines = 0;
+        Ok(())
+    }
+
+    pub fn read_history_on_start(&mut self) {
+        if ! self.data.flags.contains('i') || self.data.get_param("HISTFILE").is_empty() {
             return;
         }
-        let filename = self.data.get_param("HISTFILE");
-        if filename == "" {
-            eprintln!("sush: HISTFILE is not set");
+        let _ = self.load_history("", true);
+        self.history_new_lines = 0;
+    }
+
+    pub fn write_history_to_file(&mut self) {
+        if ! self.data.flags.contains('i') || self.is_subshell {
             return;
         }
-    
-        let file = match OpenOptions::new().create(true)
-                .write(true).append(true).open(&filename) {
-            Ok(f) => f,
-            _     => {
-                eprintln!("sush: invalid history file");
-                return;
-            },
+
+        let result = match self.shopts.query("histappend") {
+            true  => self.append_new_history(""),
+            false => self.write_all_history(""),
         };
-    
-        let mut f = BufWriter::new(file);
-        for h in self.history.iter().rev() {
-            if h == "" {
-                continue;
-            }
-            let _ = f.write(h.as_bytes());
-            let _ = f.write(&vec![0x0A]);
+        if let Err(msg) = result {
+            eprintln!("sush: {}", msg);
         }
-        let _ = f.flush();
     }
 }
@@ -0,0 +1,299 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::utils;
+
+struct Expander<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    history: &'a [String],
+    print_only: bool,
+    last_subst: Option<(String, String)>,
+}
+
+/* Ok(None): the line has no history expansion */
+pub fn expand(line: &str, history: &[String]) -> Result<Option<(String, bool)>, String> {
+    let mut ex = Expander {
+        chars: line.chars().collect(),
+        pos: 0,
+        history,
+        print_only: false,
+        last_subst: None,
+    };
+
+    if line.starts_with("^") {
+        return ex.quick_substitution().map(|s| Some((s, ex.print_only)));
+    }
+
+    let mut ans = String::new();
+    let (mut in_single, mut in_double, mut changed) = (false, false, false);
+    while ex.pos < ex.chars.len() {
+        let c = ex.chars[ex.pos];
+        if c == '\\' && ! in_single {
+            ans.extend(ex.chars[ex.pos..].iter().take(2));
+            ex.pos += 2;
+            continue;
+        }
+
+        match c {
+            '\'' if ! in_double => in_single = ! in_single,
+            '"' if ! in_single => in_double = ! in_double,
+            '!' if ! in_single && ex.is_designator(in_double) => {
+                ex.pos += 1;
+                ans += &ex.expand_one(&ans)?;
+                changed = true;
+                continue;
+            },
+            _ => {},
+        }
+        ans.push(c);
+        ex.pos += 1;
+    }
+
+    match changed {
+        true  => Ok(Some((ans, ex.print_only))),
+        false => Ok(None),
+    }
+}
+
+impl Expander<'_> {
+    fn peek(&self, n: usize) -> Option<char> {
+        self.chars.get(self.pos + n).copied()
+    }
+
+    fn is_designator(&self, in_double: bool) -> bool {
+        match self.peek(1) {
+            None => false,
+            Some(c) => ! (" \t\n=(".contains(c) || in_double && c == '"'),
+        }
+    }
+
+    fn take_while(&mut self, judge: fn(char) -> bool) -> String {
+        let ans: String = self.chars[self.pos..].iter().take_while(|c| judge(**c)).collect();
+        self.pos += ans.chars().count();
+        ans
+    }
+
+    fn event_error(&self, spec: &str) -> String {
+        format!("!{}: event not found", spec)
+    }
+
+    fn search(&self, judge: impl Fn(&String) -> bool, spec: &str) -> Result<String, String> {
+        match self.history.iter().rev().find(|h| judge(h)) {
+            Some(h) => Ok(h.clone()),
+            None    => Err(self.event_error(spec)),
+        }
+    }
+
+    fn event(&mut self, current: &str) -> Result<String, String> {
+        match self.peek(0) {
+            Some('!') => {
+                self.pos += 1;
+                self.search(|_| true, "!")
+            },
+            Some('#') => {
+                self.pos += 1;
+                Ok(current.to_string())
+            },
+            Some('?') => {
+                self.pos += 1;
+                let s = self.take_while(|c| c != '?' && c != '\n');
+                if self.peek(0) == Some('?') {
+                    self.pos += 1;
+                }
+                self.search(|h| h.contains(&s), &("?".to_owned() + &s))
+            },
+            Some(c) if c.is_ascii_digit() || c == '-' => {
+                let sign = match c == '-' {
+                    true  => { self.pos += 1; "-" },
+                    false => "",
+                };
+                let num = self.take_while(|c| c.is_ascii_digit());
+                let n = num.parse::<usize>().unwrap_or(0);
+                let len = self.history.len();
+                let index = match sign {
+                    "-" if n > 0 && n <= len => Some(len - n),
+                    "" if n > 0 && n <= len => Some(n - 1),
+                    _ => None,
+                };
+                match index {
+                    Some(i) => Ok(self.history[i].clone()),
+                    None    => Err(self.event_error(&(sign.to_owned() + &num))),
+                }
+            },
+            Some(c) if "^$*:".contains(c) => self.search(|_| true, ""),
+            _ => {
+                let s = self.take_while(|c| ! " \t\n:;&|<>()".contains(c));
+                self.search(|h| h.starts_with(&s), &s)
+            },
+        }
+    }
+
+    fn word_num(&mut self, last: usize) -> Option<usize> {
+        match self.peek(0) {
+            Some('^') => { self.pos += 1; Some(1) },
+            Some('$') => { self.pos += 1; Some(last) },
+            Some(c) if c.is_ascii_digit() => self.take_while(|c| c.is_ascii_digit()).parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn word_designator(&mut self, event: String) -> Result<String, String> {
+        match (self.peek(0), self.peek(1)) {
+            (Some(':'), Some(c)) if c.is_ascii_digit() || "^$*-".contains(c) => self.pos += 1,
+            (Some(c), _) if "^$*".contains(c) => {},
+            _ => return Ok(event),
+        }
+
+        let words = utils::split_words(&event);
+        let last = words.len().saturating_sub(1);
+        let (from, to) = match self.peek(0) {
+            Some('*') => {
+                self.pos += 1;
+                (1, last)
+            },
+            Some('-') => {
+                self.pos += 1;
+                (0, self.word_num(last).unwrap_or(last.saturating_sub(1)))
+            },
+            _ => {
+                let from = self.word_num(last).unwrap_or(0);
+                match self.peek(0) {
+                    Some('*') => { self.pos += 1; (from, last) },
+                    Some('-') => {
+                        self.pos += 1;
+                        (from, self.word_num(last).unwrap_or(last.saturating_sub(1)))
+                    },
+                    _ => (from, from),
+                }
+            },
+        };
+
+        if from > to {
+            return match from == last + 1 { //e.g. !* for a command without arguments
+                true  => Ok(String::new()),
+                false => Err("bad word specifier".to_string()),
+            };
+        }
+        match words.get(from..=to) {
+            Some(ws) => Ok(ws.join(" ")),
+            None     => Err("bad word specifier".to_string()),
+        }
+    }
+
+    fn substitute(&mut self, text: &str, global: bool) -> Result<String, String> {
+        let start = self.pos;
+        let delim = match self.peek(0) {
+            Some(c) => { self.pos += 1; c },
+            None    => return Err("bad word specifier".to_string()),
+        };
+
+        let mut parts = [String::new(), String::new()];
+        for part in parts.iter_mut() {
+            while let Some(c) = self.peek(0) {
+                if c == '\n' {
+                    break;
+                }
+                self.pos += 1;
+                if c == delim {
+                    break;
+                }
+                match (c, self.peek(0)) {
+                    ('\\', Some(d)) if d == delim => {
+                        part.push(d);
+                        self.pos += 1;
+                    },
+                    _ => part.push(c),
+                }
+            }
+        }
+
+        let old = match parts[0].as_str() {
+            "" => match self.last_subst.as_ref() {
+                Some(s) => s.0.clone(),
+                None    => return Err("no previous substitution".to_string()),
+            },
+            s => s.to_string(),
+        };
+        let new = parts[1].replace("&", &old);
+        self.last_subst = Some((old, new));
+
+        let spec: String = self.chars[start-1..self.pos].iter().collect();
+        self.apply_substitution(text, global, &spec)
+    }
+
+    fn apply_substitution(&self, text: &str, global: bool, spec: &str) -> Result<String, String> {
+        let (old, new) = match self.last_subst.as_ref() {
+            Some(s) => s,
+            None    => return Err(format!(":{}: no previous substitution", spec)),
+        };
+        if ! text.contains(old.as_str()) {
+            return Err(format!(":{}: substitution failed", spec));
+        }
+        match global {
+            true  => Ok(text.replace(old.as_str(), new)),
+            false => Ok(text.replacen(old.as_str(), new, 1)),
+        }
+    }
+
+    fn modifiers(&mut self, mut text: String) -> Result<String, String> {
+        while self.peek(0) == Some(':') {
+            let global = match self.peek(1) {
+                Some('g') | Some('a') => { self.pos += 1; true },
+                _ => false,
+            };
+
+            let c = match self.peek(1) {
+                Some(c) if "htrepqs&".contains(c) => c,
+                _ => break,
+            };
+            self.pos += 2;
+
+            text = match c {
+                'h' => match text.rfind('/') {
+                    Some(0) => "/".to_string(),
+                    Some(n) => text[..n].to_string(),
+                    None    => text,
+                },
+                't' => text.rsplit('/').next().unwrap_or("").to_string(),
+                'r' => match text.rfind('.') {
+                    Some(n) if ! text[n..].contains('/') => text[..n].to_string(),
+                    _ => text,
+                },
+                'e' => match text.rfind('.') {
+                    Some(n) if ! text[n..].contains('/') => text[n..].to_string(),
+                    _ => text,
+                },
+                'p' => { self.print_only = true; text },
+                'q' => utils::escape::single_quote(&text),
+                's' => self.substitute(&text, global)?,
+                _   => self.apply_substitution(&text, global, "&")?,
+            };
+        }
+        Ok(text)
+    }
+
+    fn expand_one(&mut self, current: &str) -> Result<String, String> {
+        let event = self.event(current)?;
+        let words = self.word_designator(event)?;
+        self.modifiers(words)
+    }
+
+    /* ^old^new^ is !!:s/old/new/ */
+    fn quick_substitution(&mut self) -> Result<String, String> {
+        let prev = self.search(|_| true, "!")?;
+        let line: String = self.chars.iter().collect();
+        let (body, newline) = match line.strip_suffix('\n') {
+            Some(b) => (b.to_string(), "\n"),
+            None    => (line.clone(), ""),
+        };
+
+        let parts: Vec<&str> = body[1..].splitn(3, '^').collect();
+        let old = parts[0].to_string();
+        let new = parts.get(1).unwrap_or(&"").to_string();
+        self.last_subst = Some((old, new));
+
+        let rest = parts.get(2).unwrap_or(&"").to_string();
+        Ok(self.apply_substitution(&prev, false, &body)? + &rest + newline)
+    }
+}
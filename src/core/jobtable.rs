@@ -106,6 +106,10 @@ impl JobEntry {
         exit_status
     }
 
+    pub fn is_stopped(&self) -> bool {
+        self.display_status == "Stopped"
+    }
+
     pub fn print(&self, priority: &Vec<usize>) {
         if priority[0] == self.id {
             println!("[{}]+  {}     {}", self.id, &self.display_status, &self.text);
@@ -184,6 +188,12 @@ impl ShellCore {
         for e in self.job_table.iter_mut() {
             e.update_status(false);
         }
+
+        self.process_substitution_pids.retain(|pid| {
+            let mut status = WaitStatus::StillAlive;
+            wait_nonblock(pid, &mut status);
+            still(&status)
+        });
     }
 
     pub fn jobtable_print_status_change(&mut self) {
@@ -0,0 +1,424 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::ShellCore;
+use std::collections::HashMap;
+use std::fs;
+use termion::event;
+use termion::event::{Event, Key};
+
+/* names of the editing functions in the readline manner */
+pub const FUNCTIONS: [&str; 38] = [
+    "abort", "accept-line", "backward-char", "backward-delete-char",
+    "backward-kill-word", "backward-word", "beginning-of-line", "capitalize-word",
+    "clear-screen", "complete", "delete-char", "downcase-word",
+    "edit-and-execute-command", "end-of-line", "forward-char", "forward-search-history",
+    "forward-word", "history-search-backward", "history-search-forward", "kill-line",
+    "kill-word", "next-history", "previous-history", "reverse-search-history",
+    "self-insert", "transpose-chars", "transpose-words", "undo",
+    "unix-line-discard", "unix-word-rubout", "upcase-word", "yank",
+    "yank-last-arg", "yank-pop", "beginning-of-history", "end-of-history",
+    "quoted-insert", "force-accept-line",
+];
+
+const DEFAULT_BINDINGS: [(&str, &str); 52] = [
+    ("\\C-a", "beginning-of-line"), ("\\C-b", "backward-char"),
+    ("\\C-d", "delete-char"), ("\\C-e", "end-of-line"),
+    ("\\C-f", "forward-char"), ("\\C-g", "abort"),
+    ("\\C-h", "backward-delete-char"), ("\\C-i", "complete"),
+    ("\\C-j", "accept-line"), ("\\C-k", "kill-line"),
+    ("\\C-l", "clear-screen"), ("\\C-n", "next-history"),
+    ("\\C-p", "previous-history"), ("\\C-q", "quoted-insert"),
+    ("\\C-r", "reverse-search-history"), ("\\C-s", "forward-search-history"),
+    ("\\C-t", "transpose-chars"), ("\\C-u", "unix-line-discard"),
+    ("\\C-v", "quoted-insert"), ("\\C-w", "unix-word-rubout"),
+    ("\\C-y", "yank"), ("\\C-_", "undo"),
+    ("\\C-?", "backward-delete-char"), ("\\C-x\\C-e", "edit-and-execute-command"),
+    ("\\C-x\\C-u", "undo"), ("\\e<", "beginning-of-history"),
+    ("\\e>", "end-of-history"), ("\\e.", "yank-last-arg"),
+    ("\\e_", "yank-last-arg"), ("\\eb", "backward-word"),
+    ("\\ec", "capitalize-word"), ("\\ed", "kill-word"),
+    ("\\ef", "forward-word"), ("\\el", "downcase-word"),
+    ("\\et", "transpose-words"), ("\\eu", "upcase-word"),
+    ("\\ey", "yank-pop"), ("\\e\\C-?", "backward-kill-word"),
+    ("\\e\\C-h", "backward-kill-word"), ("\\e[A", "history-search-backward"),
+    ("\\e[B", "history-search-forward"), ("\\e[C", "forward-char"),
+    ("\\e[D", "backward-char"), ("\\e[H", "beginning-of-line"),
+    ("\\e[F", "end-of-line"), ("\\e[1~", "beginning-of-line"),
+    ("\\e[4~", "end-of-line"), ("\\e[3~", "delete-char"),
+    ("\\e[5~", "beginning-of-history"), ("\\e[6~", "end-of-history"),
+    ("\\e\\C-m", "force-accept-line"), ("\\e\\C-j", "force-accept-line"),
+];
+
+const DEFAULT_VARIABLES: [(&str, &str); 7] = [
+    ("bell-style", "audible"), ("completion-fuzzy", "off"),
+    ("completion-ignore-case", "off"), ("completion-map-case", "off"),
+    ("editing-mode", "emacs"), ("show-all-if-ambiguous", "off"),
+    ("show-mode-in-prompt", "off"),
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Binding {
+    Function(String),
+    Macro(String),
+    Command(String), // bind -x
+}
+
+pub enum Lookup {
+    Found(Binding),
+    Prefix,
+    NotFound,
+}
+
+#[derive(Debug)]
+pub struct KeyMap {
+    pub bindings: HashMap<Vec<Key>, Binding>,
+    pub variables: HashMap<String, String>,
+}
+
+fn control(c: char) -> Option<u8> {
+    match c {
+        '?' => Some(0x7F),
+        '@' => Some(0),
+        c if c.is_ascii() => Some(c.to_ascii_lowercase() as u8 & 0x1F),
+        _ => None,
+    }
+}
+
+/* readline escapes in a quoted key sequence to bytes */
+fn unescape(s: &str) -> Option<Vec<u8>> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut ans = vec![];
+    let mut pos = 0;
+    let mut meta = false;
+
+    while pos < chars.len() {
+        let mut bytes = vec![];
+        if chars[pos] != '\\' || pos + 1 >= chars.len() {
+            let mut buf = [0; 4];
+            bytes.extend(chars[pos].encode_utf8(&mut buf).bytes());
+            pos += 1;
+        }else{
+            let rest: String = chars[pos+1..].iter().collect();
+            pos += 2;
+            match chars[pos-1] {
+                'C' if rest.starts_with("C-") => {
+                    if rest[2..].starts_with("\\M-") {
+                        bytes.push(0x1B);
+                        pos += 3;
+                    }
+                    bytes.push(control(*chars.get(pos + 1)?)?);
+                    pos += 2;
+                },
+                'M' if rest.starts_with("M-") => {
+                    meta = true;
+                    pos += 1;
+                    continue;
+                },
+                'e' => bytes.push(0x1B),
+                'a' => bytes.push(0x07),
+                'b' => bytes.push(0x08),
+                'd' => bytes.push(0x7F),
+                'f' => bytes.push(0x0C),
+                'n' => bytes.push(b'\n'),
+                'r' => bytes.push(b'\r'),
+                't' => bytes.push(b'\t'),
+                'v' => bytes.push(0x0B),
+                'x' => {
+                    let hex: String = chars[pos..].iter().take(2).take_while(|c| c.is_ascii_hexdigit()).collect();
+                    pos += hex.len();
+                    bytes.push(u8::from_str_radix(&hex, 16).ok()?);
+                },
+                c if c.is_digit(8) => {
+                    let oct: String = chars[pos-1..].iter().take(3).take_while(|c| c.is_digit(8)).collect();
+                    pos += oct.len() - 1;
+                    bytes.push(u8::from_str_radix(&oct, 8).ok()?);
+                },
+                c => {
+                    let mut buf = [0; 4];
+                    bytes.extend(c.encode_utf8(&mut buf).bytes());
+                },
+            }
+        }
+
+        if meta {
+            ans.push(0x1B);
+            meta = false;
+        }
+        ans.extend(bytes);
+    }
+    Some(ans)
+}
+
+/* a key sequence in readline notation (without the quotes) to keys */
+pub fn parse_sequence(s: &str) -> Option<Vec<Key>> {
+    let bytes = unescape(s)?;
+    let mut iter = bytes.into_iter().map(Ok);
+    let mut ans = vec![];
+    while let Some(Ok(b)) = iter.next() {
+        let mut rest = iter.clone();
+        match event::parse_event(b, &mut rest) {
+            Ok(Event::Key(k)) => {
+                ans.push(k);
+                iter = rest;
+            },
+            _ if b == 0x1B => ans.push(Key::Esc),
+            _ => return None,
+        }
+    }
+    match ans.len() {
+        0 => None,
+        _ => Some(ans),
+    }
+}
+
+/* Control-x, Meta-x, Rubout and so on */
+fn parse_keyname(s: &str) -> Option<Vec<Key>> {
+    let mut seq = String::new();
+    let mut name = s;
+    loop {
+        if let Some(n) = name.strip_prefix("Control-").or(name.strip_prefix("C-")) {
+            seq += "\\C-";
+            name = n;
+        }else if let Some(n) = name.strip_prefix("Meta-").or(name.strip_prefix("M-")) {
+            seq += "\\M-";
+            name = n;
+        }else{
+            break;
+        }
+    }
+
+    let key = match name.to_lowercase().as_str() {
+        "rubout" | "del" => "\\d",
+        "escape" | "esc" => "\\e",
+        "lfd" | "newline" => "\\n",
+        "ret" | "return" => "\\r",
+        "spc" | "space" => " ",
+        "tab" => "\\t",
+        _ => name,
+    };
+    parse_sequence(&(seq + key))
+}
+
+fn char_to_string(c: char) -> String {
+    match c {
+        '\n' => "\\C-m".to_string(),
+        '\t' => "\\C-i".to_string(),
+        '"'  => "\\\"".to_string(),
+        '\\' => "\\\\".to_string(),
+        '\x1b' => "\\e".to_string(),
+        '\x7f' => "\\C-?".to_string(),
+        c if (c as u32) < 0x20 => format!("\\C-{}", ((c as u8) + 0x60) as char),
+        c => c.to_string(),
+    }
+}
+
+pub fn key_to_string(key: &Key) -> String {
+    match key {
+        Key::Char(c) => char_to_string(*c),
+        Key::Ctrl(c) => match c {
+            '4' => "\\C-\\\\".to_string(),
+            '5' => "\\C-]".to_string(),
+            '6' => "\\C-^".to_string(),
+            '7' => "\\C-_".to_string(),
+            c   => format!("\\C-{}", c),
+        },
+        Key::Alt(c) => "\\e".to_owned() + &char_to_string(*c),
+        Key::Backspace => "\\C-?".to_string(),
+        Key::Esc => "\\e".to_string(),
+        Key::Null => "\\C-@".to_string(),
+        Key::Up => "\\e[A".to_string(),
+        Key::Down => "\\e[B".to_string(),
+        Key::Right => "\\e[C".to_string(),
+        Key::Left => "\\e[D".to_string(),
+        Key::Home => "\\e[H".to_string(),
+        Key::End => "\\e[F".to_string(),
+        Key::Insert => "\\e[2~".to_string(),
+        Key::Delete => "\\e[3~".to_string(),
+        Key::PageUp => "\\e[5~".to_string(),
+        Key::PageDown => "\\e[6~".to_string(),
+        Key::BackTab => "\\e[Z".to_string(),
+        Key::F(n) => format!("\\e[{}~", n),
+        _ => String::new(),
+    }
+}
+
+pub fn sequence_to_string(keys: &[Key]) -> String {
+    keys.iter().map(key_to_string).collect()
+}
+
+/* the position of the closing quote */
+fn closing_quote(s: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in s.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == quote => return Some(i),
+            _ => {},
+        }
+    }
+    None
+}
+
+/* "seq" or keyname before the colon, and the rest */
+pub fn split_binding(line: &str) -> Result<(Vec<Key>, String), String> {
+    let line = line.trim();
+    let error = || format!("{}: cannot parse the key sequence", line);
+    let (seq, rest) = match line.chars().next() {
+        Some(q) if q == '"' || q == '\'' => {
+            let end = closing_quote(line, q).ok_or_else(error)?;
+            let rest = line[end+1..].trim_start().strip_prefix(':').ok_or_else(error)?;
+            (parse_sequence(&line[1..end]), rest)
+        },
+        _ => {
+            let (name, rest) = line.split_once(':').ok_or_else(error)?;
+            (parse_keyname(name.trim()), rest)
+        },
+    };
+
+    match seq {
+        Some(s) => Ok((s, rest.trim().to_string())),
+        None    => Err(error()),
+    }
+}
+
+impl KeyMap {
+    pub fn new() -> KeyMap {
+        let mut keymap = KeyMap {
+            bindings: HashMap::new(),
+            variables: HashMap::new(),
+        };
+
+        for (seq, func) in DEFAULT_BINDINGS {
+            if let Some(keys) = parse_sequence(seq) {
+                keymap.bindings.insert(keys, Binding::Function(func.to_string()));
+            }
+        }
+        for (name, value) in DEFAULT_VARIABLES {
+            keymap.variables.insert(name.to_string(), value.to_string());
+        }
+        keymap
+    }
+
+    pub fn lookup(&self, keys: &[Key]) -> Lookup {
+        if let Some(b) = self.bindings.get(keys) {
+            return Lookup::Found(b.clone());
+        }
+        match self.bindings.keys().any(|k| k.len() > keys.len() && k.starts_with(keys)) {
+            true  => Lookup::Prefix,
+            false => Lookup::NotFound,
+        }
+    }
+
+    pub fn query(&self, name: &str) -> bool {
+        self.variables.get(name).map(|v| v == "on" || v == "On" || v == "1").unwrap_or(false)
+    }
+
+    pub fn set_variable(&mut self, name: &str, value: &str) {
+        self.variables.insert(name.to_string(), value.to_string());
+    }
+
+    /* a line of inputrc: "set name value" or a key binding */
+    pub fn read_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return Ok(());
+        }
+
+        if let Some(rest) = line.strip_prefix("set ") {
+            let mut words = rest.split_whitespace();
+            let name = words.next().unwrap_or("");
+            let value = words.next().unwrap_or("");
+            self.set_variable(name, value);
+            return Ok(());
+        }
+
+        let (seq, target) = split_binding(line)?;
+        let binding = match target.chars().next() {
+            Some(q) if q == '"' || q == '\'' => {
+                let end = closing_quote(&target, q).unwrap_or(target.len());
+                let text = unescape(&target[1..end]).unwrap_or_default();
+                Binding::Macro(String::from_utf8_lossy(&text).to_string())
+            },
+            _ => {
+                let name = target.split_whitespace().next().unwrap_or("");
+                if ! FUNCTIONS.contains(&name) {
+                    return Err(format!("{}: unknown function name", name));
+                }
+                Binding::Function(name.to_string())
+            },
+        };
+        self.bindings.insert(seq, binding);
+        Ok(())
+    }
+
+    /* mode: "emacs" or "vi" for $if */
+    pub fn read_file(&mut self, path: &str, mode: &str, depth: usize) -> Result<(), String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut enabled = vec![true]; // the stack of $if
+        let mut keymap = "emacs".to_string();
+
+        for line in text.lines() {
+            let line = line.trim();
+            let active = enabled.iter().all(|e| *e);
+            if let Some(cond) = line.strip_prefix("$if") {
+                let cond = cond.trim();
+                enabled.push(match cond.split_once('=') {
+                    Some(("mode", m)) => m.trim() == mode,
+                    Some(_) => false,
+                    None => cond == "Bash" || cond == "sush",
+                });
+            }else if line.starts_with("$else") {
+                if let Some(e) = enabled.last_mut() {
+                    *e = ! *e;
+                }
+            }else if line.starts_with("$endif") {
+                if enabled.len() > 1 {
+                    enabled.pop();
+                }
+            }else if ! active {
+                continue;
+            }else if let Some(file) = line.strip_prefix("$include") {
+                if depth < 10 {
+                    let _ = self.read_file(file.trim(), mode, depth + 1);
+                }
+            }else if let Some(name) = line.strip_prefix("set keymap") {
+                keymap = name.trim().to_string();
+            }else if keymap.starts_with("emacs") || keymap == "vi-insert" || line.starts_with("set ") {
+                let _ = self.read_line(line);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ShellCore {
+    /* INPUTRC, ~/.inputrc or /etc/inputrc */
+    pub fn read_inputrc(&mut self) {
+        if ! self.data.flags.contains('i') {
+            return;
+        }
+
+        let home = self.data.get_param("HOME");
+        let path = match self.data.get_param("INPUTRC") {
+            p if ! p.is_empty() => p,
+            _ if fs::metadata(home.clone() + "/.inputrc").is_ok() => home + "/.inputrc",
+            _ => "/etc/inputrc".to_string(),
+        };
+
+        let mode = match self.options.query("vi") {
+            true  => "vi",
+            false => "emacs",
+        };
+        let _ = self.keymap.read_file(&path, mode, 0);
+
+        match self.keymap.variables["editing-mode"].as_str() {
+            "vi" if mode == "emacs" => {
+                self.options.set("vi", true);
+                self.options.set("emacs", false);
+            },
+            _ => {},
+        }
+    }
+}
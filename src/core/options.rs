@@ -3,6 +3,17 @@
 
 use std::collections::HashMap;
 
+/* options of set that also have single-letter flags in $- */
+pub const FLAGS: [(char, &str); 15] = [
+    ('a', "allexport"), ('b', "notify"), ('e', "errexit"), ('f', "noglob"),
+    ('h', "hashall"), ('m', "monitor"), ('n', "noexec"), ('u', "nounset"),
+    ('v', "verbose"), ('x', "xtrace"), ('B', "braceexpand"), ('C', "noclobber"),
+    ('E', "errtrace"), ('H', "histexpand"), ('T', "functrace"),
+];
+
+/* the order of the letters in $- */
+pub const FLAG_ORDER: &str = "abefhikmnptuvxBCEHPTc";
+
 #[derive(Debug)]
 pub struct Options {
     opts: HashMap<String, bool>,
@@ -14,11 +25,28 @@ impl Options {
             opts: HashMap::new(),
         };
 
-        options.opts.insert("pipefail".to_string(), false);
+        for (_, opt) in FLAGS {
+            options.opts.insert(opt.to_string(), false);
+        }
+        for opt in ["emacs", "history", "ignoreeof", "pipefail", "posix", "vi"] {
+            options.opts.insert(opt.to_string(), false);
+        }
 
         options
     }
 
+    pub fn flag_to_name(flag: char) -> Option<&'static str> {
+        FLAGS.iter().find(|f| f.0 == flag).map(|f| f.1)
+    }
+
+    pub fn name_to_flag(name: &str) -> Option<char> {
+        FLAGS.iter().find(|f| f.1 == name).map(|f| f.0)
+    }
+
+    pub fn exist(&self, opt: &str) -> bool {
+        self.opts.contains_key(opt)
+    }
+
     pub fn new_as_shopts() -> Options {
         let mut options = Options {
             opts: HashMap::new(),
@@ -41,7 +69,18 @@ impl Options {
             options.opts.insert(opt.to_string(), false);
         }*/
 
+        options.opts.insert("dotglob".to_string(), false);
         options.opts.insert("extglob".to_string(), true);
+        options.opts.insert("failglob".to_string(), false);
+        options.opts.insert("globstar".to_string(), false);
+        options.opts.insert("highlight".to_string(), false);
+        options.opts.insert("histappend".to_string(), false);
+        options.opts.insert("histreedit".to_string(), false);
+        options.opts.insert("histverify".to_string(), false);
+        options.opts.insert("nocaseglob".to_string(), false);
+        options.opts.insert("nullglob".to_string(), false);
+        options.opts.insert("promptvars".to_string(), true);
+        options.opts.insert("xpg_echo".to_string(), false);
 
         options
     }
@@ -108,6 +147,12 @@ impl Options {
         list.iter().for_each(|e| println!("{}", e));
     }
 
+    pub fn get_keys(&self) -> Vec<String> {
+        let mut ans: Vec<String> = self.opts.keys().cloned().collect();
+        ans.sort();
+        ans
+    }
+
     pub fn query(&self, opt: &str) -> bool {
         self.opts.contains_key(opt) && self.opts[opt]
     }
@@ -0,0 +1,160 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{signal, Feeder, Script, ShellCore};
+use nix::sys::signal::{SigHandler, Signal};
+use std::str::FromStr;
+
+pub const EXIT: i32 = 0;
+pub const DEBUG: i32 = 65;
+pub const ERR: i32 = 66;
+pub const RETURN: i32 = 67;
+
+pub fn to_number(spec: &str) -> Option<i32> {
+    if let Ok(n) = spec.parse::<i32>() {
+        return match n == EXIT || Signal::try_from(n).is_ok() {
+            true  => Some(n),
+            false => None,
+        };
+    }
+
+    let name = spec.to_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+    match name {
+        "EXIT"   => Some(EXIT),
+        "DEBUG"  => Some(DEBUG),
+        "ERR"    => Some(ERR),
+        "RETURN" => Some(RETURN),
+        _ => Signal::from_str(&("SIG".to_owned() + name)).ok().map(|s| s as i32),
+    }
+}
+
+pub fn to_name(num: i32) -> String {
+    match num {
+        EXIT   => "EXIT".to_string(),
+        DEBUG  => "DEBUG".to_string(),
+        ERR    => "ERR".to_string(),
+        RETURN => "RETURN".to_string(),
+        _ => match Signal::try_from(num) {
+            Ok(s)  => s.as_str().to_string(),
+            Err(_) => num.to_string(),
+        },
+    }
+}
+
+impl ShellCore {
+    /* action: None -> default, Some("") -> ignore, Some(com) -> run com */
+    pub fn set_trap(&mut self, num: i32, action: Option<&str>) -> bool {
+        self.parent_traps.clear();
+        if let Ok(sig) = Signal::try_from(num) {
+            let old = match action {
+                None     => {
+                    if let Some(act) = self.trap_default_actions.remove(&num) {
+                        signal::restore_action(sig, &act);
+                    }
+                    None
+                },
+                Some("") => signal::set_handler(sig, SigHandler::SigIgn),
+                Some(_)  => signal::catch(sig),
+            };
+
+            if action.is_some() {
+                match old {
+                    Some(act) => { self.trap_default_actions.entry(num).or_insert(act); },
+                    None      => return false,
+                }
+            }
+        }
+
+        match action {
+            Some(com) => self.traps.insert(num, com.to_string()),
+            None      => self.traps.remove(&num),
+        };
+        true
+    }
+
+    pub fn run_trap(&mut self, num: i32) {
+        if self.trap_running {
+            return;
+        }
+
+        match self.traps.get(&num) {
+            Some(com) if ! com.is_empty() => self.exec_trap_command(&com.clone()),
+            _ => {},
+        }
+    }
+
+    pub fn run_exit_trap(&mut self) {
+        if let Some(com) = self.traps.remove(&EXIT) {
+            self.exec_trap_command(&com);
+        }
+    }
+
+    pub fn check_trapped_signals(&mut self) {
+        for num in 1..DEBUG {
+            if signal::take_trapped(num) {
+                self.run_trap(num);
+            }
+        }
+    }
+
+    fn exec_trap_command(&mut self, com: &str) {
+        let exit_status = self.data.get_param("?");
+        let running = self.trap_running;
+        self.trap_running = true;
+
+        let mut feeder = Feeder::new(com);
+        if let Some(mut s) = Script::parse(&mut feeder, self, false) {
+            s.exec(self);
+        }
+
+        self.trap_running = running;
+        self.data.set_param("?", &exit_status);
+    }
+
+    /* DEBUG and RETURN traps need functrace (-T), ERR needs errtrace (-E) to be inherited by functions */
+    pub fn hide_function_traps(&mut self) -> Vec<(i32, String)> {
+        let mut nums = vec![];
+        if ! self.data.flags.contains('T') {
+            nums.extend([DEBUG, RETURN]);
+        }
+        if ! self.data.flags.contains('E') {
+            nums.push(ERR);
+        }
+
+        nums.iter()
+            .filter_map(|n| self.traps.remove(n).map(|com| (*n, com)))
+            .collect()
+    }
+
+    pub fn restore_function_traps(&mut self, hidden: Vec<(i32, String)>) {
+        for (num, com) in hidden {
+            self.traps.entry(num).or_insert(com);
+        }
+    }
+
+    /* POSIX: caught signals are reset to the defaults in a subshell, ignored ones stay ignored.
+     * the commands of the caught ones are still shown by trap -p */
+    pub fn reset_traps_for_subshell(&mut self) {
+        let nums: Vec<i32> = self.traps.iter()
+                             .filter(|(_, com)| com.as_str() != "")
+                             .map(|(num, _)| *num).collect();
+
+        self.parent_traps.clear();
+        for num in nums {
+            if let Some(com) = self.traps.remove(&num) {
+                self.parent_traps.insert(num, com);
+            }
+            if let Ok(sig) = Signal::try_from(num) {
+                signal::set_handler(sig, SigHandler::SigDfl);
+                self.trap_default_actions.remove(&num);
+            }
+        }
+
+        for num in self.traps.keys() {
+            if let Ok(sig) = Signal::try_from(*num) {
+                signal::set_handler(sig, SigHandler::SigIgn);
+            }
+        }
+    }
+}
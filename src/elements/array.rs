@@ -3,22 +3,52 @@
 
 use crate::{ShellCore, Feeder};
 use crate::elements::command;
+use super::subscript::Subscript;
 use super::word::Word;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug, Clone)]
 pub struct Array {
     pub text: String,
-    pub words: Vec<Word>,
+    pub words: Vec<(Option<Subscript>, Word)>,
 }
 
 impl Array {
-    pub fn eval(&mut self, core: &mut ShellCore) -> Option<Vec<String>> {
-        let mut ans = vec![];
+    pub fn eval(&mut self, core: &mut ShellCore) -> Option<BTreeMap<usize, String>> {
+        let mut ans = BTreeMap::new();
+        let mut pos = 0;
 
-        for w in &mut self.words {
-            match w.eval(core) {
-                None     => return None,
-                Some(ws) => ans.extend(ws),
+        for (s, w) in &mut self.words {
+            let values = match s {
+                Some(s) => {
+                    pos = s.eval_as_index(core, "")?.parse::<usize>().ok()?;
+                    vec![w.eval_as_value(core)?]
+                },
+                None => w.eval(core)?,
+            };
+
+            for v in values {
+                ans.insert(pos, v);
+                pos += 1;
+            }
+        }
+
+        Some(ans)
+    }
+
+    pub fn eval_as_assoc(&mut self, core: &mut ShellCore, name: &str) -> Option<HashMap<String, String>> {
+        let mut ans = HashMap::new();
+
+        for (s, w) in &mut self.words {
+            match s {
+                Some(s) => {
+                    let key = s.eval_as_key(core)?;
+                    ans.insert(key, w.eval_as_value(core)?);
+                },
+                None => {
+                    eprintln!("sush: {}: {}: must use subscript when assigning associative array", name, &w.text);
+                    return None;
+                },
             }
         }
 
@@ -37,15 +67,33 @@ impl Array {
             return false;
         }
 
+        let subscript = Self::eat_subscript(feeder, ans, core);
+
         let w = match Word::parse(feeder, core, false) {
             Some(w) => w,
+            _ if subscript.is_some() => Word::new(),
             _       => return false,
         };
         ans.text += &w.text;
-        ans.words.push(w);
+        ans.words.push((subscript, w));
         true
     }
 
+    fn eat_subscript(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> Option<Subscript> {
+        feeder.set_backup();
+        if let Some(s) = Subscript::parse(feeder, core) {
+            if feeder.starts_with("=") {
+                feeder.pop_backup();
+                ans.text += &s.text;
+                ans.text += &feeder.consume(1);
+                return Some(s);
+            }
+        }
+
+        feeder.rewind();
+        None
+    }
+
     pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Array> {
         if ! feeder.starts_with("(") {
             return None;
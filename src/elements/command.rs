@@ -7,12 +7,14 @@ pub mod simple;
 pub mod paren;
 pub mod brace;
 pub mod r#for;
+pub mod select;
 pub mod test;
 pub mod function_def;
 pub mod r#while;
 pub mod r#if;
 
 use crate::{ShellCore, Feeder, Script};
+use crate::feeder::terminal::highlight::Color;
 use self::arithmetic::ArithmeticCommand;
 use self::case::CaseCommand;
 use self::simple::SimpleCommand;
@@ -21,6 +23,7 @@ use self::brace::BraceCommand;
 use self::function_def::FunctionDefinition;
 use self::r#while::WhileCommand;
 use self::r#for::ForCommand;
+use self::select::SelectCommand;
 use self::r#if::IfCommand;
 use self::test::TestCommand;
 use std::fmt;
@@ -92,10 +95,24 @@ pub fn eat_inner_script(feeder: &mut Feeder, core: &mut ShellCore,
         return false;
     }
     feeder.nest.push( (left.to_string(), right.iter().map(|e| e.to_string()).collect()) );
+    paint_keyword(feeder, left);
     feeder.consume(left.len());
     *ans = Script::parse(feeder, core, permit_empty);
     feeder.nest.pop();
-    ! ans.is_none()
+    if ans.is_none() {
+        return false;
+    }
+    if let Some(end) = right.iter().find(|e| feeder.starts_with(e)) {
+        paint_keyword(feeder, end);
+    }
+    true
+}
+
+/* marks the reserved word at the head for highlighting. Parentheses and ;; are not */
+fn paint_keyword(feeder: &mut Feeder, word: &str) {
+    if word == "{" || word == "}" || word.chars().all(|c| c.is_ascii_lowercase()) {
+        feeder.paint(word.len(), Color::Keyword);
+    }
 }
 
 pub fn eat_blank_with_comment(feeder: &mut Feeder, core: &mut ShellCore, ans_text: &mut String) -> bool {
@@ -140,6 +157,7 @@ pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Box<dyn Comman
     else if let Some(a) = BraceCommand::parse(feeder, core) { Some(Box::new(a)) }
     else if let Some(a) = ForCommand::parse(feeder, core) { Some(Box::new(a)) }
     else if let Some(a) = WhileCommand::parse(feeder, core) { Some(Box::new(a)) }
+    else if let Some(a) = SelectCommand::parse(feeder, core) { Some(Box::new(a)) }
     else if let Some(a) = CaseCommand::parse(feeder, core) { Some(Box::new(a)) }
     else if let Some(a) = TestCommand::parse(feeder, core) { Some(Box::new(a)) }
     else{ None }
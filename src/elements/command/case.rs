@@ -3,6 +3,7 @@
 
 use crate::{error_message, ShellCore, Feeder, Script};
 use crate::elements::command;
+use crate::feeder::terminal::highlight::Color;
 use crate::elements::word::Word;
 use crate::utils::glob;
 use super::{Command, Redirect};
@@ -115,12 +116,14 @@ impl CaseCommand {
         }
 
         let mut ans = Self::new();
+        feeder.paint(4, Color::Keyword);
         ans.text = feeder.consume(4);
 
         if ! Self::eat_word(feeder, &mut ans, core) 
         || ! feeder.starts_with("in") {
             return None;
         }
+        feeder.paint(2, Color::Keyword);
         ans.text += &feeder.consume(2);
 
         loop {
@@ -130,6 +133,7 @@ impl CaseCommand {
             }
 
             if feeder.starts_with("esac") {
+                feeder.paint(4, Color::Keyword);
                 ans.text += &feeder.consume(4);
                 break;
             }
@@ -4,6 +4,7 @@
 use crate::{error_message, ShellCore, Feeder, Script};
 use super::{Command, Redirect};
 use crate::elements::command;
+use crate::feeder::terminal::highlight::Color;
 use crate::elements::word::Word;
 use crate::elements::expr::arithmetic::ArithmeticExpr;
 use std::sync::atomic::Ordering::Relaxed;
@@ -37,6 +38,7 @@ impl Command for ForCommand {
         core.loop_level -= 1;
         if core.loop_level == 0 {
             core.break_counter = 0;
+            core.continue_flag = false;
         }
     }
 
@@ -84,6 +86,7 @@ impl ForCommand {
                 core.break_counter -= 1;
                 break;
             }
+            core.continue_flag = false;
         }
         true
     }
@@ -123,6 +126,7 @@ impl ForCommand {
                 core.break_counter -= 1;
                 break;
             }
+            core.continue_flag = false;
 
             let (ok, _) = Self::eval_arithmetic(&mut self.arithmetics[2], core);
             if ! ok {
@@ -205,6 +209,7 @@ impl ForCommand {
             return;
         }
 
+        feeder.paint(2, Color::Keyword);
         ans.text += &feeder.consume(2);
         ans.has_in = true;
 
@@ -236,6 +241,7 @@ impl ForCommand {
             return None;
         }
         let mut ans = Self::new();
+        feeder.paint(3, Color::Keyword);
         ans.text = feeder.consume(3);
 
         if Self::eat_name(feeder, &mut ans, core) {
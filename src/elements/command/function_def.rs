@@ -3,13 +3,15 @@
 
 use crate::{error_message, ShellCore, Feeder};
 use super::{Command, Pipe, Redirect};
+use crate::core::trap;
 use crate::elements::command;
+use crate::feeder::terminal::highlight::Color;
 use crate::elements::command::{BraceCommand, IfCommand, ParenCommand, WhileCommand};
 use nix::unistd::Pid;
 
 fn reserved(w: &str) -> bool {
     match w {
-        "{" | "}" | "while" | "do" | "done" | "if" | "then" | "elif" | "else" | "fi" => true,
+        "{" | "}" | "while" | "until" | "do" | "done" | "if" | "then" | "elif" | "else" | "fi" => true,
         _ => false,
     }
 }
@@ -58,16 +60,22 @@ impl FunctionDefinition {
         let len = core.data.position_parameters.len();
         args[0] = core.data.position_parameters[len-1][0].clone();
         core.data.position_parameters.push(args.to_vec());
+        let layer = core.data.get_layer_num() - 1;
+        core.data.function_layers.push(layer);
 
         let mut dummy = Pipe::new("|".to_string());
 
         core.source_function_level += 1;
+        let hidden_traps = core.hide_function_traps();
         let pid = self.command.clone()
                         .expect(&error_message::internal_str("empty function"))
                         .exec(core, &mut dummy);
         core.return_flag = false;
+        core.run_trap(trap::RETURN);
+        core.restore_function_traps(hidden_traps);
         core.source_function_level -= 1;
 
+        core.data.function_layers.pop();
         core.data.position_parameters.pop();
 
         return pid;
@@ -106,6 +114,7 @@ impl FunctionDefinition {
         feeder.set_backup();
 
         if feeder.starts_with("function") {
+            feeder.paint(8, Color::Keyword);
             ans.text += &feeder.consume(8);
             command::eat_blank_with_comment(feeder, core, &mut ans.text);
         }
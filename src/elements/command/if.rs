@@ -18,7 +18,10 @@ pub struct IfCommand {
 impl Command for IfCommand {
     fn run(&mut self, core: &mut ShellCore, _: bool) {
         for i in 0..self.if_elif_scripts.len() {
+            let suspend_e_option = core.suspend_e_option;
+            core.suspend_e_option = true;
             self.if_elif_scripts[i].exec(core);
+            core.suspend_e_option = suspend_e_option;
             if core.data.get_param("?") == "0" {
                 self.then_scripts[i].exec(core);
                 return;
@@ -27,7 +30,7 @@ impl Command for IfCommand {
 
         match self.else_script.as_mut() {
             Some(s) => s.exec(core),
-            _ => {},
+            _ => core.suspend_e_option = true, //a false condition doesn't trigger errexit
         }
     }
 
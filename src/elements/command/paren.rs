@@ -11,6 +11,7 @@ pub struct ParenCommand {
     text: String,
     script: Option<Script>,
     redirects: Vec<Redirect>,
+    substitution: bool,
 }
 
 impl Command for ParenCommand {
@@ -23,6 +24,11 @@ impl Command for ParenCommand {
             error_message::internal(" (no fork for subshell)");
         }
 
+        if self.substitution && ! core.options.query("posix") {
+            core.data.flags.retain(|f| f != 'e'); //not inherited by $( )
+            core.options.set("errexit", false);
+        }
+
         match self.script {
             Some(ref mut s) => s.exec(core),
             _ => error_message::internal(" (ParenCommand::exec)"),
@@ -42,11 +48,13 @@ impl ParenCommand {
             text: String::new(),
             script: None,
             redirects: vec![],
+            substitution: false,
         }
     }
 
     pub fn parse(feeder: &mut Feeder, core: &mut ShellCore, substitution: bool) -> Option<Self> {
         let mut ans = Self::new();
+        ans.substitution = substitution;
         if command::eat_inner_script(feeder, core, "(", vec![")"], &mut ans.script, substitution) {
             ans.text.push_str("(");
             ans.text.push_str(&ans.script.as_ref().unwrap().get_text());
@@ -0,0 +1,244 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{error_message, ShellCore, Feeder, Script};
+use super::{Command, Redirect};
+use crate::core::builtins::read;
+use crate::elements::command;
+use crate::feeder::terminal::highlight::Color;
+use crate::elements::word::Word;
+use crate::feeder::terminal;
+use std::sync::atomic::Ordering::Relaxed;
+
+#[derive(Debug, Clone)]
+pub struct SelectCommand {
+    text: String,
+    name: String,
+    has_in: bool,
+    values: Vec<Word>,
+    do_script: Option<Script>,
+    redirects: Vec<Redirect>,
+    force_fork: bool,
+}
+
+impl Command for SelectCommand {
+    fn run(&mut self, core: &mut ShellCore, _: bool) {
+        core.loop_level += 1;
+
+        if ! self.run_with_values(core) {
+            core.data.set_param("?", "1");
+        }
+
+        core.loop_level -= 1;
+        if core.loop_level == 0 {
+            core.break_counter = 0;
+            core.continue_flag = false;
+        }
+    }
+
+    fn get_text(&self) -> String { self.text.clone() }
+    fn get_redirects(&mut self) -> &mut Vec<Redirect> { &mut self.redirects }
+    fn set_force_fork(&mut self) { self.force_fork = true; }
+    fn boxed_clone(&self) -> Box<dyn Command> {Box::new(self.clone())}
+    fn force_fork(&self) -> bool { self.force_fork }
+}
+
+impl SelectCommand {
+    fn eval_values(&mut self, core: &mut ShellCore) -> Option<Vec<String>> {
+        let mut ans = vec![];
+        for w in &mut self.values {
+            match w.eval(core) {
+                Some(mut ws) => ans.append(&mut ws),
+                None     => return None,
+            }
+        }
+
+        Some(ans)
+    }
+
+    /* the same layout as bash: numbered items in columns fitting $COLUMNS */
+    fn print_menu(values: &[String], core: &mut ShellCore) {
+        let index_len = values.len().to_string().len();
+        let max_len = values.iter().map(|v| v.chars().count()).max().unwrap_or(0);
+        let width = max_len + index_len + 4;
+
+        let columns = core.data.get_param("COLUMNS").parse::<usize>().unwrap_or(80);
+        let cols = std::cmp::max(columns / width, 1);
+        let mut rows = values.len().div_ceil(cols);
+        if rows == 1 {
+            rows = values.len();
+        }
+
+        let mut menu = String::new();
+        for row in 0..rows {
+            let mut i = row;
+            loop {
+                let item = format!("{:>w$}) {}", i + 1, values[i], w = index_len);
+                i += rows;
+                if i >= values.len() {
+                    menu += &item;
+                    break;
+                }
+                menu += &format!("{:<w$}", item, w = width);
+            }
+            menu += "\n";
+        }
+        eprint!("{}", menu);
+    }
+
+    fn run_with_values(&mut self, core: &mut ShellCore) -> bool {
+        let values = match self.has_in {
+            true  => match self.eval_values(core) {
+                Some(vs) => vs,
+                None     => return false,
+            },
+            false => core.data.get_position_params(),
+        };
+
+        if values.is_empty() {
+            return true;
+        }
+
+        let mut show_menu = true;
+        loop {
+            if core.sigint.load(Relaxed) {
+                return false;
+            }
+
+            if show_menu {
+                Self::print_menu(&values, core);
+                show_menu = false;
+            }
+
+            let ps3 = match core.data.has_value("PS3") {
+                true  => {
+                    let raw = core.data.get_param("PS3");
+                    terminal::expand_prompt(core, &raw)
+                },
+                false => "#? ".to_string(),
+            };
+            eprint!("{}", ps3);
+
+            let mut line = String::new();
+            if read::read_line(&mut line) == 0 {
+                eprintln!();
+                return false;
+            }
+
+            let reply = line.trim().to_string();
+            if reply.is_empty() {
+                show_menu = true;
+                continue;
+            }
+
+            core.data.set_param("REPLY", &reply);
+            let value = match reply.parse::<usize>() {
+                Ok(n) if n >= 1 && n <= values.len() => values[n-1].clone(),
+                _ => String::new(),
+            };
+            core.data.set_param(&self.name, &value);
+
+            self.do_script.as_mut()
+                .unwrap_or_else(|| panic!("{}", error_message::internal_str("no script")))
+                .exec(core);
+
+            if core.break_counter > 0 {
+                core.break_counter -= 1;
+                break;
+            }
+            core.continue_flag = false;
+        }
+        true
+    }
+
+    fn new() -> SelectCommand {
+        SelectCommand {
+            text: String::new(),
+            name: String::new(),
+            has_in: false,
+            values: vec![],
+            do_script: None,
+            redirects: vec![],
+            force_fork: false,
+        }
+    }
+
+    fn eat_name(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
+        command::eat_blank_with_comment(feeder, core, &mut ans.text);
+
+        let len = feeder.scanner_name(core);
+        if len == 0 {
+            return false;
+        }
+
+        ans.name = feeder.consume(len);
+        ans.text += &ans.name.clone();
+        command::eat_blank_with_comment(feeder, core, &mut ans.text);
+        true
+    }
+
+    fn eat_in_part(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) {
+        if ! feeder.starts_with("in") {
+            return;
+        }
+
+        feeder.paint(2, Color::Keyword);
+        ans.text += &feeder.consume(2);
+        ans.has_in = true;
+
+        loop {
+            command::eat_blank_with_comment(feeder, core, &mut ans.text);
+            match Word::parse(feeder, core, false) {
+                Some(w) => {
+                    ans.text += &w.text.clone();
+                    ans.values.push(w);
+                },
+                None    => return,
+            }
+        }
+    }
+
+    fn eat_end(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
+        command::eat_blank_with_comment(feeder, core, &mut ans.text);
+        if feeder.starts_with(";") || feeder.starts_with("\n") {
+            ans.text += &feeder.consume(1);
+            command::eat_blank_with_comment(feeder, core, &mut ans.text);
+            true
+        }else{
+            false
+        }
+    }
+
+    pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Self> {
+        if ! feeder.starts_with("select") {
+            return None;
+        }
+        let mut ans = Self::new();
+        feeder.paint(6, Color::Keyword);
+        ans.text = feeder.consume(6);
+
+        if ! Self::eat_name(feeder, &mut ans, core) {
+            return None;
+        }
+        Self::eat_in_part(feeder, &mut ans, core);
+
+        if ! Self::eat_end(feeder, &mut ans, core) {
+            return None;
+        }
+
+        if feeder.len() == 0 && ! feeder.feed_additional_line(core) {
+            return None;
+        }
+
+        if command::eat_inner_script(feeder, core, "do", vec!["done"],  &mut ans.do_script, false) {
+            ans.text.push_str("do");
+            ans.text.push_str(&ans.do_script.as_mut().unwrap().get_text());
+            ans.text.push_str(&feeder.consume(4)); //done
+
+            command::eat_redirects(feeder, core, &mut ans.redirects, &mut ans.text);
+            Some(ans)
+        }else{
+            None
+        }
+    }
+}
@@ -6,6 +6,7 @@ pub mod parser;
 use crate::{error_message, ShellCore};
 use super::{Command, Pipe, Redirect};
 use crate::core::data::Value;
+use crate::core::trap;
 use crate::elements::substitution::Substitution;
 use crate::elements::word::Word;
 use nix::unistd;
@@ -20,7 +21,7 @@ use nix::errno::Errno;
 pub struct SimpleCommand {
     text: String,
     substitutions: Vec<Substitution>,
-    evaluated_subs: Vec<(String, Value)>,
+    evaluated_subs: Vec<(String, Option<String>, Value)>,
     words: Vec<Word>,
     args: Vec<String>,
     redirects: Vec<Redirect>,
@@ -32,10 +33,15 @@ pub struct SimpleCommand {
 
 impl Command for SimpleCommand {
     fn exec(&mut self, core: &mut ShellCore, pipe: &mut Pipe) -> Option<Pid> {
-        if core.return_flag || core.break_counter > 0 {
+        if core.return_flag || core.break_counter > 0 || core.continue_flag {
             return None;
         }
 
+        if ! core.trap_running {
+            core.data.set_param("BASH_COMMAND", self.text.trim_end());
+            core.run_trap(trap::DEBUG);
+        }
+
         if ! self.eval_substitutions(core){
             core.data.set_param("?", "1");
             return None;
@@ -91,7 +97,7 @@ impl Command for SimpleCommand {
 
 impl SimpleCommand {
     fn exec_external_command(&mut self, core: &mut ShellCore) -> ! {
-        self.set_environment_variables();
+        self.set_environment_variables(core);
         let cargs = Self::to_cargs(&self.args);
 
         match unistd::execvp(&cargs[0], &cargs) {
@@ -117,6 +123,12 @@ impl SimpleCommand {
     }
 
     fn exec_command(&mut self, core: &mut ShellCore, pipe: &mut Pipe) -> Option<Pid> {
+        if self.args.len() == 1 && self.args[0] == "exec"
+        && ! self.force_fork && ! pipe.is_connected() {
+            self.exec_redirects(core);
+            return None;
+        }
+
         if self.force_fork 
         || pipe.is_connected() 
         || ( ! core.builtins.contains_key(&self.args[0]) 
@@ -128,6 +140,14 @@ impl SimpleCommand {
         }
     }
 
+    /* exec without a command leaves its redirections for the shell */
+    fn exec_redirects(&mut self, core: &mut ShellCore) {
+        match self.redirects.iter_mut().all(|r| r.connect(false, core)) {
+            true  => core.data.set_param("?", "0"),
+            false => core.data.set_param("?", "1"),
+        }
+    }
+
     fn check_sigint(core: &mut ShellCore) -> bool {
         if core.sigint.load(Relaxed) {
             core.data.set_param("?", "130");
@@ -138,30 +158,33 @@ impl SimpleCommand {
 
     fn exec_set_params(&mut self, core: &mut ShellCore) -> Option<Pid> {
         for s in &self.evaluated_subs {
-            match &s.1 {
-                Value::EvaluatedSingle(v) => core.data.set_param(&s.0, &v),
-                Value::EvaluatedArray(a) => core.data.set_array(&s.0, &a),
-                _ => {},
+            if ! core.data.set_value(&s.0, s.1.as_deref(), &s.2, None) {
+                core.data.set_param("?", "1");
+                core.word_eval_error = true; // an assignment error stops the command list
+                return None;
+            }else if core.data.flags.contains('a') { //allexport
+                let layer = core.data.get_layer(&s.0).unwrap_or(0);
+                core.data.set_layer_attributes(&s.0, "x", layer);
             }
         }
         None
     }
 
     fn set_local_params(&mut self, core: &mut ShellCore) {
+        let layer = core.data.get_layer_num() - 1;
         for s in &self.evaluated_subs {
-            match &s.1 {
-                Value::EvaluatedSingle(v) => core.data.set_local_param(&s.0, &v),
-                Value::EvaluatedArray(a) => core.data.set_local_array(&s.0, &a),
-                _ => {},
-            }
+            core.data.set_value(&s.0, s.1.as_deref(), &s.2, Some(layer));
         }
     }
 
-    fn set_environment_variables(&mut self) {
+    fn set_environment_variables(&mut self, core: &mut ShellCore) {
+        for (k, v) in core.data.get_exported_params() {
+            env::set_var(&k, &v);
+        }
+
         for s in &self.evaluated_subs {
-            match &s.1 {
-                Value::EvaluatedSingle(v) => env::set_var(&s.0, &v),
-                _ => {},
+            if let (None, Value::EvaluatedSingle(v)) = (&s.1, &s.2) {
+                env::set_var(&s.0, v);
             }
         }
     }
@@ -177,7 +200,7 @@ impl SimpleCommand {
         for s in &mut self.substitutions {
             match s.eval(core) {
                 Value::None => return false,
-                a           => self.evaluated_subs.push( (s.key.clone(), a) ),
+                a           => self.evaluated_subs.push( (s.key.clone(), s.index.clone(), a) ),
             }
         }
         true
@@ -6,6 +6,7 @@ use super::{SimpleCommand};
 use crate::elements::command;
 use crate::elements::substitution::Substitution;
 use crate::elements::word::Word;
+use crate::feeder::terminal::highlight;
 
 impl SimpleCommand {
     fn new() -> SimpleCommand {
@@ -36,6 +37,7 @@ impl SimpleCommand {
     }
 
     fn eat_word(feeder: &mut Feeder, ans: &mut SimpleCommand, core: &mut ShellCore) -> bool {
+        let from = feeder.len();
         let w = match Word::parse(feeder, core, false) {
             Some(w) => w,
             _       => {
@@ -46,9 +48,12 @@ impl SimpleCommand {
         if ans.words.len() == 0 {
             if utils::reserved(&w.text) {
                 return false;
-            }else if w.text == "local" {
+            }else if ["local", "declare", "typeset", "export", "readonly"].contains(&w.text.as_str()) {
                 ans.permit_substitution_arg = true;
             }
+            if feeder.is_painting() {
+                feeder.paint_since(from, highlight::command_color(core, &w.text));
+            }
         }
         if Self::set_alias(&w, &mut ans.words, &mut ans.text, core, feeder) {
             return true;
@@ -62,6 +67,9 @@ impl SimpleCommand {
 
     fn set_alias(word: &Word, words: &mut Vec<Word>, text: &mut String,
                  core: &mut ShellCore, feeder: &mut Feeder) -> bool {
+        if feeder.is_painting() { // the replaced text would move the marks
+            return false;
+        }
         let mut w = word.text.clone();
         if ! core.data.replace_alias(&mut w) {
             return false;
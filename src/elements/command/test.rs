@@ -4,6 +4,7 @@
 use crate::{error_message, ShellCore, Feeder};
 use super::{Command, Redirect};
 use crate::elements::command;
+use crate::feeder::terminal::highlight::Color;
 use crate::elements::expr::conditional::{ConditionalExpr, CondElem};
 
 #[derive(Debug, Clone)]
@@ -53,6 +54,7 @@ impl TestCommand {
         }
 
         let mut ans = Self::new();
+        feeder.paint(2, Color::Keyword);
         ans.text = feeder.consume(2);
 
         match ConditionalExpr::parse(feeder, core) {
@@ -64,6 +66,7 @@ impl TestCommand {
         }
 
         if feeder.starts_with("]]") {
+            feeder.paint(2, Color::Keyword);
             ans.text += &feeder.consume(2);
             command::eat_redirects(feeder, core, &mut ans.redirects, &mut ans.text);
             return Some(ans);
@@ -12,19 +12,21 @@ pub struct WhileCommand {
     pub do_script: Option<Script>,
     pub redirects: Vec<Redirect>,
     force_fork: bool,
+    until: bool,
 }
 
 impl Command for WhileCommand {
     fn run(&mut self, core: &mut ShellCore, _: bool) {
         core.loop_level += 1;
+        let suspend_e_option = core.suspend_e_option;
         loop {
             core.suspend_e_option = true;
             self.while_script.as_mut()
                 .expect(&error_message::internal_str("no script"))
                 .exec(core);
 
-            core.suspend_e_option = false;
-            if core.data.get_param("?") != "0" {
+            core.suspend_e_option = suspend_e_option;
+            if (core.data.get_param("?") != "0") != self.until {
                 core.data.set_param("?", "0");
                 break;
             }
@@ -37,10 +39,12 @@ impl Command for WhileCommand {
                 core.break_counter -= 1;
                 break;
             }
+            core.continue_flag = false;
         }
         core.loop_level -= 1;
         if core.loop_level == 0 {
             core.break_counter = 0;
+            core.continue_flag = false;
         }
     }
 
@@ -59,14 +63,21 @@ impl WhileCommand {
             do_script: None,
             redirects: vec![],
             force_fork: false,
+            until: false,
         }
     }
 
     pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<WhileCommand> {
         let mut ans = Self::new();
-        if command::eat_inner_script(feeder, core, "while", vec!["do"], &mut ans.while_script, false)
+        ans.until = feeder.starts_with("until");
+        let word = match ans.until {
+            true  => "until",
+            false => "while",
+        };
+
+        if command::eat_inner_script(feeder, core, word, vec!["do"], &mut ans.while_script, false)
         && command::eat_inner_script(feeder, core, "do", vec!["done"],  &mut ans.do_script, false) {
-            ans.text.push_str("while");
+            ans.text.push_str(word);
             ans.text.push_str(&ans.while_script.as_mut().unwrap().get_text());
             ans.text.push_str("do");
             ans.text.push_str(&ans.do_script.as_mut().unwrap().get_text());
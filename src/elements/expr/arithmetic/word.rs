@@ -52,6 +52,9 @@ pub fn str_to_num(name: &str, core: &mut ShellCore) -> Result<ArithElem, String>
 
     for i in 0..RESOLVE_LIMIT {
         match is_name(&name, core) {
+            true if core.data.flags.contains('u') && ! core.data.has_value(&name) => {
+                return Err(format!("{}: unbound variable", &name));
+            },
             true  => name = core.data.get_param(&name),
             false => break,
         }
@@ -188,20 +188,19 @@ impl ConditionalExpr {
             Err(e) => return Err(e + " to conditional unary operator"),
         };
 
-        if op == "-o" || op == "-v" || op == "-z" || op == "-n" {
-            let ans = match op {
-                "-o" => core.options.query(&operand),
-                "-v" => core.data.get_value(&operand).is_some() || env::var(&operand).is_ok(),
-                "-z" => operand.len() == 0,
-                "-n" => operand.len() > 0,
-                _    => false,
-            };
+        let ans = Self::unary_test(op, &operand, core)?;
+        stack.push( CondElem::Ans(ans) );
+        Ok(())
+    }
 
-            stack.push( CondElem::Ans(ans) );
-            return Ok(());
+    pub fn unary_test(op: &str, operand: &str, core: &mut ShellCore) -> Result<bool, String> {
+        match op {
+            "-o" => Ok(core.options.query(operand)),
+            "-v" => Ok(core.data.has_value(operand) || env::var(operand).is_ok()),
+            "-z" => Ok(operand.is_empty()),
+            "-n" => Ok(! operand.is_empty()),
+            _    => Self::unary_file_check(op, operand),
         }
-
-        Self::unary_file_check(op, &operand, stack)
     }
 
     fn bin_operation(op: &str, stack: &mut Vec<CondElem>, core: &mut ShellCore) -> Result<(), String> {
@@ -217,6 +216,12 @@ impl ConditionalExpr {
             Err(e) => return Err(e),
         };
 
+        let ans = Self::binary_test(op, &left, &right, core)?;
+        stack.push( CondElem::Ans(ans) );
+        Ok(())
+    }
+
+    pub fn binary_test(op: &str, left: &str, right: &str, core: &mut ShellCore) -> Result<bool, String> {
         if op == "==" || op == "=" || op == "!=" || op == "<" || op == ">" {
             let ans = match op {
                 "==" | "=" => left == right,
@@ -226,17 +231,16 @@ impl ConditionalExpr {
                 _    => false,
             };
 
-            stack.push( CondElem::Ans(ans) );
-            return Ok(());
+            return Ok(ans);
         }
 
         if op == "-eq" || op == "-ne" || op == "-lt" || op == "-le" || op == "-gt" || op == "-ge" {
-            let lnum = match word::str_to_num(&left, core) {
+            let lnum = match word::str_to_num(left, core) {
                 Ok(ArithElem::Integer(n)) => n,
                 Ok(_) => return Err("non integer number is not supported".to_string()),
                 Err(msg) => return Err(msg),
             };
-            let rnum = match word::str_to_num(&right, core) {
+            let rnum = match word::str_to_num(right, core) {
                 Ok(ArithElem::Integer(n)) => n,
                 Ok(_) => return Err("non integer number is not supported".to_string()),
                 Err(msg) => return Err(msg),
@@ -252,16 +256,13 @@ impl ConditionalExpr {
                 _    => false,
             };
 
-            stack.push( CondElem::Ans(ans) );
-            return Ok(());
+            return Ok(ans);
         }
 
-        let result = file_check::metadata_comp(&left, &right, op);
-        stack.push( CondElem::Ans(result) );
-        Ok(())
+        Ok(file_check::metadata_comp(left, right, op))
     }
 
-    fn unary_file_check(op: &str, s: &String, stack: &mut Vec<CondElem>) -> Result<(), String> {
+    fn unary_file_check(op: &str, s: &str) -> Result<bool, String> {
         let result = match op {
             "-a" | "-e"  => file_check::exists(s),
             "-d"  => file_check::is_dir(s),
@@ -276,8 +277,7 @@ impl ConditionalExpr {
             _  => return Err("unsupported option".to_string()),
         };
 
-        stack.push( CondElem::Ans(result) );
-        Ok(())
+        Ok(result)
     }
 
     fn rev_polish_op(elem: &CondElem,
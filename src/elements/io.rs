@@ -4,8 +4,11 @@
 pub mod pipe;
 pub mod redirect;
 
+use std::fs::File;
+use std::io::{Seek, SeekFrom, Write};
+use std::os::fd::{FromRawFd, IntoRawFd};
 use std::os::unix::prelude::RawFd;
-use nix::{fcntl, unistd};
+use nix::{fcntl, libc, unistd};
 use crate::{process, ShellCore};
 use nix::errno::Errno;
 use crate::elements::Pipe;
@@ -21,6 +24,9 @@ pub fn replace(from: RawFd, to: RawFd) -> bool {
     if from < 0 || to < 0 {
         return false;
     }
+    if from == to {
+        return true;
+    }
 
     match unistd::dup2(from, to) {
         Ok(_) => {
@@ -57,8 +63,37 @@ fn share(from: RawFd, to: RawFd) -> bool {
 }
 
 pub fn backup(from: RawFd) -> RawFd {
-    fcntl::fcntl(from, fcntl::F_DUPFD_CLOEXEC(10))
-           .expect("Can't allocate fd for backup")
+    match fcntl::fcntl(from, fcntl::F_DUPFD_CLOEXEC(10)) {
+        Ok(fd) => fd,
+        Err(Errno::EBADF) => -1, //nothing to restore
+        Err(_) => panic!("Can't allocate fd for backup"),
+    }
+}
+
+pub fn is_open(fd: RawFd) -> bool {
+    fd >= 0 && fcntl::fcntl(fd, fcntl::F_GETFD) != Err(Errno::EBADF)
+}
+
+/* the lowest fd not in use from `from`, for {varname} redirections */
+pub fn unused_fd(from: RawFd) -> RawFd {
+    (from..).find(|fd| ! is_open(*fd)).unwrap_or(-1)
+}
+
+pub fn text_to_fd(text: &str) -> Result<RawFd, Errno> {
+    if text.len() <= libc::PIPE_BUF {
+        let (recv, send) = unistd::pipe()?;
+        unistd::write(&send, text.as_bytes())?;
+        return Ok(recv.into_raw_fd());
+    }
+
+    let (fd, path) = unistd::mkstemp("/tmp/sush-XXXXXX")?; // too long for a pipe buffer
+    let _ = unistd::unlink(&path);
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    if file.write_all(text.as_bytes()).is_err()
+    || file.seek(SeekFrom::Start(0)).is_err() {
+        return Err(Errno::last());
+    }
+    Ok(file.into_raw_fd())
 }
 
 pub fn connect(pipe: &mut Pipe, rs: &mut Vec<Redirect>, core: &mut ShellCore) {
@@ -3,10 +3,14 @@
 
 use std::fs::{File, OpenOptions};
 use std::os::fd::{IntoRawFd, RawFd};
-use std::io::Error;
+use std::io::{Error, ErrorKind};
 use crate::elements::io;
+use crate::elements::subword::simple::SimpleSubword;
 use crate::elements::word::Word;
+use crate::feeder::terminal::highlight::Color;
+use crate::utils::file_check;
 use crate::{error_message, Feeder, ShellCore};
+use nix::unistd;
 
 #[derive(Debug, Clone)]
 pub struct Redirect {
@@ -14,13 +18,21 @@ pub struct Redirect {
     pub symbol: String,
     pub right: Word,
     pub left: String,
+    pub here_data: Option<Word>,
     left_fd: RawFd,
     left_backup: RawFd,
-    extra_left_backup: RawFd, // &>, &>>用
+    extra_left_fd: RawFd, // 2 for &> and &>>, M for N>&M-
+    extra_left_backup: RawFd,
 }
 
 impl Redirect {
     pub fn connect(&mut self, restore: bool, core: &mut ShellCore) -> bool {
+        match self.symbol.as_str() {
+            "<<" | "<<-" => return self.redirect_here_document(restore, core),
+            "<<<" => return self.redirect_here_string(restore, core),
+            _ => {},
+        }
+
         let args = match self.right.eval(core) {
             Some(v) => v,
             None => return false,
@@ -33,19 +45,48 @@ impl Redirect {
             self.right.text = args[0].clone();
         }
 
-        match self.symbol.as_str() {
+        let varname = match self.left.starts_with("{") {
+            true  => Some(self.left[1..self.left.len()-1].to_string()),
+            false => None,
+        };
+        let restore = match varname.as_ref() {
+            Some(name) => { //{varname}: the fd stays open after the command
+                self.left_fd = match self.right.text == "-" {
+                    true  => core.data.get_param(name).parse().unwrap_or(-1),
+                    false => io::unused_fd(10),
+                };
+                false
+            },
+            None => restore,
+        };
+
+        let noclobber = core.data.flags.contains('C');
+        let result = match self.symbol.as_str() {
             "<" => self.redirect_simple_input(restore),
-            ">" => self.redirect_simple_output(restore),
-            ">&" => self.redirect_output_fd(restore),
+            ">" => self.redirect_simple_output(restore, noclobber),
+            ">|" => self.redirect_simple_output(restore, false),
+            "<>" => self.redirect_read_write(restore),
+            ">&" => self.redirect_fd(restore, 1),
+            "<&" => self.redirect_fd(restore, 0),
             ">>" => self.redirect_append(restore),
-            "&>" => self.redirect_both_output(restore),
+            "&>" => self.redirect_both_output(restore, noclobber, false),
+            "&>>" => self.redirect_both_output(restore, false, true),
             _ => error_message::internal(" (Unknown redirect symbol)"),
+        };
+
+        if let Some(name) = varname {
+            if result && self.right.text != "-" {
+                core.data.set_param(&name, &self.left_fd.to_string());
+            }
         }
+        result
     }
 
     fn set_left_fd(&mut self, default_fd: RawFd) {
         self.left_fd = if self.left.len() == 0 {
             default_fd
+        }else if self.left.starts_with("{") {
+            self.left_fd
         }else{
             self.left.parse()
                 .expect(&error_message::internal_str("invalid FD"))
@@ -74,24 +115,119 @@ impl Redirect {
         }
     }
 
+    fn connect_to_text(&mut self, text: &str, restore: bool) -> bool {
+        if restore {
+            self.left_backup = io::backup(self.left_fd);
+        }
+
+        match io::text_to_fd(text) {
+            Ok(fd) => {
+                let result = io::replace(fd, self.left_fd);
+                if ! result {
+                    io::close(fd, "sush(fatal): here-document does not close");
+                    self.left_fd = -1;
+                }
+                result
+            },
+            Err(e) => {
+                eprintln!("sush: cannot create temp file for here-document: {}", e);
+                false
+            },
+        }
+    }
+
+    fn redirect_here_document(&mut self, restore: bool, core: &mut ShellCore) -> bool {
+        let text = match self.here_data.as_ref() {
+            Some(w) => match w.eval_as_here_document(core) {
+                Some(s) => s,
+                None    => return false,
+            },
+            None => String::new(),
+        };
+
+        self.set_left_fd(0);
+        self.connect_to_text(&text, restore)
+    }
+
+    fn redirect_here_string(&mut self, restore: bool, core: &mut ShellCore) -> bool {
+        let text = match self.right.tilde_and_dollar_expansion(core) {
+            Some(mut w) => w.make_unquoted_word().unwrap_or_default() + "\n",
+            None        => return false,
+        };
+
+        self.set_left_fd(0);
+        self.connect_to_text(&text, restore)
+    }
+
     fn redirect_simple_input(&mut self, restore: bool) -> bool {
         self.set_left_fd(0);
         self.connect_to_file(File::open(&self.right.text), restore)
     }
 
-    fn redirect_simple_output(&mut self, restore: bool) -> bool {
+    /* noclobber: an existing regular file is not overwritten */
+    fn create_file(&self, noclobber: bool) -> Result<File, Error> {
+        if noclobber && file_check::is_regular_file(&self.right.text) {
+            eprintln!("sush: {}: cannot overwrite existing file", &self.right.text);
+            return Err(Error::from(ErrorKind::AlreadyExists));
+        }
+        File::create(&self.right.text)
+    }
+
+    fn redirect_simple_output(&mut self, restore: bool, noclobber: bool) -> bool {
         self.set_left_fd(1);
-        self.connect_to_file(File::create(&self.right.text), restore)
+        match self.create_file(noclobber) {
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => false,
+            result => self.connect_to_file(result, restore),
+        }
     }
 
-    fn redirect_output_fd(&mut self, _: bool) -> bool {
-        let fd = match self.right.text.parse::<RawFd>() {
+    fn redirect_read_write(&mut self, restore: bool) -> bool {
+        self.set_left_fd(0);
+        self.connect_to_file(OpenOptions::new().create(true).truncate(false)
+                .read(true).write(true).open(&self.right.text), restore)
+    }
+
+    /* N>&M, N<&M, N>&- (close) and N>&M- (move) */
+    fn redirect_fd(&mut self, restore: bool, default_fd: RawFd) -> bool {
+        self.set_left_fd(default_fd);
+        if self.right.text == "-" {
+            if restore {
+                self.left_backup = io::backup(self.left_fd);
+            }
+            let _ = unistd::close(self.left_fd);
+            return true;
+        }
+
+        let (src, moving) = match self.right.text.strip_suffix("-") {
+            Some(s) => (s.to_string(), true),
+            None    => (self.right.text.clone(), false),
+        };
+        let fd = match src.parse::<RawFd>() {
             Ok(n) => n,
-            _     => return false,
+            _     => {
+                eprintln!("sush: {}: ambiguous redirect", &self.right.text);
+                return false;
+            },
         };
+        if ! io::is_open(fd) { //checked before backups occupy fds from 10
+            eprintln!("sush: {}: Bad file descriptor", fd);
+            return false;
+        }
 
-        self.set_left_fd(1);
-        io::share(fd, self.left_fd)
+        if restore {
+            self.left_backup = io::backup(self.left_fd);
+        }
+        if ! io::share(fd, self.left_fd) {
+            return false;
+        }
+        if moving && fd != self.left_fd {
+            if restore {
+                self.extra_left_fd = fd;
+                self.extra_left_backup = io::backup(fd);
+            }
+            let _ = unistd::close(fd);
+        }
+        true
     }
 
     fn redirect_append(&mut self, restore: bool) -> bool {
@@ -100,13 +236,22 @@ impl Redirect {
                 .write(true).append(true).open(&self.right.text), restore)
     }
 
-    fn redirect_both_output(&mut self, restore: bool) -> bool {
+    fn redirect_both_output(&mut self, restore: bool, noclobber: bool, append: bool) -> bool {
         self.left_fd = 1;
-        if ! self.connect_to_file(File::create(&self.right.text), restore){
+        let result = match append {
+            true  => OpenOptions::new().create(true).append(true).open(&self.right.text),
+            false => self.create_file(noclobber),
+        };
+        let result = match result {
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => return false,
+            result => result,
+        };
+        if ! self.connect_to_file(result, restore){
             return false;
         }
 
         if restore {
+            self.extra_left_fd = 2;
             self.extra_left_backup = io::backup(2);
         }
         io::share(1, 2);
@@ -118,7 +263,7 @@ impl Redirect {
             io::replace(self.left_backup, self.left_fd);
         }
         if self.extra_left_backup >= 0 {
-            io::replace(self.extra_left_backup, 2);
+            io::replace(self.extra_left_backup, self.extra_left_fd);
         }
     }
 
@@ -128,8 +273,10 @@ impl Redirect {
             symbol: String::new(),
             right: Word::new(),
             left: String::new(),
+            here_data: None,
             left_fd: -1,
             left_backup: -1,
+            extra_left_fd: -1,
             extra_left_backup: -1,
         }
     }
@@ -138,8 +285,10 @@ impl Redirect {
         match feeder.scanner_redirect_symbol(core) {
             0 => false,
             n => {
+                let from = feeder.len() + ans.left.len();
                 ans.symbol = feeder.consume(n);
                 ans.text += &ans.symbol.clone();
+                feeder.paint_since(from, Color::Redirect);
                 true
             },
         }
@@ -160,6 +309,13 @@ impl Redirect {
     }
 
     fn eat_left(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
+        let len = feeder.scanner_redirect_varname();
+        if len > 0 {
+            ans.left = feeder.consume(len);
+            ans.text += &ans.left.clone();
+            return true;
+        }
+
         let len = feeder.scanner_uint(core);
         if len == 0 {
             return true; //左側なし（文法上OK）
@@ -174,13 +330,42 @@ impl Redirect {
         }
     }
 
+    fn eat_here_document(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
+        if ans.symbol != "<<" && ans.symbol != "<<-" {
+            return true;
+        }
+
+        let delim = match ans.right.clone().make_unquoted_word() {
+            Some(d) => d,
+            None    => return false,
+        };
+        let quoted = ans.right.text.contains(|c| "'\"\\".contains(c));
+
+        let body = match feeder.feed_here_document(core, &delim, ans.symbol == "<<-") {
+            Some(b) => b,
+            None    => return false,
+        };
+
+        ans.here_data = Some( match quoted {
+            true  => {
+                let mut w = Word::new();
+                w.text = body.clone();
+                w.subwords.push(Box::new(SimpleSubword{ text: body }));
+                w
+            },
+            false => Word::parse_here_document(&mut Feeder::new(&body), core),
+        });
+        true
+    }
+
     pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Redirect> {
         let mut ans = Self::new();
         feeder.set_backup(); //追加
 
         if Self::eat_left(feeder, &mut ans, core) &&
            Self::eat_symbol(feeder, &mut ans, core) &&
-           Self::eat_right(feeder, &mut ans, core) {
+           Self::eat_right(feeder, &mut ans, core) &&
+           Self::eat_here_document(feeder, &mut ans, core) {
             feeder.pop_backup();
             Some(ans)
         }else{
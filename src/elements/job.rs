@@ -33,21 +33,27 @@ impl Job {
         let mut do_next = true;
         let susp_e_option = core.suspend_e_option;
         for (pipeline, end) in self.pipelines.iter_mut().zip(self.pipeline_ends.iter()) {
-            if core.word_eval_error {
-                return;
+            if core.word_eval_error || core.return_flag
+            || core.break_counter > 0 || core.continue_flag {
+                break;
             }
 
-            core.suspend_e_option = susp_e_option || end == "&&" || end == "||";
+            core.suspend_e_option = susp_e_option || end == "&&" || end == "||"
+                                    || pipeline.exclamation;
 
             if do_next {
                 core.jobtable_check_status();
+                let fd_num = core.process_substitution_fds.len();
                 let (pids, exclamation, time) = pipeline.exec(core, pgid);
                 let waitstatuses = core.wait_pipeline(pids.clone(), exclamation, time);
+                core.close_process_substitutions(fd_num);
 
                 Self::check_stop(core, &pipeline.text, &pids, &waitstatuses);
+                core.check_trapped_signals();
             }
             do_next = (core.data.get_param("?") == "0") == (end == "&&");
         }
+        core.suspend_e_option = susp_e_option;
     }
 
     fn check_stop(core: &mut ShellCore, text: &str,
@@ -74,6 +80,7 @@ impl Job {
         };
         core.tty_fd = None;
 
+        let fd_num = core.process_substitution_fds.len();
         let pids = if self.pipelines.len() == 1 {
             if self.pipelines[0].commands.len() == 1 {
                 self.pipelines[0].commands[0].set_force_fork();
@@ -82,6 +89,8 @@ impl Job {
         }else{
             vec![self.exec_fork_bg(core, pgid)]
         };
+        core.close_process_substitutions(fd_num);
+        core.data.set_layer_param("!", &pids[0].unwrap().to_string(), 0);
         eprintln!("{}", &pids[0].unwrap().as_raw());
         let len = pids.len();
         let new_job_id = core.generate_new_job_id();
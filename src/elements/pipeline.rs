@@ -4,6 +4,7 @@
 use crate::{error_message, Feeder, ShellCore};
 use super::command;
 use super::command::Command;
+use crate::feeder::terminal::highlight::Color;
 use super::Pipe;
 use nix::time;
 use nix::sys::resource;
@@ -16,7 +17,7 @@ pub struct Pipeline {
     pub commands: Vec<Box<dyn Command>>,
     pub pipes: Vec<Pipe>,
     pub text: String,
-    exclamation: bool,
+    pub exclamation: bool,
     pub time: bool,
 }
 
@@ -81,7 +82,10 @@ impl Pipeline {
 
     fn eat_exclamation(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
         match feeder.starts_with("!") {
-            true  => ans.text += &feeder.consume(1),
+            true  => {
+                feeder.paint(1, Color::Keyword);
+                ans.text += &feeder.consume(1);
+            },
             false => return false,
         }
 
@@ -93,7 +97,10 @@ impl Pipeline {
 
     fn eat_time(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
         match feeder.starts_with("time") {
-            true  => ans.text += &feeder.consume(4),
+            true  => {
+                feeder.paint(4, Color::Keyword);
+                ans.text += &feeder.consume(4);
+            },
             false => return false,
         }
 
@@ -20,7 +20,8 @@ pub struct Script {
 impl Script {
     pub fn exec(&mut self, core: &mut ShellCore) {
         for (job, end) in self.jobs.iter_mut().zip(self.job_ends.iter()) {
-            if core.word_eval_error {
+            if core.word_eval_error || core.return_flag
+            || core.break_counter > 0 || core.continue_flag {
                 return;
             }
             job.exec(core, end == "&");
@@ -2,6 +2,10 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use crate::{ShellCore, Feeder};
+use crate::elements::expr::arithmetic::ArithmeticExpr;
+use crate::elements::subword;
+use crate::elements::subword::simple::SimpleSubword;
+use crate::elements::word::Word;
 
 #[derive(Debug, Clone)]
 pub struct Subscript {
@@ -9,19 +13,78 @@ pub struct Subscript {
 }
 
 impl Subscript {
-    pub fn eval(&mut self) -> Option<String> {
+    pub fn eval(&mut self, core: &mut ShellCore, name: &str) -> Option<String> {
+        let inner = self.inner();
+        if inner == "@" || inner == "*" {
+            return Some(inner.to_string());
+        }
+
+        match core.data.is_assoc(name) {
+            true  => self.eval_as_key(core),
+            false => self.eval_as_index(core, name),
+        }
+    }
+
+    fn inner(&self) -> &str {
         let len = self.text.len();
-        let inner = &self.text[1..len-1];
+        &self.text[1..len-1]
+    }
 
-        if inner.len() == 1 {
-            if let Some(ch) = inner.chars().nth(0) {
-                if '0' <= ch && ch <= '9' || ch == '@' {
-                    return Some(inner.to_string());
-                }
-            }
+    /* a subscript of an associative array is expanded as a word */
+    pub fn eval_as_key(&mut self, core: &mut ShellCore) -> Option<String> {
+        let mut feeder = Feeder::new(self.inner());
+        let mut word = Word::new();
+        while feeder.len() > 0 {
+            let sw = match subword::parse(&mut feeder, core) {
+                Some(sw) => sw,
+                None => {
+                    let len = match feeder.scanner_blank(core) {
+                        0 => feeder.scanner_unknown_in_param_brace(),
+                        n => n,
+                    };
+                    if len == 0 {
+                        break;
+                    }
+                    Box::new(SimpleSubword{ text: feeder.consume(len) })
+                },
+            };
+            word.text += sw.get_text();
+            word.subwords.push(sw);
         }
 
-        None
+        word.eval_as_value(core)
+    }
+
+    /* a subscript of an indexed array is an arithmetic expression */
+    pub fn eval_as_index(&mut self, core: &mut ShellCore, name: &str) -> Option<String> {
+        let inner = self.inner().to_string();
+        let n = match ArithmeticExpr::parse(&mut Feeder::new(&inner), core, false) {
+            Some(mut a) => match a.eval(core).map(|s| s.parse::<i64>()) {
+                Some(Ok(n)) => n,
+                Some(_) => {
+                    eprintln!("sush: {}: bad array subscript", &inner);
+                    return None;
+                },
+                None => return None,
+            },
+            None => {
+                eprintln!("sush: {}: bad array subscript", &inner);
+                return None;
+            },
+        };
+
+        if n >= 0 {
+            return Some(n.to_string());
+        }
+
+        let len = core.data.get_array_end(name) as i64;
+        match n + len >= 0 {
+            true  => Some((n + len).to_string()),
+            false => {
+                eprintln!("sush: {}: bad array subscript", &inner);
+                None
+            },
+        }
     }
 
     pub fn new() -> Subscript {
@@ -36,13 +99,20 @@ impl Subscript {
         }
 
         let mut ans = Self::new();
-
-        while ! feeder.starts_with("]") {
+        loop {
             let len = feeder.scanner_inner_subscript(core);
             ans.text += &feeder.consume(len);
+            if feeder.len() == 0 {
+                return None;
+            }
+
+            ans.text += &feeder.consume(1);
+            let depth = ans.text.matches('[').count() - ans.text.matches(']').count();
+            if depth == 0 {
+                break;
+            }
         }
 
-        ans.text += &feeder.consume(1);
         Some(ans)
     }
 }
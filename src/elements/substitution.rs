@@ -4,59 +4,142 @@
 use crate::{ShellCore, Feeder};
 use crate::core::data::Value;
 use super::array::Array;
+use super::expr::arithmetic::ArithmeticExpr;
+use super::subscript::Subscript;
 use super::word::Word;
 
 #[derive(Debug, Clone)]
 pub struct Substitution {
     pub text: String,
     pub key: String,
+    pub subscript: Option<Subscript>,
+    pub index: Option<String>,
     pub value: Value,
 }
 
 impl Substitution {
     pub fn eval(&mut self, core: &mut ShellCore) -> Value {
+        self.index = None;
+        if let Some(s) = self.subscript.as_mut() {
+            match s.eval(core, &self.key) {
+                Some(i) if i != "@" && i != "*" => self.index = Some(i),
+                _ => return Value::None,
+            }
+        }
+
+        let integer = core.data.has_attribute(&core.data.resolve_nameref(&self.key), 'i');
         match &self.value {
             Value::None      => Value::EvaluatedSingle("".to_string()),
-            Value::Single(v) => Self::eval_as_value(&v, core),
-            Value::Array(a)  => Self::eval_as_array(&mut a.clone(), core),
+            Value::Single(v) => Self::eval_as_value(v, core, integer),
+            Value::Array(_) if self.index.is_some() => {
+                eprintln!("sush: {}: cannot assign list to array member", &self.text);
+                Value::None
+            },
+            Value::Array(a)  => match core.data.is_assoc(&self.key) {
+                true  => Self::eval_as_assoc(&mut a.clone(), core, &self.key, integer),
+                false => Self::eval_as_array(&mut a.clone(), core, integer),
+            },
             _                => Value::None,
         }
     }
 
-    fn eval_as_value(w: &Word, core: &mut ShellCore) -> Value {
-        match w.eval_as_value(core) {
+    fn eval_as_integer(s: &str, core: &mut ShellCore) -> Option<String> {
+        if s.is_empty() {
+            return Some("0".to_string());
+        }
+        ArithmeticExpr::parse(&mut Feeder::new(s), core, false)?.eval(core)
+    }
+
+    fn eval_as_value(w: &Word, core: &mut ShellCore, integer: bool) -> Value {
+        let value = match w.eval_as_value(core) {
+            Some(s) if integer => Self::eval_as_integer(&s, core),
+            v => v,
+        };
+
+        match value {
             Some(s) => Value::EvaluatedSingle(s),
             None    => Value::None,
         }
     }
 
-    fn eval_as_array(a: &mut Array, core: &mut ShellCore) -> Value {
-        match a.eval(core) {
-            Some(values) => Value::EvaluatedArray(values),
-            None         => Value::None,
+    fn eval_as_array(a: &mut Array, core: &mut ShellCore, integer: bool) -> Value {
+        let mut values = match a.eval(core) {
+            Some(values) => values,
+            None         => return Value::None,
+        };
+
+        if integer {
+            for v in values.values_mut() {
+                match Self::eval_as_integer(v, core) {
+                    Some(n) => *v = n,
+                    None    => return Value::None,
+                }
+            }
+        }
+        Value::EvaluatedArray(values)
+    }
+
+    fn eval_as_assoc(a: &mut Array, core: &mut ShellCore, name: &str, integer: bool) -> Value {
+        let mut values = match a.eval_as_assoc(core, name) {
+            Some(values) => values,
+            None         => return Value::None,
+        };
+
+        if integer {
+            for v in values.values_mut() {
+                match Self::eval_as_integer(v, core) {
+                    Some(n) => *v = n,
+                    None    => return Value::None,
+                }
+            }
         }
+        Value::AssocArray(values)
     }
 
     pub fn new() -> Substitution {
         Substitution {
             text: String::new(),
             key: String::new(),
+            subscript: None,
+            index: None,
             value: Value::None,
         }
     }
 
-    pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Self> {
-        let len = feeder.scanner_name_and_equal(core);
+    fn eat_name_and_equal(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
+        let len = feeder.scanner_name(core);
         if len == 0 {
-            return None;
+            return false;
         }
 
-        let mut ans = Self::new();
+        feeder.set_backup();
+        let name = feeder.consume(len);
+        let subscript = match feeder.starts_with("[") {
+            true  => Subscript::parse(feeder, core),
+            false => None,
+        };
+
+        if ! feeder.starts_with("=") {
+            feeder.rewind();
+            return false;
+        }
+        feeder.pop_backup();
 
-        let mut name_eq = feeder.consume(len);
-        ans.text += &name_eq;
-        name_eq.pop();
-        ans.key = name_eq.clone();
+        ans.key = name;
+        ans.text += &ans.key;
+        if let Some(s) = subscript {
+            ans.text += &s.text;
+            ans.subscript = Some(s);
+        }
+        ans.text += &feeder.consume(1);
+        true
+    }
+
+    pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Self> {
+        let mut ans = Self::new();
+        if ! Self::eat_name_and_equal(feeder, &mut ans, core) {
+            return None;
+        }
 
         if let Some(a) = Array::parse(feeder, core) {
             ans.text += &a.text;
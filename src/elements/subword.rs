@@ -9,6 +9,7 @@ mod escaped_char;
 mod ext_glob;
 mod double_quoted;
 pub mod parameter;
+mod process;
 mod varname;
 mod arithmetic;
 
@@ -22,6 +23,7 @@ use self::ext_glob::ExtGlob;
 use self::double_quoted::DoubleQuoted;
 use self::single_quoted::SingleQuoted;
 use self::parameter::Parameter;
+use self::process::ProcessSubstitution;
 use self::varname::VarName;
 use std::fmt;
 use std::fmt::Debug;
@@ -88,40 +90,11 @@ pub trait Subword {
     fn get_child_subwords(&self) -> Option<&Vec<Box<dyn Subword>>> { None }
 }
 
-fn replace_history_expansion(feeder: &mut Feeder, core: &mut ShellCore) -> bool {
-    let len = feeder.scanner_history_expansion(core);
-    if len == 0 {
-        return false;
-    }
-
-    let history_len = core.history.len();
-    if history_len < 2 {
-        feeder.replace(len, "");
-        return true;
-    }
-
-    let mut his = String::new();
-    for h in &core.history[1..] {
-        let last = h.split(" ").last().unwrap();
-
-        if ! last.starts_with("!$") {
-            his = last.to_string();
-            break;
-        }
-    }
-
-    feeder.replace(len, &his);
-    true
-}
-
 pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Box<dyn Subword>> {
-    if replace_history_expansion(feeder, core) {
-        return parse(feeder, core);
-    }
-
     if let Some(a) = BracedParam::parse(feeder, core){ Some(Box::new(a)) }
     else if let Some(a) = Arithmetic::parse(feeder, core){ Some(Box::new(a)) }
     else if let Some(a) = CommandSubstitution::parse(feeder, core){ Some(Box::new(a)) }
+    else if let Some(a) = ProcessSubstitution::parse(feeder, core){ Some(Box::new(a)) }
     else if let Some(a) = SingleQuoted::parse(feeder, core){ Some(Box::new(a)) }
     else if let Some(a) = DoubleQuoted::parse(feeder, core){ Some(Box::new(a)) }
     else if let Some(a) = ExtGlob::parse(feeder, core){ Some(Box::new(a)) }
@@ -131,3 +104,26 @@ pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Box<dyn Subwor
     else if let Some(a) = SimpleSubword::parse(feeder){ Some(Box::new(a)) }
     else{ None }
 }
+
+pub fn parse_in_here_document(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Box<dyn Subword>> {
+    match feeder.scanner_here_document_escaped_char() {
+        0 => {},
+        _ if feeder.starts_with("\\\n") => {
+            feeder.consume(2);
+            return Some(Box::new(SimpleSubword{ text: String::new() }));
+        },
+        n => return Some(Box::new(EscapedChar{ text: feeder.consume(n) })),
+    }
+
+    if let Some(a) = BracedParam::parse(feeder, core){ Some(Box::new(a)) }
+    else if let Some(a) = Arithmetic::parse(feeder, core){ Some(Box::new(a)) }
+    else if let Some(a) = CommandSubstitution::parse(feeder, core){ Some(Box::new(a)) }
+    else if let Some(a) = Parameter::parse(feeder, core){ Some(Box::new(a)) }
+    else if let Some(a) = VarName::parse(feeder, core){ Some(Box::new(a)) }
+    else{
+        match feeder.scanner_here_document_subword() {
+            0 => None,
+            n => Some(Box::new(SimpleSubword{ text: feeder.consume(n) })),
+        }
+    }
+}
@@ -4,6 +4,7 @@
 use crate::{ShellCore, Feeder};
 use crate::elements::command::arithmetic::ArithmeticCommand;
 use crate::elements::subword::Subword;
+use crate::feeder::terminal::highlight::Color;
 
 #[derive(Debug, Clone)]
 pub struct Arithmetic {
@@ -30,10 +31,12 @@ impl Arithmetic {
             return None;
         }
         feeder.set_backup();
+        let from = feeder.len();
         let dl = feeder.consume(1);
 
         if let Some(a) = ArithmeticCommand::parse(feeder, core) {
             feeder.pop_backup();
+            feeder.paint_since(from, Color::Variable);
             return Some(Arithmetic{ text: dl + &a.text.clone(), com: a});
         }
         feeder.rewind();
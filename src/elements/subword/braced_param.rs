@@ -1,12 +1,24 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
+mod case_conv;
+mod remove;
+mod replace;
+mod substr;
+mod transform;
+
 use crate::{ShellCore, Feeder};
 use crate::elements::subword;
 use crate::elements::subword::Subword;
 use crate::elements::subscript::Subscript;
 use crate::elements::word::Word;
+use crate::feeder::terminal::highlight::Color;
+use self::case_conv::CaseConv;
+use self::remove::Remove;
+use self::replace::Replace;
+use self::substr::Substr;
 use super::simple::SimpleSubword;
+use std::env;
 
 #[derive(Debug, Clone)]
 pub struct BracedParam {
@@ -14,8 +26,16 @@ pub struct BracedParam {
     pub name: String,
     pub unknown: String,
     pub subscript: Option<Subscript>,
+    pub num: bool,
+    pub indirect: bool,
+    pub name_list: bool,
     pub default_symbol: Option<String>,
     pub default_value: Option<Word>,
+    pub substr: Option<Substr>,
+    pub remove: Option<Remove>,
+    pub replace: Option<Replace>,
+    pub case_conv: Option<CaseConv>,
+    pub transform: Option<String>,
 }
 
 fn is_param(s :&String) -> bool {
@@ -39,38 +59,97 @@ fn is_param(s :&String) -> bool {
     s.chars().position(|c| !name_c(c)) == None
 }
 
+/* reads a word in a brace until one of the end strings appears */
+fn parse_word(feeder: &mut Feeder, core: &mut ShellCore, ends: &[&str]) -> Word {
+    let mut word = Word::new();
+
+    while ! ends.iter().any(|e| feeder.starts_with(e)) {
+        if feeder.len() == 0 {
+            match feeder.feed_additional_line(core) {
+                true  => continue,
+                false => break,
+            }
+        }
+
+        let sw = match subword::parse(feeder, core) {
+            Some(sw) => sw,
+            None     => {
+                let len = match feeder.scanner_blank(core) {
+                    0 => feeder.scanner_unknown_in_param_brace(),
+                    n => n,
+                };
+                if len == 0 {
+                    break;
+                }
+                Box::new(SimpleSubword{ text: feeder.consume(len) })
+            },
+        };
+
+        word.text += sw.get_text();
+        word.subwords.push(sw);
+    }
+
+    word
+}
+
 impl Subword for BracedParam {
     fn get_text(&self) -> &str { &self.text.as_ref() }
     fn boxed_clone(&self) -> Box<dyn Subword> {Box::new(self.clone())}
 
     fn substitute(&mut self, core: &mut ShellCore) -> bool {
-        if self.name.len() == 0 || ! is_param(&self.name) {
+        if ! self.check() {
             eprintln!("sush: {}: bad substitution", &self.text);
             return false;
         }
-        if self.unknown.len() > 0 
-        && ! self.unknown.starts_with("-")
-        && ! self.unknown.starts_with(",") {
-            eprintln!("sush: {}: bad substitution", &self.text);
+
+        if self.name_list {
+            self.text = Self::get_names_with_prefix(&self.name, core).join(" ");
+            return true;
+        }
+        if self.indirect && self.is_array_all() {
+            self.text = core.data.get_array_keys(&self.name).join(" ");
+            return true;
+        }
+        if self.indirect && self.subscript.is_none() {
+            if let Some(target) = core.data.get_nameref_target(&self.name) {
+                self.text = target; // ${!name} of a nameref is the name it refers to
+                return true;
+            }
+        }
+        if self.indirect && ! self.resolve_indirect(core) {
             return false;
         }
 
-        if let Some(sub) = self.subscript.as_mut() {
-            if let Some(s) = sub.eval() {
-                self.text = core.data.get_array(&self.name, &s);
-            }
-        }else{
-            let value = core.data.get_param(&self.name);
-            self.text = value.to_string();
+        if core.data.flags.contains('u') && self.default_symbol.is_none()
+        && ! self.is_array_all() && ! self.is_set(core) {
+            let name = match self.subscript.as_ref() {
+                Some(s) => self.name.clone() + &s.text,
+                None if self.name.starts_with(|c: char| c.is_ascii_digit()) => "$".to_owned() + &self.name,
+                None => self.name.clone(),
+            };
+            return core.unbound_variable(&name);
         }
 
-        match self.default_symbol.as_ref() {
-            Some(s) => if s == ":+" || self.text == "" {
-                return self.replace_to_default(core);
-            },
-            _ => {},
+        let mut values = self.get_values(core);
+
+        if self.num {
+            self.text = match self.is_array_all() {
+                true  => values.len().to_string(),
+                false => values.join(" ").chars().count().to_string(),
+            };
+            return true;
+        }
+
+        if self.default_symbol.is_some() {
+            self.text = values.join(" ");
+            return self.replace_to_default(core);
         }
 
+        if ! self.apply_operations(&mut values, core) {
+            return false;
+        }
+
+        self.text = values.join(" ");
         true
     }
 
@@ -91,17 +170,178 @@ impl BracedParam {
             name: String::new(),
             unknown: String::new(),
             subscript: None,
+            num: false,
+            indirect: false,
+            name_list: false,
             default_symbol: None,
             default_value: None,
+            substr: None,
+            remove: None,
+            replace: None,
+            case_conv: None,
+            transform: None,
+        }
+    }
+
+    fn check(&self) -> bool {
+        if self.name.is_empty() || ! is_param(&self.name) || ! self.unknown.is_empty() {
+            return false;
+        }
+
+        let has_operation = self.default_symbol.is_some() || self.substr.is_some()
+                            || self.remove.is_some() || self.replace.is_some()
+                            || self.case_conv.is_some() || self.transform.is_some();
+
+        ! ( self.num && has_operation )
+    }
+
+    fn is_array_all(&self) -> bool {
+        match self.subscript.as_ref() {
+            Some(sub) => sub.text == "[@]" || sub.text == "[*]",
+            None      => self.name == "@" || self.name == "*",
+        }
+    }
+
+    fn is_set(&mut self, core: &mut ShellCore) -> bool {
+        if ! core.data.has_value(&self.name) {
+            return false;
+        }
+
+        let name = self.name.clone();
+        match self.subscript.as_mut().and_then(|s| s.eval(core, &name)) {
+            Some(pos) if core.data.is_array(&name) => core.data.get_array_keys(&name).contains(&pos),
+            Some(pos) => match pos.parse::<usize>() {
+                Ok(n) => n == 0,
+                _ => true,
+            },
+            None => true,
+        }
+    }
+
+    fn get_values(&mut self, core: &mut ShellCore) -> Vec<String> {
+        if self.name == "@" || self.name == "*" {
+            let mut params = core.data.get_position_params();
+            if self.substr.is_some() {
+                params.insert(0, core.data.get_param("0"));
+            }
+            return params;
+        }
+
+        let pos = match self.subscript.as_mut() {
+            Some(sub) => match sub.eval(core, &self.name) {
+                Some(s) => s,
+                None    => return vec![String::new()],
+            },
+            None => return vec![core.data.get_param(&self.name)],
+        };
+
+        if ! self.is_array_all() {
+            return vec![core.data.get_array(&self.name, &pos)];
+        }
+
+        if core.data.is_array(&self.name) {
+            return core.data.get_array_all(&self.name);
+        }
+        match core.data.has_value(&self.name) {
+            true  => vec![core.data.get_param(&self.name)],
+            false => vec![],
+        }
+    }
+
+    fn get_names_with_prefix(prefix: &str, core: &mut ShellCore) -> Vec<String> {
+        let mut names = core.data.get_keys();
+        names.extend(env::vars().map(|(k, _)| k));
+        names.retain(|n| n.starts_with(prefix) && is_param(n));
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn resolve_indirect(&mut self, core: &mut ShellCore) -> bool {
+        let target = self.get_values(core).join(" ");
+        let (name, subscript) = match target.find('[') {
+            Some(n) if target.ends_with("]") => {
+                (target[..n].to_string(), Some(Subscript{ text: target[n..].to_string() }))
+            },
+            _ => (target.clone(), None),
+        };
+
+        if ! is_param(&name) {
+            eprintln!("sush: {}: invalid indirect expansion", &target);
+            return false;
+        }
+
+        self.name = name;
+        self.subscript = subscript;
+        true
+    }
+
+    fn apply_operations(&mut self, values: &mut Vec<String>, core: &mut ShellCore) -> bool {
+        let array_all = self.is_array_all();
+        if let Some(s) = self.substr.as_mut() {
+            let ok = match array_all {
+                true  => s.set_elements(values, core),
+                false => s.set(values, core),
+            };
+            if ! ok {
+                return false;
+            }
+        }
+
+        let extglob = core.shopts.query("extglob");
+
+        if let Some(r) = self.remove.as_mut() {
+            if ! r.set(values, core, extglob) {
+                return false;
+            }
+        }
+        if let Some(r) = self.replace.as_mut() {
+            if ! r.set(values, core, extglob) {
+                return false;
+            }
         }
+        if let Some(c) = self.case_conv.as_mut() {
+            if ! c.set(values, core, extglob) {
+                return false;
+            }
+        }
+        if let Some(t) = self.transform.clone() {
+            let is_array = self.is_array_all() || core.data.is_array(&self.name);
+            let name = match self.subscript.as_ref() {
+                Some(sub) if ! self.is_array_all() => self.name.clone() + &sub.text,
+                _ => self.name.clone(),
+            };
+            transform::set(&t, &name, is_array && ! name.contains('['), values, core);
+        }
+
+        true
     }
 
     fn replace_to_default(&mut self, core: &mut ShellCore) -> bool {
         let symbol = match self.default_symbol.as_ref() {
-            Some(s) => s,
+            Some(s) => s.clone(),
             None    => return true,
         };
 
+        let is_set = self.is_set(core);
+        let is_null = self.text.is_empty();
+
+        let replace = match symbol.as_str() {
+            ":-" | ":=" | ":?" => ! is_set || is_null,
+            "-" | "=" | "?"    => ! is_set,
+            ":+"               => is_set && ! is_null,
+            "+"                => is_set,
+            _                  => false,
+        };
+
+        if ! replace {
+            self.default_value = None;
+            if symbol.ends_with("+") {
+                self.text = String::new();
+            }
+            return true;
+        }
+
         let word = match self.default_value.as_ref() {
             Some(w) => match w.tilde_and_dollar_expansion(core) {
                             Some(w2) => w2,
@@ -112,29 +352,24 @@ impl BracedParam {
 
         let value: String = word.subwords.iter().map(|s| s.get_text()).collect();
 
-        if symbol == ":-" {
-            self.default_value = Some(word);
-            return true;
-        }
-        if symbol == ":=" {
+        if symbol.ends_with("=") {
             core.data.set_param(&self.name, &value);
             self.default_value = None;
             self.text = value;
             return true;
         }
-        if symbol == ":?" {
-            eprintln!("sush: {}: {}", &self.name, &value);
-            return false;
-        }
-        if symbol == ":+" {
-            self.default_value = match self.text.as_str() {
-                "" => None,
-                _  => Some(word),
+        if symbol.ends_with("?") {
+            let msg = match (value.as_str(), symbol.as_str()) {
+                ("", ":?") => "parameter null or not set",
+                ("", _)    => "parameter not set",
+                (v, _)     => v,
             };
-            return true;
+            eprintln!("sush: {}: {}", &self.name, msg);
+            return false;
         }
 
-        return false;
+        self.default_value = Some(word);
+        true
     }
 
     fn eat_subscript(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
@@ -147,13 +382,6 @@ impl BracedParam {
         false
     }
 
-    fn push_default_subword(len: usize, feeder: &mut Feeder, ans: &mut Self, word: &mut Word) {
-        let blank = feeder.consume(len);
-        let sw = Box::new(SimpleSubword{ text: blank.clone() });
-        word.subwords.push(sw);
-        ans.text += &blank.clone();
-    }
-
     fn eat_default_value(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
         let num = feeder.scanner_parameter_default_symbol();
         if num == 0 {
@@ -165,31 +393,84 @@ impl BracedParam {
 
         let num = feeder.scanner_blank(core);
         ans.text += &feeder.consume(num);
-        let mut word = Word::new();
 
-        while ! feeder.starts_with("}") {
-            if let Some(sw) = subword::parse(feeder, core) {
-                ans.text += sw.get_text();
-                word.text += sw.get_text();
-                word.subwords.push(sw);
-            }
+        let word = parse_word(feeder, core, &["}"]);
+        ans.text += &word.text;
+        ans.default_value = Some(word);
+        true
+    }
 
-            if feeder.starts_with("\n") {
-                Self::push_default_subword(1, feeder, ans, &mut word);
-                feeder.feed_additional_line(core);
-            }
+    fn eat_substr(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
+        match Substr::parse(feeder, core) {
+            Some(s) => {
+                ans.text += &s.text;
+                ans.substr = Some(s);
+                true
+            },
+            None => false,
+        }
+    }
 
-            let num = feeder.scanner_blank(core);
-            if num != 0 {
-                Self::push_default_subword(num, feeder, ans, &mut word);
-            }
+    fn eat_remove(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
+        match Remove::parse(feeder, core) {
+            Some(r) => {
+                ans.text += &r.text;
+                ans.remove = Some(r);
+                true
+            },
+            None => false,
         }
+    }
 
-        ans.default_value = Some(word);
+    fn eat_replace(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
+        match Replace::parse(feeder, core) {
+            Some(r) => {
+                ans.text += &r.text;
+                ans.replace = Some(r);
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn eat_case_conv(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
+        match CaseConv::parse(feeder, core) {
+            Some(c) => {
+                ans.text += &c.text;
+                ans.case_conv = Some(c);
+                true
+            },
+            None => false,
+        }
+    }
 
+    fn eat_transform(feeder: &mut Feeder, ans: &mut Self) -> bool {
+        let len = feeder.scanner_parameter_transform();
+        if len == 0 {
+            return false;
+        }
+
+        let symbol = feeder.consume(len);
+        ans.text += &symbol;
+        ans.transform = Some(symbol[1..].to_string());
         true
     }
 
+    fn eat_prefix(feeder: &mut Feeder, ans: &mut Self) {
+        if feeder.starts_with("#}") || feeder.starts_with("!}") {
+            return;
+        }
+
+        if feeder.starts_with("#") {
+            ans.num = true;
+        }else if feeder.starts_with("!") {
+            ans.indirect = true;
+        }else{
+            return;
+        }
+        ans.text += &feeder.consume(1);
+    }
+
     fn eat_param(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
         let len = feeder.scanner_name(core);
         if len != 0 {
@@ -208,9 +489,19 @@ impl BracedParam {
         feeder.starts_with("}")
     }
 
-    fn eat_unknown(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) {
-        if feeder.len() == 0 {
-            feeder.feed_additional_line(core);
+    fn eat_name_list_symbol(feeder: &mut Feeder, ans: &mut Self) -> bool {
+        if ! ans.indirect || ! ( feeder.starts_with("*}") || feeder.starts_with("@}") ) {
+            return false;
+        }
+
+        ans.text += &feeder.consume(1);
+        ans.name_list = true;
+        true
+    }
+
+    fn eat_unknown(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
+        if feeder.len() == 0 && ! feeder.feed_additional_line(core) {
+            return false;
         }
 
         let unknown = match feeder.starts_with("\\}") {
@@ -218,21 +509,9 @@ impl BracedParam {
             false => feeder.consume(1),
         };
 
-        ans.unknown += &unknown.clone();
-        ans.text += &unknown;
-        return;
-        /*
-
-        let len = feeder.scanner_unknown_in_param_brace();
-        if len == 0 {
-            return false;
-        }
-
-        let unknown = feeder.consume(len);
         ans.unknown += &unknown.clone();
         ans.text += &unknown;
         true
-        */
     }
 
     pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<BracedParam> {
@@ -240,18 +519,30 @@ impl BracedParam {
             return None;
         }
         let mut ans = Self::new();
+        let from = feeder.len();
         ans.text += &feeder.consume(2);
 
+        Self::eat_prefix(feeder, &mut ans);
         if Self::eat_param(feeder, &mut ans, core) {
             Self::eat_subscript(feeder, &mut ans, core);
-            Self::eat_default_value(feeder, &mut ans, core);
+            let _ = Self::eat_name_list_symbol(feeder, &mut ans)
+                 || Self::eat_default_value(feeder, &mut ans, core)
+                 || Self::eat_substr(feeder, &mut ans, core)
+                 || Self::eat_remove(feeder, &mut ans, core)
+                 || Self::eat_replace(feeder, &mut ans, core)
+                 || Self::eat_case_conv(feeder, &mut ans, core)
+                 || Self::eat_transform(feeder, &mut ans);
         }
 
         while ! feeder.starts_with("}") {
-            Self::eat_unknown(feeder, &mut ans, core);
+            if ! Self::eat_unknown(feeder, &mut ans, core) {
+                feeder.paint_since(from, Color::Variable);
+                return None;
+            }
         }
 
         ans.text += &feeder.consume(1);
+        feeder.paint_since(from, Color::Variable);
         Some(ans)
     }
 }
@@ -0,0 +1,61 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{ShellCore, Feeder};
+use crate::elements::word::Word;
+use crate::utils::glob;
+
+#[derive(Debug, Clone)]
+pub struct CaseConv {
+    pub text: String,
+    pub symbol: String,
+    pub pattern: Word,
+}
+
+impl CaseConv {
+    pub fn set(&mut self, values: &mut [String], core: &mut ShellCore, extglob: bool) -> bool {
+        let pattern = match self.pattern.eval_for_case_pattern(core) {
+            Some(p) if ! p.is_empty() => p,
+            Some(_) => "?".to_string(),
+            None    => return false,
+        };
+
+        for v in values.iter_mut() {
+            *v = self.convert(v, &pattern, extglob);
+        }
+        true
+    }
+
+    fn convert(&self, text: &str, pattern: &str, extglob: bool) -> String {
+        let upper = self.symbol.starts_with("^");
+        let all = self.symbol.len() == 2;
+
+        let mut ans = String::new();
+        for (i, c) in text.chars().enumerate() {
+            if (i > 0 && ! all) || ! glob::compare(&c.to_string(), pattern, extglob) {
+                ans.push(c);
+            }else if upper {
+                ans.extend(c.to_uppercase());
+            }else{
+                ans.extend(c.to_lowercase());
+            }
+        }
+        ans
+    }
+
+    pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Self> {
+        let len = feeder.scanner_parameter_case_conv_symbol();
+        if len == 0 {
+            return None;
+        }
+
+        let symbol = feeder.consume(len);
+        let pattern = super::parse_word(feeder, core, &["}"]);
+
+        Some(CaseConv {
+            text: symbol.clone() + &pattern.text,
+            symbol,
+            pattern,
+        })
+    }
+}
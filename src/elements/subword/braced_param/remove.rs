@@ -0,0 +1,72 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{ShellCore, Feeder};
+use crate::elements::word::Word;
+use crate::utils::glob;
+
+#[derive(Debug, Clone)]
+pub struct Remove {
+    pub text: String,
+    pub symbol: String,
+    pub pattern: Word,
+}
+
+/* byte positions of all char boundaries including the end of the string */
+pub fn char_boundaries(s: &str) -> Vec<usize> {
+    let mut ans: Vec<usize> = s.char_indices().map(|(i, _)| i).collect();
+    ans.push(s.len());
+    ans
+}
+
+impl Remove {
+    pub fn set(&mut self, values: &mut [String], core: &mut ShellCore, extglob: bool) -> bool {
+        let pattern = match self.pattern.eval_for_case_pattern(core) {
+            Some(p) => p,
+            None    => return false,
+        };
+
+        for v in values.iter_mut() {
+            *v = self.remove(v, &pattern, extglob);
+        }
+        true
+    }
+
+    fn remove(&self, text: &str, pattern: &str, extglob: bool) -> String {
+        let mut pos = char_boundaries(text);
+        if self.symbol == "##" || self.symbol == "%" {
+            pos.reverse();
+        }
+
+        let prefix = self.symbol.starts_with("#");
+        for p in pos {
+            let (head, tail) = text.split_at(p);
+            match prefix {
+                true  => if glob::compare(&head.to_string(), pattern, extglob) {
+                    return tail.to_string();
+                },
+                false => if glob::compare(&tail.to_string(), pattern, extglob) {
+                    return head.to_string();
+                },
+            }
+        }
+
+        text.to_string()
+    }
+
+    pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Self> {
+        let len = feeder.scanner_parameter_remove_symbol();
+        if len == 0 {
+            return None;
+        }
+
+        let symbol = feeder.consume(len);
+        let pattern = super::parse_word(feeder, core, &["}"]);
+
+        Some(Remove {
+            text: symbol.clone() + &pattern.text,
+            symbol,
+            pattern,
+        })
+    }
+}
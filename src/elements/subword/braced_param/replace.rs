@@ -0,0 +1,108 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{ShellCore, Feeder};
+use crate::elements::word::Word;
+use crate::utils::glob;
+use super::remove::char_boundaries;
+
+#[derive(Debug, Clone)]
+pub struct Replace {
+    pub text: String,
+    pub head: String,
+    pub pattern: Word,
+    pub string: Option<Word>,
+}
+
+impl Replace {
+    pub fn set(&mut self, values: &mut [String], core: &mut ShellCore, extglob: bool) -> bool {
+        let pattern = match self.pattern.eval_for_case_pattern(core) {
+            Some(p) => p,
+            None    => return false,
+        };
+        if pattern.is_empty() {
+            return true;
+        }
+
+        let string = match self.string.as_ref() {
+            Some(w) => match w.tilde_and_dollar_expansion(core) {
+                Some(mut w) => w.make_unquoted_word().unwrap_or_default(),
+                None        => return false,
+            },
+            None => String::new(),
+        };
+
+        for v in values.iter_mut() {
+            *v = self.replace(v, &pattern, &string, extglob);
+        }
+        true
+    }
+
+    fn replace(&self, text: &str, pattern: &str, string: &str, extglob: bool) -> String {
+        let pos = char_boundaries(text);
+        let matches = |from: usize, to: usize| glob::compare(&text[from..to].to_string(), pattern, extglob);
+
+        if self.head == "#" {
+            return match pos.iter().rev().find(|p| matches(0, **p)) {
+                Some(p) => string.to_string() + &text[*p..],
+                None    => text.to_string(),
+            };
+        }
+        if self.head == "%" {
+            return match pos.iter().find(|p| matches(**p, text.len())) {
+                Some(p) => text[..*p].to_string() + string,
+                None    => text.to_string(),
+            };
+        }
+
+        let mut ans = String::new();
+        let mut i = 0;
+        while i < pos.len() - 1 {
+            let longest = (i+1..pos.len()).rev().find(|j| matches(pos[i], pos[*j]));
+            match longest {
+                Some(j) => {
+                    ans += string;
+                    if self.head != "/" {
+                        return ans + &text[pos[j]..];
+                    }
+                    i = j;
+                },
+                None => {
+                    ans += &text[pos[i]..pos[i+1]];
+                    i += 1;
+                },
+            }
+        }
+        ans
+    }
+
+    pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Self> {
+        if ! feeder.starts_with("/") {
+            return None;
+        }
+
+        let mut ans = Replace {
+            text: feeder.consume(1),
+            head: String::new(),
+            pattern: Word::new(),
+            string: None,
+        };
+
+        if feeder.starts_with("/") || feeder.starts_with("#") || feeder.starts_with("%") {
+            ans.head = feeder.consume(1);
+            ans.text += &ans.head;
+        }
+
+        ans.pattern = super::parse_word(feeder, core, &["/", "}"]);
+        ans.text += &ans.pattern.text;
+
+        if feeder.starts_with("/") {
+            ans.text += &feeder.consume(1);
+            let string = super::parse_word(feeder, core, &["}"]);
+            ans.text += &string.text;
+            ans.string = Some(string);
+        }
+
+        Some(ans)
+    }
+}
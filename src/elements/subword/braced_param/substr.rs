@@ -0,0 +1,105 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{ShellCore, Feeder};
+use crate::elements::expr::arithmetic::ArithmeticExpr;
+
+#[derive(Debug, Clone)]
+pub struct Substr {
+    pub text: String,
+    pub offset: Option<ArithmeticExpr>,
+    pub length: Option<ArithmeticExpr>,
+}
+
+fn eval_expr(expr: &mut Option<ArithmeticExpr>, core: &mut ShellCore) -> Option<Option<i64>> {
+    let e = match expr.as_mut() {
+        Some(e) => e,
+        None    => return Some(None),
+    };
+
+    match e.eval(core).map(|s| s.parse::<i64>()) {
+        Some(Ok(n)) => Some(Some(n)),
+        _ => None,
+    }
+}
+
+impl Substr {
+    pub fn set(&mut self, values: &mut [String], core: &mut ShellCore) -> bool {
+        for v in values.iter_mut() {
+            let chars: Vec<char> = v.chars().collect();
+            match self.range(chars.len(), core) {
+                Some((from, to)) => *v = chars[from..to].iter().collect(),
+                None => return false,
+            }
+        }
+        true
+    }
+
+    pub fn set_elements(&mut self, values: &mut Vec<String>, core: &mut ShellCore) -> bool {
+        match self.range(values.len(), core) {
+            Some((from, to)) => {
+                *values = values[from..to].to_vec();
+                true
+            },
+            None => false,
+        }
+    }
+
+    fn range(&mut self, len: usize, core: &mut ShellCore) -> Option<(usize, usize)> {
+        let len = len as i64;
+        let mut from = eval_expr(&mut self.offset, core)?.unwrap_or(0);
+        if from < 0 {
+            from += len;
+            if from < 0 {
+                return Some((0, 0));
+            }
+        }
+        let from = std::cmp::min(from, len);
+
+        let to = match eval_expr(&mut self.length, core)? {
+            None => len,
+            Some(n) if n < 0 => {
+                if len + n < from {
+                    eprintln!("sush: {}: substring expression < 0", n);
+                    return None;
+                }
+                len + n
+            },
+            Some(n) => std::cmp::min(from + n, len),
+        };
+
+        Some((from as usize, to as usize))
+    }
+
+    fn eat_expr(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> Option<ArithmeticExpr> {
+        let len = feeder.scanner_parameter_substr();
+        let text = feeder.consume(len);
+        ans.text += &text;
+
+        if text.trim() == "" {
+            return None;
+        }
+        ArithmeticExpr::parse(&mut Feeder::new(&text), core, false)
+    }
+
+    pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Self> {
+        if ! feeder.starts_with(":") {
+            return None;
+        }
+
+        let mut ans = Substr {
+            text: feeder.consume(1),
+            offset: None,
+            length: None,
+        };
+
+        ans.offset = Self::eat_expr(feeder, &mut ans, core);
+        if feeder.starts_with(":") {
+            ans.text += &feeder.consume(1);
+            ans.length = Some(Self::eat_expr(feeder, &mut ans, core)
+                              .unwrap_or(ArithmeticExpr::new()));
+        }
+
+        Some(ans)
+    }
+}
@@ -0,0 +1,46 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::ShellCore;
+use crate::core::builtins::declare;
+use crate::feeder::terminal;
+use crate::utils::escape;
+use crate::utils::escape::{double_quote, single_quote as quote};
+
+fn upper_first(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) => c.to_uppercase().chain(chars).collect(),
+        None    => String::new(),
+    }
+}
+
+pub fn set(symbol: &str, name: &str, is_array: bool,
+           values: &mut Vec<String>, core: &mut ShellCore) {
+    let base = name.split('[').next().unwrap_or(name);
+    let is_set = core.data.has_value(base);
+    let flags = core.data.get_attribute_flags(&core.data.resolve_nameref(base));
+
+    match symbol {
+        "Q" if is_set => values.iter_mut().for_each(|v| *v = quote(v)),
+        "E" => values.iter_mut().for_each(|v| *v = escape::ansi_c(v)),
+        "P" => values.iter_mut().for_each(|v| *v = terminal::expand_prompt(core, v)),
+        "U" => values.iter_mut().for_each(|v| *v = v.to_uppercase()),
+        "L" => values.iter_mut().for_each(|v| *v = v.to_lowercase()),
+        "u" => values.iter_mut().for_each(|v| *v = upper_first(v)),
+        "a" => values.iter_mut().for_each(|v| *v = flags.clone()),
+        "A" if ! is_set => values.clear(),
+        "A" if is_array => *values = declare::declaration(name, core).into_iter().collect(),
+        "A" if ! flags.is_empty() => {
+            values.iter_mut().for_each(|v| *v = format!("declare -{} {}={}", flags, name, quote(v)))
+        },
+        "A" => values.iter_mut().for_each(|v| *v = format!("{}={}", name, quote(v))),
+        "K" if is_array => {
+            *values = core.data.get_array_keys(name).iter().zip(values.iter())
+                      .map(|(k, v)| format!("{} {}", k, double_quote(v)))
+                      .collect();
+        },
+        "K" => values.iter_mut().for_each(|v| *v = quote(v)),
+        _ => {},
+    }
+}
@@ -6,6 +6,7 @@ use crate::elements::Pipe;
 use crate::elements::command::Command;
 use crate::elements::command::paren::ParenCommand;
 use crate::elements::subword::Subword;
+use crate::feeder::terminal::highlight::Color;
 use nix::unistd;
 use std::{thread, time};
 use std::fs::File;
@@ -28,7 +29,10 @@ impl Subword for CommandSubstitution {
         pipe.set(-1, unistd::getpgrp());
         let pid = self.command.exec(core, &mut pipe);
         let result = self.read(pipe.recv, core);
+        let suspend_e_option = core.suspend_e_option;
+        core.suspend_e_option = true; //errexit depends on the command using this
         core.wait_pipeline(vec![pid], false, false);
+        core.suspend_e_option = suspend_e_option;
         result
     }
 }
@@ -75,9 +79,11 @@ impl CommandSubstitution {
         if ! feeder.starts_with("$(") {
             return None;
         }
+        feeder.paint(2, Color::Variable);
         let mut text = feeder.consume(1);
 
         if let Some(pc) = ParenCommand::parse(feeder, core, true) {
+            feeder.paint_since(feeder.len() + 1, Color::Variable); // )
             text += &pc.get_text();
             Some(CommandSubstitution {text: text, command: pc} )
         }else{
@@ -4,6 +4,7 @@
 use crate::{error_message, ShellCore, Feeder};
 use crate::elements::word::{Word, substitution};
 use crate::elements::subword::CommandSubstitution;
+use crate::feeder::terminal::highlight::Color;
 use super::{BracedParam, EscapedChar, SimpleSubword, Parameter, Subword, VarName};
 
 #[derive(Debug, Clone)]
@@ -139,7 +140,8 @@ impl DoubleQuoted {
     }
 
     fn eat_escaped_char(feeder: &mut Feeder, ans: &mut Self, core: &mut ShellCore) -> bool {
-        if feeder.starts_with("\\$") || feeder.starts_with("\\\\") {
+        if feeder.starts_with("\\$") || feeder.starts_with("\\\\")
+        || feeder.starts_with("\\\"") || feeder.starts_with("\\`") {
             let txt = feeder.consume(2);
             ans.text += &txt;
             ans.subwords.push(Box::new(EscapedChar{ text: txt }));
@@ -155,7 +157,11 @@ impl DoubleQuoted {
             return false;
         }
 
+        let from = feeder.len() + 1;
         let txt = feeder.consume(len);
+        if ans.subwords.last().map(|s| s.get_text() == "$").unwrap_or(false) {
+            feeder.paint_since(from, Color::Variable);
+        }
         ans.text += &txt;
         ans.subwords.push(Box::new( VarName{ text: txt}));
         true
@@ -171,6 +177,7 @@ impl DoubleQuoted {
             return None;
         }
         let mut ans = Self::new();
+        let from = feeder.len();
         ans.text = feeder.consume(1);
 
         loop {
@@ -184,11 +191,13 @@ impl DoubleQuoted {
 
             if feeder.starts_with("\"") {
                 ans.text += &feeder.consume(1);
+                feeder.paint_since(from, Color::Quoted);
 //                eprintln!("{:?}", &ans);
                 return Some(ans);
             }else if feeder.len() > 0 {
                 error_message::internal("unknown chars in double quoted word");
             }else if ! feeder.feed_additional_line(core) {
+                feeder.paint_since(from, Color::Quoted);
                 return None;
             }
         }
@@ -3,6 +3,7 @@
 
 use crate::{ShellCore, Feeder};
 use super::Subword;
+use crate::feeder::terminal::highlight::Color;
 
 #[derive(Debug, Clone)]
 pub struct Parameter {
@@ -14,7 +15,16 @@ impl Subword for Parameter {
     fn boxed_clone(&self) -> Box<dyn Subword> {Box::new(self.clone())}
 
     fn substitute(&mut self, core: &mut ShellCore) -> bool {
-        let value = core.data.get_param(&self.text[1..]);
+        let name = &self.text[1..];
+        if core.data.flags.contains('u') && name != "@" && name != "*"
+        && ! core.data.has_value(name) {
+            return match name.starts_with(|c: char| c.is_ascii_digit()) {
+                true  => core.unbound_variable(&self.text),
+                false => core.unbound_variable(name),
+            };
+        }
+
+        let value = core.data.get_param(name);
         self.text = value.to_string();
         true
     }
@@ -24,7 +34,10 @@ impl Parameter {
     pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Self> {
         match feeder.scanner_dollar_special_and_positional_param(core) {
             0 => None,
-            n => Some(Self { text: feeder.consume(n) } ),
+            n => {
+                feeder.paint(n, Color::Variable);
+                Some(Self { text: feeder.consume(n) } )
+            },
         }
     }
 }
@@ -0,0 +1,88 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{ShellCore, Feeder};
+use crate::elements::io;
+use crate::elements::command::Command;
+use crate::elements::command::paren::ParenCommand;
+use crate::elements::subword::Subword;
+use crate::feeder::terminal::highlight::Color;
+use nix::unistd;
+use nix::unistd::{ForkResult, Pid};
+use std::os::fd::IntoRawFd;
+
+#[derive(Debug, Clone)]
+pub struct ProcessSubstitution {
+    pub text: String,
+    direction: String, // "<" or ">"
+    command: ParenCommand,
+}
+
+impl Subword for ProcessSubstitution {
+    fn get_text(&self) -> &str {self.text.as_ref()}
+    fn boxed_clone(&self) -> Box<dyn Subword> {Box::new(self.clone())}
+
+    fn substitute(&mut self, core: &mut ShellCore) -> bool {
+        let (recv, send) = match unistd::pipe() {
+            Ok((r, s)) => (r.into_raw_fd(), s.into_raw_fd()),
+            Err(e) => {
+                eprintln!("sush: cannot make pipe for process substitution: {}", e);
+                return false;
+            },
+        };
+
+        let (parent_fd, child_fd, child_to) = match self.direction.as_str() {
+            "<" => (recv, send, 1),
+            _   => (send, recv, 0),
+        };
+
+        match unsafe{unistd::fork()} {
+            Ok(ForkResult::Child) => {
+                core.initialize_as_subshell(Pid::from_raw(0), unistd::getpgrp());
+                io::close(parent_fd, "sush(fatal): cannot close a pipe");
+                for fd in core.process_substitution_fds.drain(..) {
+                    io::close(fd, "sush(fatal): cannot close a pipe");
+                }
+                io::replace(child_fd, child_to);
+                self.command.run(core, true);
+                core.exit()
+            },
+            Ok(ForkResult::Parent { child } ) => {
+                io::close(child_fd, "sush(fatal): cannot close a pipe");
+                core.process_substitution_fds.push(parent_fd);
+                core.process_substitution_pids.push(child);
+                core.data.set_layer_param("!", &child.to_string(), 0);
+                self.text = format!("/dev/fd/{}", parent_fd);
+                true
+            },
+            Err(err) => panic!("sush(fatal): Failed to fork. {}", err),
+        }
+    }
+}
+
+impl ProcessSubstitution {
+    pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Self> {
+        if ! feeder.starts_with("<(") && ! feeder.starts_with(">(") {
+            return None;
+        }
+        feeder.set_backup();
+        feeder.paint(2, Color::Variable);
+        let direction = feeder.consume(1);
+
+        match ParenCommand::parse(feeder, core, true) {
+            Some(pc) => {
+                feeder.pop_backup();
+                feeder.paint_since(feeder.len() + 1, Color::Variable); // )
+                Some(ProcessSubstitution {
+                    text: direction.clone() + &pc.get_text(),
+                    direction,
+                    command: pc,
+                })
+            },
+            None => {
+                feeder.rewind();
+                None
+            },
+        }
+    }
+}
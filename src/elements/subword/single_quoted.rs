@@ -3,6 +3,7 @@
 
 use crate::{ShellCore, Feeder};
 use super::Subword;
+use crate::feeder::terminal::highlight::Color;
 
 #[derive(Debug, Clone)]
 pub struct SingleQuoted {
@@ -31,8 +32,14 @@ impl Subword for SingleQuoted {
 impl SingleQuoted {
     pub fn parse(feeder: &mut Feeder, core: &mut ShellCore) -> Option<Self> {
         match feeder.scanner_single_quoted_subword(core) {
-            0 => None,
+            0 => {
+                if feeder.starts_with("'") { // not closed
+                    feeder.paint(feeder.len(), Color::Quoted);
+                }
+                None
+            },
             n => {
+                feeder.paint(n, Color::Quoted);
                 let s = feeder.consume(n);
                 Some(SingleQuoted{ text: s })
             },
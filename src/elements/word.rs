@@ -10,6 +10,7 @@ mod split;
 use crate::{ShellCore, Feeder};
 use crate::elements::subword;
 use super::subword::Subword;
+use crate::feeder::terminal::highlight::Color;
 
 #[derive(Debug, Clone)]
 pub struct Word {
@@ -19,10 +20,15 @@ pub struct Word {
 
 impl Word {
     pub fn eval(&mut self, core: &mut ShellCore) -> Option<Vec<String>> {
+        let words = match core.data.flags.contains('B') {
+            true  => brace_expansion::eval(&mut self.clone()),
+            false => vec![self.clone()],
+        };
+
         let mut ws = vec![];
-        for w in brace_expansion::eval(&mut self.clone()) {
+        for w in words {
             match w.tilde_and_dollar_expansion(core) {
-                Some(w) => ws.append( &mut w.split_and_path_expansion(core) ),
+                Some(w) => ws.append( &mut w.split_and_path_expansion(core)? ),
                 None    => return None,
             };
         }
@@ -32,7 +38,7 @@ impl Word {
 
     pub fn eval_as_value(&self, core: &mut ShellCore) -> Option<String> {
         let mut ws = match self.tilde_and_dollar_expansion(core) {
-            Some(w) => w.split_and_path_expansion(core),
+            Some(w) => w.split_and_path_expansion(core)?,
             None    => return None,
         };
 
@@ -53,6 +59,16 @@ impl Word {
         }
     }
 
+    pub fn eval_as_here_document(&self, core: &mut ShellCore) -> Option<String> {
+        let mut w = self.clone();
+        match substitution::eval(&mut w, core) {
+            true  => Some(w.subwords.iter_mut()
+                           .filter_map(|s| s.make_unquoted_string())
+                           .collect()),
+            false => None,
+        }
+    }
+
     pub fn tilde_and_dollar_expansion(&self, core: &mut ShellCore) -> Option<Word> {
         let mut w = self.clone();
         tilde_expansion::eval(&mut w, core);
@@ -62,13 +78,16 @@ impl Word {
         }
     }
 
-    pub fn split_and_path_expansion(&self, core: &mut ShellCore) -> Vec<Word> {
+    pub fn split_and_path_expansion(&self, core: &mut ShellCore) -> Option<Vec<Word>> {
         let mut ans = vec![];
-        let extglob = core.shopts.query("extglob");
+        let noglob = core.data.flags.contains('f');
         for mut w in split::eval(self, core) {
-            ans.append(&mut path_expansion::eval(&mut w, extglob) );
+            match noglob {
+                true  => ans.push(w),
+                false => ans.append(&mut path_expansion::eval(&mut w, core)? ),
+            }
         }
-        ans
+        Some(ans)
     }
 
     fn make_args(words: &mut Vec<Word>) -> Vec<String> {
@@ -119,13 +138,31 @@ impl Word {
         self.subwords.push(subword.clone());
     }
 
+    pub fn parse_here_document(feeder: &mut Feeder, core: &mut ShellCore) -> Word {
+        let mut ans = Word::new();
+        while let Some(sw) = subword::parse_in_here_document(feeder, core) {
+            ans.push(&sw);
+        }
+        ans
+    }
+
     pub fn parse(feeder: &mut Feeder, core: &mut ShellCore, as_operand: bool) -> Option<Word> {
         if feeder.starts_with("#") {
             return None;
         }
 
         let mut ans = Word::new();
-        while let Some(sw) = subword::parse(feeder, core) {
+        loop {
+            let from = feeder.len() + 1;
+            let sw = match subword::parse(feeder, core) {
+                Some(sw) => sw,
+                None     => break,
+            };
+            match ans.subwords.last().map(|s| s.get_text() == "$") { // $name and $'...'
+                Some(true) if sw.is_name() => feeder.paint_since(from, Color::Variable),
+                Some(true) if sw.get_text().starts_with("'") => feeder.paint_since(from, Color::Quoted),
+                _ => {},
+            }
             ans.push(&sw);
 
             if as_operand && feeder.scanner_math_symbol(core) != 0 {
@@ -1,49 +1,206 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
+use crate::{file_check, ShellCore};
 use crate::elements::word::Word;
-use crate::utils::directory;
+use crate::utils::{directory, glob};
+use nix::libc;
+use std::cmp::Ordering;
+use std::ffi::CString;
+use std::fs;
+use std::sync::Once;
 use super::subword::simple::SimpleSubword;
 
-pub fn eval(word: &mut Word, extglob: bool) -> Vec<Word> {
-    let paths = expand(&word.make_glob_string(), extglob);
+/* the shell options and the variable which change pathname expansion */
+struct GlobOptions {
+    extglob: bool,
+    dotglob: bool,
+    globstar: bool,
+    nocaseglob: bool,
+    ignore: Vec<String>, // patterns in GLOBIGNORE
+}
+
+impl GlobOptions {
+    fn new(core: &mut ShellCore) -> GlobOptions {
+        let ignore: Vec<String> = core.data.get_param("GLOBIGNORE").split(':')
+                                  .filter(|p| ! p.is_empty()).map(|p| p.to_string()).collect();
+        GlobOptions {
+            extglob: core.shopts.query("extglob"),
+            dotglob: core.shopts.query("dotglob") || ! ignore.is_empty(),
+            globstar: core.shopts.query("globstar"),
+            nocaseglob: core.shopts.query("nocaseglob"),
+            ignore,
+        }
+    }
+
+    fn fold(&self, s: &str) -> String {
+        match self.nocaseglob {
+            true  => s.to_lowercase(),
+            false => s.to_string(),
+        }
+    }
+
+    fn visible(&self, file: &str, glob: &str) -> bool {
+        ! file.starts_with(".") || glob.starts_with(".")
+        || (self.dotglob && file != "." && file != "..")
+    }
+
+    /* . and .. are always ignored when GLOBIGNORE is set */
+    fn ignored(&self, path: &str) -> bool {
+        if self.ignore.is_empty() {
+            return false;
+        }
+
+        let name = path.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+        name == "." || name == ".."
+        || self.ignore.iter().any(|p| glob::compare(&path.to_string(), p, self.extglob))
+    }
+}
+
+pub fn eval(word: &mut Word, core: &mut ShellCore) -> Option<Vec<Word>> {
+    let globstr = word.make_glob_string();
+    if ! is_glob(&globstr, core.shopts.query("extglob")) {
+        return Some(vec![word.clone()]);
+    }
+
+    let mut paths = expand(&globstr, &GlobOptions::new(core));
+    sort(&mut paths, &collation(core));
 
     if paths.len() > 0 {
         let mut tmp = word.clone();
-        paths.iter()
-             .map(|p| rewrite(&mut tmp, &p))
-             .collect()
-    }else{
-        vec![word.clone()]
+        return Some(paths.iter().map(|p| rewrite(&mut tmp, p)).collect());
+    }
+
+    if core.shopts.query("failglob") {
+        eprintln!("sush: no match: {}", &word.text);
+        return None;
+    }
+    match core.shopts.query("nullglob") {
+        true  => Some(vec![]),
+        false => Some(vec![word.clone()]),
     }
 }
 
-fn expand(globstr: &str, extglob: bool) -> Vec<String> {
-    if globstr.find("*") == None 
-    && globstr.find("?") == None
-    && globstr.find("@") == None
-    && globstr.find("+") == None
-    && globstr.find("!") == None
-    && globstr.find("[") == None {
-        return vec![];
+/* whether the string has a wildcard which is not quoted */
+fn is_glob(globstr: &str, extglob: bool) -> bool {
+    let mut escaped = false;
+    let mut prev = ' ';
+    for (i, c) in globstr.char_indices() {
+        if escaped {
+            escaped = false;
+            prev = ' ';
+            continue;
+        }
+
+        match c {
+            '\\' => escaped = true,
+            '*' | '?' => return true,
+            '[' if globstr[i+1..].chars().skip(1).any(|c| c == ']') => return true,
+            '(' if extglob && "@+!".contains(prev) => return true,
+            _ => {},
+        }
+        prev = c;
     }
-        
+    false
+}
+
+fn expand(globstr: &str, opts: &GlobOptions) -> Vec<String> {
+    let glob_elems: Vec<&str> = globstr.split("/").collect();
     let mut ans_cands = vec!["".to_string()];
-    let mut tmp_ans_cands = vec![];
 
-    for glob_elem in globstr.split("/") {
-        for cand in ans_cands {
-            tmp_ans_cands.extend( directory::glob(&cand, &glob_elem, extglob) );
+    for (i, glob_elem) in glob_elems.iter().enumerate() {
+        let last = i == glob_elems.len() - 1;
+        let mut tmp_ans_cands = vec![];
+        for cand in &ans_cands {
+            match opts.globstar && *glob_elem == "**" {
+                true  => tmp_ans_cands.extend( descendants(cand, last, opts) ),
+                false => tmp_ans_cands.extend( matches(cand, glob_elem, opts) ),
+            }
         }
-        ans_cands = tmp_ans_cands.clone();
-        tmp_ans_cands.clear();
+        ans_cands = tmp_ans_cands;
     }
 
     ans_cands.iter_mut().for_each(|e| {e.pop();} );
-    ans_cands.sort();
+    ans_cands.retain(|e| ! e.is_empty() && ! opts.ignored(e));
     ans_cands
 }
 
+fn matches(dir: &str, glob: &str, opts: &GlobOptions) -> Vec<String> {
+    let make_path = |file: &str| dir.to_owned() + file + "/";
+
+    if glob.is_empty() || glob == "." || glob == ".." {
+        return vec![make_path(glob)];
+    }
+
+    let mut fs = directory::files(dir);
+    fs.append( &mut vec![".".to_string(), "..".to_string()] );
+
+    let pattern = opts.fold(glob);
+    fs.iter().filter(|f| opts.visible(f, glob) && glob::compare(&opts.fold(f), &pattern, opts.extglob))
+             .map(|f| make_path(f)).collect()
+}
+
+/* ** as a directory: the directory and all the directories under it.
+ * ** at the end: the files under the directory in addition */
+fn descendants(dir: &str, last: bool, opts: &GlobOptions) -> Vec<String> {
+    let mut ans = match last {
+        true  => vec![dir.to_owned() + "/"],
+        false => vec![dir.to_string()],
+    };
+    walk(dir, last, opts, &mut ans);
+    ans
+}
+
+/* symbolic links to directories are matched but not followed */
+fn walk(dir: &str, with_files: bool, opts: &GlobOptions, ans: &mut Vec<String>) {
+    let mut fs = directory::files(dir);
+    fs.sort();
+
+    for f in fs.iter().filter(|f| ! f.starts_with(".") || opts.dotglob) {
+        let path = dir.to_owned() + f + "/";
+        match fs::symlink_metadata(&path[..path.len()-1]) {
+            Ok(m) if m.is_dir() => {
+                ans.push(path.clone());
+                walk(&path, with_files, opts, ans);
+            },
+            _ if with_files || file_check::is_dir(&path) => ans.push(path),
+            _ => {},
+        }
+    }
+}
+
+/* LC_ALL, LC_COLLATE or LANG */
+fn collation(core: &mut ShellCore) -> String {
+    for name in ["LC_ALL", "LC_COLLATE", "LANG"] {
+        let locale = core.data.get_param(name);
+        if ! locale.is_empty() {
+            return locale;
+        }
+    }
+    String::new()
+}
+
+fn sort(paths: &mut Vec<String>, locale: &str) {
+    match locale.is_empty() || locale == "C" || locale == "POSIX" || locale.starts_with("C.") {
+        true  => paths.sort(),
+        false => paths.sort_by(|a, b| strcoll(a, b)),
+    }
+    paths.dedup();
+}
+
+/* compares strings in the collation order of the locale given by the environment */
+fn strcoll(a: &str, b: &str) -> Ordering {
+    static SETLOCALE: Once = Once::new();
+    SETLOCALE.call_once(|| unsafe {
+        libc::setlocale(libc::LC_COLLATE, c"".as_ptr());
+    });
+
+    match (CString::new(a), CString::new(b)) {
+        (Ok(ca), Ok(cb)) => unsafe { libc::strcoll(ca.as_ptr(), cb.as_ptr()) }.cmp(&0).then(a.cmp(b)),
+        _ => a.cmp(b),
+    }
+}
+
 fn rewrite(word: &mut Word, path: &str) -> Word {
     word.subwords[0] = Box::new( SimpleSubword{ text: path.to_string() } );
     while word.subwords.len() > 1 {
@@ -1,11 +1,15 @@
 //SPDX-FileCopyrightText: 2022 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
-mod terminal;
+pub mod terminal;
 mod scanner;
 
 use std::{io, process};
-use crate::ShellCore;
+use std::fs::File;
+use std::os::fd::IntoRawFd;
+use crate::{error_message, Script, ShellCore};
+use crate::elements::io as fd;
+use self::terminal::highlight::Color;
 use std::sync::atomic::Ordering::Relaxed;
 
 pub enum InputError {
@@ -19,6 +23,10 @@ pub struct Feeder {
     backup: Vec<String>,
     pub nest: Vec<(String, Vec<String>)>,
     lineno: usize,
+    queued: Vec<String>, // the rest of lines given at once from the terminal
+    probing: bool,       // no additional line is read
+    incomplete: bool,    // an additional line was required while probing
+    spans: Option<Vec<(usize, usize, Color)>>, // painted parts as the remaining lengths at both ends
 }
 
 impl Feeder {
@@ -28,6 +36,75 @@ impl Feeder {
             backup: vec![],
             nest: vec![("".to_string(), vec![])],
             lineno: 0,
+            queued: vec![],
+            probing: false,
+            incomplete: false,
+            spans: None,
+        }
+    }
+
+    /* a feeder for a part of the line under editing, which never reads an additional line */
+    pub fn new_probing(s: &str) -> Feeder {
+        let mut ans = Feeder::new(s);
+        ans.probing = true;
+        ans
+    }
+
+    /* true when the parser needs more lines for the text: an open quote, a compound command
+     * without its end, a here document and so on */
+    pub fn is_incomplete(core: &mut ShellCore, text: &str) -> bool {
+        let mut feeder = Feeder::new_probing("");
+        feeder.queued = Self::split_lines(&(text.to_string() + "\n"));
+        feeder.probe(core);
+        feeder.incomplete
+    }
+
+    /* the byte ranges of the text painted by the parsers for the highlight shopt */
+    pub fn highlight(core: &mut ShellCore, text: &str) -> Vec<(usize, usize, Color)> {
+        let mut feeder = Feeder::new_probing("");
+        feeder.queued = vec![text.to_string() + "\n"];
+        feeder.spans = Some(vec![]);
+        feeder.probe(core);
+
+        let len = text.len() + 1;
+        feeder.spans.unwrap_or_default().iter()
+            .map(|(from, to, color)| (len.saturating_sub(*from), len.saturating_sub(*to), *color))
+            .collect()
+    }
+
+    /* parses the queued lines without executing them or printing errors */
+    fn probe(&mut self, core: &mut ShellCore) {
+        let exit_status = core.data.get_param("?");
+        let stderr = fd::backup(2);
+        if let Ok(null) = File::options().write(true).open("/dev/null") {
+            fd::replace(null.into_raw_fd(), 2);
+        }
+        while ! self.incomplete && self.feed_queued_line(core) {
+            let _ = Script::parse(self, core, false);
+        }
+        fd::replace(stderr, 2);
+
+        core.data.alias_memo.clear();
+        core.data.set_param("?", &exit_status);
+    }
+
+    pub fn is_painting(&self) -> bool {
+        self.spans.is_some()
+    }
+
+    /* marks the next len bytes */
+    pub fn paint(&mut self, len: usize, color: Color) {
+        let rem = self.remaining.len();
+        if let Some(spans) = self.spans.as_mut() {
+            spans.push((rem, rem.saturating_sub(len), color));
+        }
+    }
+
+    /* marks the part consumed since the length of the remaining text was from */
+    pub fn paint_since(&mut self, from: usize, color: Color) {
+        let rem = self.remaining.len();
+        if let Some(spans) = self.spans.as_mut() {
+            spans.push((from, rem, color));
         }
     }
 
@@ -60,8 +137,13 @@ impl Feeder {
         }
     }
 
+    /* the marks in the rewound part are removed unless the text turns out to be incomplete */
     pub fn rewind(&mut self) {
         self.remaining = self.backup.pop().expect("SUSHI INTERNAL ERROR (backup error)");
+        let rem = self.remaining.len();
+        if let (Some(spans), false) = (self.spans.as_mut(), self.incomplete) {
+            spans.retain(|s| s.0 > rem);
+        }
     }   
 
     fn read_line_stdin(core: &mut ShellCore) -> Result<String, InputError> {
@@ -78,24 +160,118 @@ impl Feeder {
         if len == 0 {
             Err(InputError::Eof)
         }else{
-            Ok(line)
+            Ok(Self::expand_history(core, line, false).unwrap_or_default())
+        }
+    }
+
+    /* PROMPT_COMMAND: a string or an array of commands */
+    fn run_prompt_command(core: &mut ShellCore) {
+        let commands = match core.data.get_array_len("PROMPT_COMMAND") {
+            0 => vec![core.data.get_param("PROMPT_COMMAND")],
+            _ => core.data.get_array_all("PROMPT_COMMAND"),
+        };
+
+        let exit_status = core.data.get_param("?");
+        for com in commands.iter().filter(|c| ! c.is_empty()) {
+            let mut feeder = Feeder::new(com);
+            if let Some(mut s) = Script::parse(&mut feeder, core, false) {
+                s.exec(core);
+            }
+        }
+        core.data.set_param("?", &exit_status);
+    }
+
+    fn read_line_interactive(core: &mut ShellCore, prompt: &str) -> Result<String, InputError> {
+        let mut preset = String::new();
+        loop {
+            let line = terminal::read_line(core, prompt, &preset)?;
+            match Self::expand_history(core, line, true) {
+                Ok(ln) => return Ok(ln),
+                Err(ln) => preset = ln,
+            }
+        }
+    }
+
+    /* history expansion of an input line from the terminal or the standard input.
+     * Err has the line to edit again under histverify or histreedit */
+    fn expand_history(core: &mut ShellCore, line: String, interactive: bool) -> Result<String, String> {
+        if ! core.data.flags.contains('H') || ! core.options.query("history") {
+            return Ok(line);
+        }
+
+        match core.expand_history(&line) {
+            Ok(None) => Ok(line),
+            Ok(Some((expanded, _))) if interactive && core.shopts.query("histverify") => {
+                Err(expanded.trim_end_matches('\n').to_string())
+            },
+            Ok(Some((expanded, print_only))) => {
+                eprintln!("{}", expanded.trim_end_matches('\n'));
+                if ! print_only {
+                    return Ok(expanded);
+                }
+                core.add_history(&expanded);
+                Ok("\n".to_string())
+            },
+            Err(msg) => {
+                eprintln!("sush: {}", msg);
+                if interactive && core.shopts.query("histreedit") {
+                    return Err(line.trim_end_matches('\n').to_string());
+                }
+                Ok("\n".to_string())
+            },
+        }
+    }
+
+    fn split_lines(text: &str) -> Vec<String> {
+        text.split_inclusive('\n').map(|s| s.to_string()).collect()
+    }
+
+    /* the parser reads a multi-line text from the terminal line by line */
+    fn feed_queued_line(&mut self, core: &mut ShellCore) -> bool {
+        if self.queued.is_empty() {
+            return false;
+        }
+
+        let line = self.queued.remove(0);
+        match self.probing {
+            true  => self.remaining += &line,
+            false => self.add_line(line.clone(), core),
         }
+        self.add_backup(&line);
+        true
+    }
+
+    fn add_lines(&mut self, text: String, core: &mut ShellCore) {
+        let mut lines = Self::split_lines(&text);
+        if lines.is_empty() {
+            return;
+        }
+        let first = lines.remove(0);
+        self.queued.extend(lines);
+        self.add_line(first.clone(), core);
+        self.add_backup(&first);
     }
 
     fn feed_additional_line_core(&mut self, core: &mut ShellCore) -> Result<(), InputError> {
+        if self.feed_queued_line(core) {
+            return Ok(());
+        }
+        if self.probing {
+            self.incomplete = true;
+            return Err(InputError::Interrupt);
+        }
         if core.sigint.load(Relaxed) {
             return Err(InputError::Interrupt);
         }
 
         let line = match ! core.read_stdin {
-            true  => terminal::read_line(core, "PS2"),
+            true  => Self::read_line_interactive(core, "PS2"),
             false => Self::read_line_stdin(core),
         };
 
         match line { 
             Ok(ln) => {
-                self.add_line(ln.clone(), core);
-                self.add_backup(&ln);
+                self.add_lines(ln, core);
                 Ok(())
             },
             Err(e) => Err(e),
@@ -121,15 +297,76 @@ impl Feeder {
         }
     }
 
+    pub fn feed_here_document(&mut self, core: &mut ShellCore,
+                              delim: &str, strip_tab: bool) -> Option<String> {
+        let start = match self.remaining.find('\n') {
+            Some(n) => n + 1,
+            None    => {
+                self.remaining += "\n";
+                self.remaining.len()
+            },
+        };
+
+        let lineno = self.lineno;
+        let mut body = String::new();
+        let mut end = start;
+        loop {
+            if end >= self.remaining.len() {
+                match self.feed_additional_line_core(core) {
+                    Ok(()) => continue,
+                    Err(InputError::Eof) => {
+                        let msg = format!("warning: here-document at line {} delimited by end-of-file (wanted `{}')",
+                                          lineno, delim);
+                        error_message::print(&msg, core, true);
+                        break;
+                    },
+                    Err(InputError::Interrupt) => {
+                        core.data.set_param("?", "130");
+                        return None;
+                    },
+                }
+            }
+
+            let len = match self.remaining[end..].find('\n') {
+                Some(n) => n + 1,
+                None    => self.remaining.len() - end,
+            };
+            let mut line = self.remaining[end..end+len].to_string();
+            end += len;
+
+            if strip_tab {
+                line = line.trim_start_matches('\t').to_string();
+            }
+            if line.trim_end_matches('\n') == delim {
+                break;
+            }
+            if ! line.ends_with("\n") {
+                line += "\n";
+            }
+            body += &line;
+        }
+
+        self.remaining = self.remaining[..start].to_string() + &self.remaining[end..];
+        Some(body)
+    }
+
     pub fn feed_line(&mut self, core: &mut ShellCore) -> Result<(), InputError> {
+        if self.feed_queued_line(core) {
+            return Ok(());
+        }
+
+        if ! core.read_stdin {
+            Self::run_prompt_command(core);
+        }
+
         let line = match ! core.read_stdin {
-            true  => terminal::read_line(core, "PS1"),
+            true  => Self::read_line_interactive(core, "PS1"),
             false => Self::read_line_stdin(core),
         };
 
         match line {
             Ok(ln) => {
-                self.add_line(ln, core);
+                self.add_lines(ln, core);
                 Ok(())
             },
             Err(e) => Err(e),
@@ -2,6 +2,7 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 use super::Feeder;
+use super::terminal::highlight::Color;
 use crate::ShellCore;
 
 impl Feeder {
@@ -111,13 +112,6 @@ impl Feeder {
         }
     }
 
-    pub fn scanner_history_expansion(&mut self, _: &mut ShellCore) -> usize {
-        match self.starts_with("!$") {
-            true  => 2,
-            false => 0,
-        }
-    }
-
     pub fn scanner_dollar_special_and_positional_param(&mut self, core: &mut ShellCore) -> usize {
         if ! self.starts_with("$") {
             return 0;
@@ -206,6 +200,21 @@ impl Feeder {
             ">>", "<<", "<=", ">=", "&", "^", "=", "+", "-", "/", "*", "%", "<", ">", "|", "^", ","])
     }
 
+    /* {varname} just before a redirect symbol */
+    pub fn scanner_redirect_varname(&mut self) -> usize {
+        if ! self.starts_with("{") || self.remaining[1..].starts_with(|c: char| c.is_ascii_digit()) {
+            return 0;
+        }
+
+        let name_len = self.remaining[1..].chars()
+                       .take_while(|c| *c == '_' || c.is_ascii_alphanumeric()).count();
+        let rest = &self.remaining[name_len+1..];
+        match name_len > 0 && rest.starts_with("}") && rest[1..].starts_with(['<', '>']) {
+            true  => name_len + 2,
+            false => 0,
+        }
+    }
+
     pub fn scanner_uint(&mut self, core: &mut ShellCore) -> usize {
         let judge = |ch| '0' <= ch && ch <= '9';
         self.scanner_chars(judge, core, 0)
@@ -253,7 +262,7 @@ impl Feeder {
         self.scanner_one_of(&["|&","|"])
     }
 
-    pub fn scanner_comment(&self) -> usize {
+    pub fn scanner_comment(&mut self) -> usize {
         if ! self.remaining.starts_with("#") {
             return 0;
         }
@@ -265,16 +274,64 @@ impl Feeder {
             }
             ans += ch.len_utf8();
         }
+        self.paint(ans, Color::Comment);
         ans
     }
 
     pub fn scanner_redirect_symbol(&mut self, core: &mut ShellCore) -> usize {
-        self.backslash_check_and_feed(vec![">", "&"], core);
-        self.scanner_one_of(&["&>", ">&", ">>", "<", ">"])
+        self.backslash_check_and_feed(vec![">", "&", "<"], core);
+        self.scanner_one_of(&["&>>", "&>", ">&", ">>", ">|", "<<<", "<<-", "<<", "<&", "<>", "<", ">"])
+    }
+
+    pub fn scanner_here_document_escaped_char(&mut self) -> usize {
+        self.scanner_one_of(&["\\$", "\\`", "\\\\", "\\\n"])
+    }
+
+    pub fn scanner_here_document_subword(&mut self) -> usize {
+        let mut ans = 0;
+        for ch in self.remaining.chars() {
+            if ans > 0 && ( "$\\_".find(ch).is_some() || ch.is_ascii_alphanumeric() ) {
+                break;
+            }
+            ans += ch.len_utf8();
+        }
+        ans
     }
 
     pub fn scanner_parameter_default_symbol(&mut self) -> usize {
-        self.scanner_one_of(&[":-", ":=", ":?", ":+"])
+        self.scanner_one_of(&[":-", ":=", ":?", ":+", "-", "=", "?", "+"])
+    }
+
+    pub fn scanner_parameter_remove_symbol(&mut self) -> usize {
+        self.scanner_one_of(&["##", "#", "%%", "%"])
+    }
+
+    pub fn scanner_parameter_case_conv_symbol(&mut self) -> usize {
+        self.scanner_one_of(&["^^", "^", ",,", ","])
+    }
+
+    pub fn scanner_parameter_transform(&mut self) -> usize {
+        let mut chars = self.remaining.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some('@'), Some(c), Some('}')) if "QEPAaULuK".find(c).is_some() => 2,
+            _ => 0,
+        }
+    }
+
+    pub fn scanner_parameter_substr(&mut self) -> usize {
+        let mut nest = 0;
+        let mut ans = 0;
+        for ch in self.remaining.chars() {
+            match ch {
+                '(' | '{' | '[' => nest += 1,
+                ')' | ']'       => nest -= 1,
+                '}' | ':' if nest == 0 => break,
+                '}'             => nest -= 1,
+                _ => {},
+            }
+            ans += ch.len_utf8();
+        }
+        ans
     }
 
     pub fn scanner_test_check_option(&mut self, core: &mut ShellCore) -> usize {
@@ -2,19 +2,24 @@
 //SPDX-License-Identifier: BSD-3-Clause
 
 mod completion;
-
-use crate::{file_check, InputError, ShellCore};
+mod edit;
+pub mod highlight;
+mod menu;
+mod multiline;
+mod prompt;
+mod search;
+mod vi;
+
+use crate::{Feeder, InputError, Script, ShellCore};
+use crate::core::history::HistoryEntry;
+use crate::core::keymap::{Binding, Lookup};
 use std::io;
-use std::fs::File;
 use std::io::{Write, Stdout};
 use std::sync::atomic::Ordering::Relaxed;
-use std::path::Path;
-use nix::unistd;
-use nix::unistd::User;
 use termion::cursor::DetectCursorPos;
 use termion::event;
 use termion::raw::{IntoRawMode, RawTerminal};
-use termion::input::TermRead;
+use termion::input::{Keys, TermRead};
 use unicode_width::UnicodeWidthChar;
 
 struct Terminal {
@@ -24,146 +29,85 @@ struct Terminal {
     chars: Vec<char>,
     head: usize,
     hist_ptr: usize,
+    hist_prefix: Option<String>, // for Up/Down on a non-empty line
+    /* for the emacs editing commands */
+    killing: bool,
+    yanked: Option<(usize, usize)>, // (the start of the text, the index of the kill ring)
+    last_arg: Option<(usize, usize)>, // (the start of the text, the index of the history)
+    undo_stack: Vec<(Vec<char>, usize)>,
+    vi: Option<vi::ViState>, // None in emacs mode
+    bell_style: String, // audible, visible or none
+    multiline: bool, // Enter on an incomplete command inserts a newline
+    /* for the highlight shopt */
+    colors: Vec<highlight::Color>,
+    suggestion: String,
     prompt_width_map: Vec<usize>,
     /* for extended completion */
-    completion_candidate: String,
-    tab_row: i32,
-    tab_col: i32,
     escape_at_completion: bool,
+    completion_options: Option<Vec<String>>, // None: default completion
+    completion_context: completion::context::Context,
+    completion_descriptions: Vec<String>, // given by completion functions
+    completion_matcher: completion::matcher::Matcher,
+    completion_scores: Vec<i32>, // of fuzzy matches
 }
 
-fn oct_string(s: &str) -> bool {
-    if s.chars().nth(0) != Some('\\') {
-        return false;
-    }
-
-    for i in 1..4 {
-        match s.chars().nth(i) {
-            Some(c) => {
-                if c < '0' || '9' < c {
-                    return false;
-                }
-            },
-            _ => return false,
-        }
-    }
-
-    true
-}
-
-fn oct_to_hex_in_str(from: &str) -> String {
-    let mut i = 0;
-    let mut pos = vec![];
-
-    for ch in from.chars() {
-        if oct_string(&from[i..]) {
-            pos.push(i);
-        }
-        i += ch.len_utf8();
-    }
-
-    let mut prev = 0;
-    let mut ans = String::new();
-    for p in pos {
-        ans += &from[prev..p];
-        if let Ok(n) = u32::from_str_radix(&from[p+1..p+4], 8) {
-            ans += &char::from_u32(n).unwrap().to_string();
-        }
-        prev = p+4;
-    }
-    ans += &from[prev..];
-    ans
+/* for ${name@P} */
+pub fn expand_prompt(core: &mut ShellCore, raw: &str) -> String {
+    prompt::expand(core, raw).replace([prompt::START_IGNORE, prompt::END_IGNORE], "")
 }
 
 impl Terminal {
     pub fn new(core: &mut ShellCore, ps: &str) -> Self {
         let raw_prompt = core.data.get_param(ps);
-        let ansi_on_prompt = oct_to_hex_in_str(&raw_prompt);
-
-        let replaced_prompt = Self::make_prompt_string(&ansi_on_prompt);
-        let prompt = replaced_prompt.replace("\\[", "").replace("\\]", "").to_string();
+        let mode = match core.options.query("vi") {
+            true  => vi::INSERT_MODE_STRING,
+            false => "",
+        };
+        let expanded_prompt = mode.to_string() + &prompt::expand(core, &raw_prompt);
+        let prompt = expanded_prompt.replace([prompt::START_IGNORE, prompt::END_IGNORE], "");
         print!("{}", prompt);
         io::stdout().flush().unwrap();
 
         let mut sout = io::stdout().into_raw_mode().unwrap();
-        let row = sout.cursor_pos().unwrap_or((1,1)).1;
+        let row = sout.cursor_pos().unwrap_or((1,1)).1 as usize;
+        let row = std::cmp::max(row.saturating_sub(prompt.matches('\n').count()), 1);
 
         Terminal {
             prompt: prompt.to_string(),
             stdout: sout,
-            prompt_row: row as usize,
+            prompt_row: row,
             chars: prompt.chars().collect(),
             head: prompt.chars().count(),
             hist_ptr: 0,
-            prompt_width_map: Self::make_width_map(&replaced_prompt),
-            completion_candidate: String::new(),
-            tab_row: -1,
-            tab_col: -1,
+            hist_prefix: None,
+            killing: false,
+            yanked: None,
+            last_arg: None,
+            undo_stack: vec![],
+            vi: match core.options.query("vi") {
+                true  => Some(vi::ViState::default()),
+                false => None,
+            },
+            bell_style: core.keymap.variables["bell-style"].clone(),
+            multiline: ps == "PS1",
+            colors: vec![],
+            suggestion: String::new(),
+            prompt_width_map: Self::make_width_map(&expanded_prompt),
             escape_at_completion: true,
+            completion_options: None,
+            completion_context: Default::default(),
+            completion_descriptions: vec![],
+            completion_matcher: Default::default(),
+            completion_scores: vec![],
         }
     }
 
-    fn get_branch(cwd: &String) -> String {
-        let mut dirs: Vec<String> = cwd.split("/").map(|s| s.to_string()).collect();
-        while dirs.len() > 0 {
-            let path = dirs.join("/") + "/.git/HEAD";
-            dirs.pop();
-
-            if ! file_check::is_regular_file(&path) {
-                continue;
-            }
-
-            if let Ok(mut f) = File::open(Path::new(&path)){
-                return match f.read_line() {
-                    Ok(Some(s)) => s.replace("ref: refs/heads/","") + "🌵",
-                    _ => "".to_string(),
-                };
-            }
-        }
-
-        "".to_string()
-    }
-
-    fn make_prompt_string(raw: &str) -> String {
-        let uid = unistd::getuid();
-        let user = match User::from_uid(uid) {
-            Ok(Some(u)) => u.name,
-            _ => "".to_string(),
-        };
-        let hostname = match unistd::gethostname() {
-            Ok(h) => h.to_string_lossy().to_string(),
-            _ => "".to_string(),
-        };
-
-        let homedir = match User::from_uid(uid) {
-            Ok(Some(u)) => u.dir.to_string_lossy().to_string(),
-            _ => "".to_string(),
-        };
-        let mut cwd = match unistd::getcwd() {
-            Ok(p) => p.to_string_lossy()
-                      .to_string(),
-            _ => "".to_string(),
-        };
-        let branch = Self::get_branch(&cwd);
-
-        if cwd.starts_with(&homedir) {
-            cwd = cwd.replacen(&homedir, "~", 1);
-        }
-
-        raw.replace("\\u", &user)
-           .replace("\\h", &hostname)
-           .replace("\\w", &cwd)
-           .replace("\\b", &branch)
-           .to_string()
-    }
-
     fn make_width_map(prompt: &str) -> Vec<usize> {
-        let tmp = prompt.replace("\\[", "\x01").replace("\\]", "\x02").to_string();
         let mut in_escape = false;
         let mut ans = vec![];
-        for c in tmp.chars() {
-            if c == '\x01' || c == '\x02' {
-                in_escape = c == '\x01';
+        for c in prompt.chars() {
+            if c == prompt::START_IGNORE || c == prompt::END_IGNORE {
+                in_escape = c == prompt::START_IGNORE;
                 continue;
             }
 
@@ -240,7 +184,7 @@ impl Terminal {
         if erase {
             self.write(&termion::clear::AfterCursor.to_string());
         }
-        self.write(&self.get_string(0).replace("\n", "\n\r"));
+        self.write(&self.decorated_string().replace("\n", "\n\r"));
         self.goto(self.head);
         self.flush();
     }
@@ -272,18 +216,6 @@ impl Terminal {
         self.chars[from..].iter().collect()
     }
 
-    pub fn goto_origin(&mut self) {
-        self.head = self.prompt.chars().count();
-        self.goto(self.head);
-        self.flush();
-    }
-
-    pub fn goto_end(&mut self) {
-        self.head = self.chars.len();
-        self.goto(self.head);
-        self.flush();
-    }
-
     pub fn shift_cursor(&mut self, shift: i32) {
         let prev = self.head;
         Self::shift_in_range(&mut self.head, shift, 
@@ -324,7 +256,11 @@ impl Terminal {
     pub fn call_history(&mut self, inc: i32, core: &mut ShellCore){
         let prev = self.hist_ptr;
         let prev_str = self.get_string(self.prompt.chars().count());
-        Self::shift_in_range(&mut self.hist_ptr, inc, 0, std::isize::MAX as usize);
+        Self::shift_in_range(&mut self.hist_ptr, inc, 0, core.history.len() - 1);
+        if prev == self.hist_ptr {
+            self.cloop();
+            return;
+        }
 
         self.chars = self.prompt.chars().collect();
         self.chars.extend(core.fetch_history(self.hist_ptr, prev, prev_str).replace("↵ \0", "\n").chars());
@@ -332,125 +268,207 @@ impl Terminal {
         self.rewrite(true);
     }
 
-    pub fn set_double_tab_completion(&mut self) {
-        let s = self.completion_candidate.clone() + " ";
-        self.replace_input(&s);
-    }
-
     pub fn cloop(&mut self) {
-        print!("\x07");
+        match self.bell_style.as_str() {
+            "none" | "off" => return,
+            "visible" => self.write("\x1b[?5h\x1b[?5l"),
+            _ => self.write("\x07"),
+        }
         self.flush();
     }
 }
 
-fn is_completion_key(key: event::Key) -> bool {
-    match key {
-        event::Key::Char('\t') 
-            | event::Key::Left | event::Key::Down
-            | event::Key::Right | event::Key::Up => true,
-        _ => false,
+/* preset: a line given back for editing (histverify, histreedit) */
+pub fn read_line(core: &mut ShellCore, prompt: &str, preset: &str) -> Result<String, InputError>{
+    let ans = edit_line(core, prompt, preset);
+    core.history.remove(0);
+    ans
+}
+
+fn run_command(term: &mut Terminal, core: &mut ShellCore, command: &str) {
+    let prompt_len = term.line_start();
+    let line = term.get_string(prompt_len);
+    core.data.set_param("READLINE_LINE", &line);
+    core.data.set_param("READLINE_POINT", &(term.head - prompt_len).to_string());
+
+    term.goto(term.chars.len());
+    term.write("\r\n");
+    term.flush();
+    let _ = term.stdout.suspend_raw_mode();
+    let mut feeder = Feeder::new(command);
+    if let Some(mut s) = Script::parse(&mut feeder, core, false) {
+        s.exec(core);
     }
+    let _ = term.stdout.activate_raw_mode();
+    term.prompt_row = term.stdout.cursor_pos().unwrap_or((1, 1)).1 as usize;
+
+    let line = core.data.get_param("READLINE_LINE");
+    let point = core.data.get_param("READLINE_POINT").parse::<usize>().unwrap_or(usize::MAX);
+    term.chars = term.prompt.chars().collect();
+    term.chars.extend(line.chars());
+    term.head = std::cmp::min(prompt_len + point, term.chars.len());
+    term.rewrite(true);
 }
 
-fn on_arrow_key(term: &mut Terminal, core: &mut ShellCore, key: &event::Key, tab_num: usize) {
-    if tab_num > 1 {
-        match key {
-            event::Key::Down  => term.tab_row += 1,
-            event::Key::Up    => term.tab_row -= 1,
-            event::Key::Right => term.tab_col += 1,
-            event::Key::Left  => term.tab_col -= 1,
-            _ => {},
-        }
-        term.completion(core, tab_num);
-    }else{
-        match key {
-            event::Key::Down  => term.call_history(-1, core),
-            event::Key::Up    => term.call_history(1, core),
-            event::Key::Right => term.shift_cursor(1),
-            event::Key::Left  => term.shift_cursor(-1),
-            _ => {},
-        }
+/* Ok(true): the line is accepted */
+fn run_function(term: &mut Terminal, core: &mut ShellCore, func: &str, key: &event::Key,
+                keys: &mut Keys<io::Stdin>, pending: &mut Option<event::Key>,
+                tab_num: &mut usize) -> Result<bool, InputError> {
+    match func {
+        "abort" => term.cloop(),
+        "accept-line" => return Ok(term.accept(core, false)),
+        "backward-char" => term.shift_cursor(-1),
+        "backward-delete-char" => term.backspace(),
+        "backward-kill-word" => term.backward_kill_word(core),
+        "backward-word" => term.backward_word(),
+        "beginning-of-history" => term.call_history(core.history.len() as i32, core),
+        "beginning-of-line" => term.goto_row_start(),
+        "capitalize-word" => term.change_case('c'),
+        "clear-screen" => term.clear_screen(),
+        "complete" => {
+            *tab_num += 1; //reset by the other functions
+            match *tab_num {
+                1 => if term.completion(core) {
+                    *pending = term.menu_select(core, keys);
+                    *tab_num = 0;
+                },
+                _ => {
+                    *pending = term.menu_completion(core, keys);
+                    *tab_num = 0;
+                },
+            }
+        },
+        "delete-char" => {
+            if term.chars.len() == term.line_start() && *key == event::Key::Ctrl('d') {
+                term.write("\r\n");
+                return Err(InputError::Eof);
+            }
+            term.delete();
+        },
+        "downcase-word" => term.change_case('l'),
+        "edit-and-execute-command" => return Ok(term.edit_and_execute_command(core) && term.accept(core, true)),
+        "end-of-history" => term.call_history(- (term.hist_ptr as i32), core),
+        "end-of-line" => term.goto_row_end(),
+        "forward-char" => if ! term.accept_suggestion() { term.shift_cursor(1) },
+        "forward-search-history" => *pending = term.incremental_search(core, keys, false),
+        "forward-word" => term.forward_word(),
+        "force-accept-line" => return Ok(term.accept(core, true)),
+        "history-search-backward" => if ! term.row_up() { term.call_history_with_prefix(1, core) },
+        "history-search-forward" => if ! term.row_down() { term.call_history_with_prefix(-1, core) },
+        "kill-line" => term.kill_line(core),
+        "kill-word" => term.kill_word(core),
+        "next-history" => if ! term.row_down() { term.call_history(-1, core) },
+        "previous-history" => if ! term.row_up() { term.call_history(1, core) },
+        "quoted-insert" => match keys.next() {
+            Some(Ok(event::Key::Char(c))) => term.insert(c),
+            Some(Ok(event::Key::Ctrl(c))) => term.insert((c as u8 & 0x1F) as char),
+            Some(Ok(event::Key::Esc)) => term.insert('\x1b'),
+            _ => term.cloop(),
+        },
+        "reverse-search-history" => *pending = term.incremental_search(core, keys, true),
+        "self-insert" => match key {
+            event::Key::Char(c) => term.insert(*c),
+            _ => term.cloop(),
+        },
+        "transpose-chars" => term.transpose_chars(),
+        "transpose-words" => term.transpose_words(),
+        "undo" => term.undo(),
+        "unix-line-discard" => term.unix_line_discard(core),
+        "unix-word-rubout" => term.unix_word_rubout(core),
+        "upcase-word" => term.change_case('u'),
+        "yank" => term.yank(core),
+        "yank-last-arg" => term.yank_last_arg(core),
+        "yank-pop" => term.yank_pop(core),
+        _ => term.cloop(),
     }
+    Ok(false)
 }
 
-pub fn read_line(core: &mut ShellCore, prompt: &str) -> Result<String, InputError>{
+fn edit_line(core: &mut ShellCore, prompt: &str, preset: &str) -> Result<String, InputError>{
     let mut term = Terminal::new(core, prompt);
+    core.history.insert(0, HistoryEntry::default()); //the line under editing
+    preset.chars().for_each(|c| term.insert(c));
     let mut term_size = Terminal::size();
-    core.history.insert(0, String::new());
-    let mut prev_key = event::Key::Char('a');
     let mut tab_num = 0;
-
-    for c in io::stdin().keys() {
+    let mut keys = io::stdin().keys();
+    let mut pending = None; //a key given back from the incremental search
+    let mut key_seq = vec![]; //keys of a binding not completed yet
+
+    loop {
+        let c = match pending.take() {
+            Some(k) => k,
+            None => match keys.next() {
+                Some(k) => k.unwrap(),
+                None    => break,
+            },
+        };
         term.check_size_change(&mut term_size);
+        let before = (term.chars.clone(), term.head);
 
-        match c.as_ref().unwrap() {
-            event::Key::Ctrl('a') => term.goto_origin(),
-            event::Key::Ctrl('b') => term.shift_cursor(-1),
-            event::Key::Ctrl('c') => {
-                core.sigint.store(true, Relaxed);
-                term.goto(term.chars.len());
-                term.write("^C\r\n");
-                return Err(InputError::Interrupt);
+        let vi_result = term.vi_key(core, &c);
+        let c = match vi_result {
+            vi::ViResult::Accept => match term.accept(core, c != event::Key::Char('\n')) {
+                true  => break,
+                false => event::Key::Null,
             },
-            event::Key::Ctrl('d') => {
-                if term.chars.len() == term.prompt.chars().count() {
-                    term.write("\r\n");
-                    return Err(InputError::Eof);
-                }else{
-                    term.delete();
-                }
-            },
-            event::Key::Ctrl('e') => term.goto_end(),
-            event::Key::Ctrl('f') => term.shift_cursor(1),
-            event::Key::Down |
-            event::Key::Left |
-            event::Key::Right |
-            event::Key::Up => on_arrow_key(&mut term, core, c.as_ref().unwrap(), tab_num),
-            event::Key::Backspace => term.backspace(),
-            event::Key::Delete => term.delete(),
-            event::Key::Char('\n') => {
-                if term.completion_candidate.len() > 0 {
-                    term.set_double_tab_completion();
-                }else{
-                    term.goto(term.chars.len());
-                    term.write("\r\n");
-                    term.chars.push('\n');
+            vi::ViResult::Done => event::Key::Null, //nothing to do anymore
+            vi::ViResult::Pass => c,
+        };
+
+        if c == event::Key::Ctrl('c') {
+            core.sigint.store(true, Relaxed);
+            term.clear_suggestion();
+            term.goto(term.chars.len());
+            term.write("^C\r\n");
+            return Err(InputError::Interrupt);
+        }
+
+        let binding = if c == event::Key::Null {
+            None
+        }else{
+            key_seq.push(c);
+            match core.keymap.lookup(&key_seq) {
+                Lookup::Prefix => continue,
+                Lookup::Found(b) => { key_seq.clear(); Some(b) },
+                Lookup::NotFound => match (std::mem::take(&mut key_seq).len(), &c) {
+                    (1, event::Key::Char(_)) => Some(Binding::Function("self-insert".to_string())),
+                    _ => Some(Binding::Function(String::new())),
+                },
+            }
+        };
+
+        let func = match binding {
+            Some(Binding::Function(f)) => {
+                if run_function(&mut term, core, &f, &c, &mut keys, &mut pending, &mut tab_num)? {
                     break;
                 }
+                f
             },
-            event::Key::Char('\t') => {
-                if tab_num == 0 || prev_key == event::Key::Char('\t') {
-                    tab_num += 1;
-                }
-                if tab_num == 2 {
-                    term.tab_row = -1;
-                    term.tab_col = 0;
-                }else if tab_num > 2 {
-                    term.tab_row += 1;
-                }
-                term.completion(core, tab_num);
+            Some(Binding::Macro(text)) => {
+                term.insert_str(&text);
+                term.rewrite(true);
+                "self-insert".to_string()
             },
-            event::Key::Char(c) => {
-                term.insert(*c);
+            Some(Binding::Command(com)) => {
+                run_command(&mut term, core, &com);
+                String::new()
             },
-            _  => {},
-        }
+            None => String::new(),
+        };
+
         term.check_scroll();
-        prev_key = c.as_ref().unwrap().clone();
-        if ! is_completion_key(prev_key) {
+        term.highlight(core);
+        term.reset_repetition(&func);
+        if term.vi.is_none() && func != "undo" && term.chars != before.0 {
+            term.undo_stack.push(before);
+        }
+        if ! func.starts_with("history-search-") {
+            term.hist_prefix = None;
+        }
+        if func != "complete" {
             tab_num = 0;
-            term.completion_candidate = String::new();
         }
     }
 
-    core.history[0] = term.get_string(term.prompt.chars().count());
-    core.history[0].pop();
-
-    /*
-    if core.history[0].len() == 0
-    || (core.history.len() > 1 && core.history[0] == core.history[1]) {
-        core.history.remove(0);
-    }*/
-
     Ok(term.get_string(term.prompt.chars().count()))
 }
@@ -1,30 +1,27 @@
 //SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
 //SPDX-License-Identifier: BSD-3-Clause
 
+pub mod context;
+pub mod matcher;
+
 use crate::{error_message, file_check, Feeder, ShellCore, utils};
 use crate::core::builtins::completion;
+use crate::core::builtins::completion::CompSpec;
 use crate::elements::command::simple::SimpleCommand;
-use crate::elements::command::Command;
-use crate::elements::io::pipe::Pipe;
 use crate::feeder::terminal::Terminal;
-use termion::cursor::DetectCursorPos;
-use unicode_width::UnicodeWidthStr;
-
-fn str_width(s: &str) -> usize {
-    UnicodeWidthStr::width(s)
-}
-
-fn common_length(chars: &Vec<char>, s: &String) -> usize {
+use self::context::{Context, Kind};
+use self::matcher::Matcher;
+fn common_length(chars: &[char], s: &str, matcher: &Matcher) -> usize {
     let max_len = chars.len();
     for (i, c) in s.chars().enumerate() {
-        if i >= max_len || chars[i] != c {
+        if i >= max_len || ! matcher.same_char(chars[i], c) {
             return i;
         }
     }
     max_len
 }
 
-fn common_string(paths: &Vec<String>) -> String {
+fn common_string(paths: &[String], matcher: &Matcher) -> String {
     if paths.len() == 0 {
         return "".to_string();
     }
@@ -33,7 +30,7 @@ fn common_string(paths: &Vec<String>) -> String {
     let mut common_len = ref_chars.len();
 
     for path in &paths[1..] {
-        let len = common_length(&ref_chars, &path);
+        let len = common_length(&ref_chars, path, matcher);
         common_len = std::cmp::min(common_len, len);
     }
 
@@ -47,49 +44,181 @@ fn is_dir(s: &str, core: &mut ShellCore) -> bool {
     file_check::is_dir(&s.replace(&tilde_prefix, &tilde_path))
 }
 
+/* $name or ${name for the word beginning with them */
+fn variable_names(core: &mut ShellCore, word: &str) -> Vec<String> {
+    let (head, name) = match word.strip_prefix("${") {
+        Some(n) => ("${", n),
+        None    => ("$", &word[1..]),
+    };
+    core.data.get_keys().into_iter()
+        .filter(|k| k.starts_with(name) && k.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_'))
+        .map(|k| head.to_owned() + &k)
+        .collect()
+}
+
+/* directories under the current directory and the ones in CDPATH */
+fn cd_directories(core: &mut ShellCore, cur: &str) -> Vec<String> {
+    let mut ans = completion::compgen_d(core, cur);
+    if ! cur.starts_with(['/', '.', '~']) {
+        let cdpath = core.data.get_param("CDPATH");
+        for dir in cdpath.split(':').filter(|d| ! d.is_empty()) {
+            let prefix = dir.trim_end_matches('/').to_string() + "/";
+            let found = completion::compgen_d(core, &(prefix.clone() + cur));
+            ans.extend(found.iter().filter_map(|p| p.strip_prefix(&prefix)).map(|p| p.to_string()));
+        }
+    }
+    ans.sort();
+    ans.dedup();
+    ans
+}
+
+/* the positions where words begin, with the same rule as utils::split_words */
+fn word_starts(s: &str) -> Vec<usize> {
+    let mut ans = vec![];
+    let mut quote = None;
+    let mut escaped = false;
+    let mut in_word = false;
+
+    for (i, c) in s.chars().enumerate() {
+        if ! in_word && c != ' ' && c != '\t' {
+            ans.push(i);
+            in_word = true;
+        }
+
+        if escaped {
+            escaped = false;
+        }else if c == '\\' {
+            escaped = true;
+        }else if quote == Some(c) {
+            quote = None;
+        }else if quote.is_none() && (c == '\'' || c == '"') {
+            quote = Some(c);
+        }else if quote.is_none() && (c == ' ' || c == '\t') {
+            in_word = false;
+        }
+    }
+    ans
+}
+
 impl Terminal {
-    pub fn completion(&mut self, core: &mut ShellCore, tab_num: usize) {
+    /* returns true when the candidates should be selected in the menu */
+    pub fn completion(&mut self, core: &mut ShellCore) -> bool {
+        match self.set_candidates(core, 1) {
+            true  => self.try_completion(core),
+            false => {
+                self.cloop();
+                false
+            },
+        }
+    }
+
+    /* sets COMPREPLY for the word at the cursor and returns false when nothing is found */
+    pub fn set_candidates(&mut self, core: &mut ShellCore, tab_num: usize) -> bool {
         self.escape_at_completion = true;
+        self.completion_options = None;
+        self.completion_descriptions.clear();
+        self.completion_matcher = Matcher::default();
+        self.completion_scores.clear();
+        let left = self.chars[self.line_start()..self.head].to_vec();
+        self.completion_context = context::scan(&left);
         core.data.set_array("COMPREPLY", &vec![]);
-        self.set_completion_info(core);
-
-        if ! Self::set_custom_compreply(core)
-        && ! self.set_default_compreply(core) {
-            self.cloop();
-            return;
+        self.set_completion_info(core, tab_num);
+
+        let exit_status = core.data.get_param("?");
+        let custom = self.set_custom_compreply(core);
+        core.data.set_param("?", &exit_status);
+
+        if custom { // the candidates are for the whole word
+            let word = self.current_word_part();
+            let ctx = &mut self.completion_context;
+            ctx.start = ctx.word_start;
+            ctx.quote = None;
+            ctx.word = word;
+            if ctx.kind == Kind::Variable || ctx.kind == Kind::User {
+                ctx.kind = Kind::Argument;
+            }
         }
 
-        match tab_num  {
-            1 => self.try_completion(core),
-            _ => self.show_list(&core.data.get_array_all("COMPREPLY"), tab_num),
-        }
+        custom || self.set_default_compreply(core)
     }
 
-    fn set_custom_compreply(core: &mut ShellCore) -> bool {
-        let cur_pos = Self::get_cur_pos(core);
-        let prev_pos = cur_pos - 1;
-        let word_num = core.data.get_array_len("COMP_WORDS") as i32;
+    /* -E for an empty line, -I for the command word, the spec of the command, or -D */
+    fn find_compspec(core: &mut ShellCore, command: &str, cword: usize) -> Option<CompSpec> {
+        let specs = &core.completion_specs;
+        let basename = command.rsplit('/').next().unwrap_or(command);
+        let key = if command.is_empty() && specs.contains_key("-E") {
+            "-E"
+        }else if cword == 0 && specs.contains_key("-I") {
+            "-I"
+        }else if cword == 0 {
+            return None;
+        }else if specs.contains_key(command) {
+            command
+        }else if specs.contains_key(basename) {
+            basename
+        }else{
+            "-D"
+        };
+        specs.get(key).cloned()
+    }
 
-        if prev_pos < 0 || prev_pos >= word_num {
-            return false;
+    fn set_custom_compreply(&mut self, core: &mut ShellCore) -> bool {
+        let cword = Self::get_cur_pos(core) as usize;
+        let command = core.data.get_array("COMP_WORDS", "0");
+        let cur = self.current_word_part();
+        let prev = match cword {
+            0 => String::new(),
+            n => core.data.get_array("COMP_WORDS", &(n-1).to_string()),
+        };
+        let args = [command.clone(), cur.clone(), prev];
+
+        let mut list = None;
+        let mut options = vec![];
+        for _ in 0..8 { // a function returns 124 after loading a new compspec
+            let spec = match Self::find_compspec(core, &command, cword) {
+                Some(s) => s,
+                None => return false,
+            };
+            core.completion_options = Some(spec.options.clone());
+            list = completion::generate(core, &spec, &cur, &args);
+            options = core.completion_options.take().unwrap_or_default();
+            if list.is_some() {
+                break;
+            }
         }
 
-        let org_word = core.data.get_array("COMP_WORDS", "0");
-        let prev_word = core.data.get_array("COMP_WORDS", &prev_pos.to_string());
-        let cur_word = core.data.get_array("COMP_WORDS", &cur_pos.to_string());
+        /* a tab separates a candidate and its description */
+        let mut list: Vec<(String, String)> = list.unwrap_or_default().into_iter()
+            .map(|c| match c.split_once('\t') {
+                Some((w, d)) => (w.to_string(), d.to_string()),
+                None         => (c, String::new()),
+            }).collect();
+        let has = |o: &str| options.iter().any(|x| x == o);
+        if list.is_empty() && (has("default") || has("bashdefault")) {
+            return false;
+        }
+        if ! has("nosort") {
+            list.sort();
+        }
+        list.dedup_by(|a, b| a.0 == b.0);
 
-        match core.completion_functions.get(&org_word) {
-            Some(value) => {
-                let command = format!("prev={} cur={} {}", &prev_word, &cur_word, &value);//TODO: cur should be set
-                let mut feeder = Feeder::new(&command);                                        // by bash-completion 
+        let (list, descriptions): (Vec<String>, Vec<String>) = list.into_iter().unzip();
+        if descriptions.iter().any(|d| ! d.is_empty()) {
+            self.completion_descriptions = descriptions;
+        }
+        core.data.set_array("COMPREPLY", &list);
+        self.escape_at_completion = ! has("noquote");
+        self.completion_options = Some(options);
+        ! list.is_empty()
+    }
 
-                if let Some(mut a) = SimpleCommand::parse(&mut feeder, core) {
-                    let mut dummy = Pipe::new("".to_string());
-                    a.exec(core, &mut dummy);
-                }
-                core.data.get_array_len("COMPREPLY") != 0
-            },
-            _ => false
+    /* the part of the word at the cursor before the cursor */
+    fn current_word_part(&self) -> String {
+        let left = self.get_string(self.line_start());
+        let left: String = left.chars().take(self.head - self.line_start()).collect();
+        match word_starts(&left).last() {
+            Some(p) if ! left.ends_with(' ') || left.ends_with("\\ ") => left.chars().skip(*p).collect(),
+            _ => String::new(),
         }
     }
 
@@ -101,23 +230,31 @@ impl Terminal {
     }
 
     pub fn set_default_compreply(&mut self, core: &mut ShellCore) -> bool {
-
-        let pos = core.data.get_param("COMP_CWORD").to_string();
-        let last = core.data.get_array("COMP_WORDS", &pos);
-
-        let (tilde_prefix, tilde_path, last_tilde_expanded) = Self::set_tilde_transform(&last, core);
-
-        let mut args = vec!["".to_string(), "".to_string(), last_tilde_expanded.to_string()];
-        let list = match pos == "0" {
-            true  => {
-                if core.data.get_array_len("COMP_WORDS") == 0 {
-                    self.escape_at_completion = false;
-                    completion::compgen_h(core, &mut args).to_vec().into_iter().filter(|h| h.len() > 0).collect()
-                }else{
-                    completion::compgen_c(core, &mut args)
-                }
+        let ctx = self.completion_context.clone();
+        let (tilde_prefix, tilde_path, word) = Self::set_tilde_transform(&ctx.word, core);
+        let names = ctx.kind == Kind::Variable || ctx.kind == Kind::User
+                    || (ctx.kind == Kind::Command && ! word.contains('/'));
+        self.completion_matcher = Matcher::new(core, ! names);
+
+        let list = match ctx.kind {
+            Kind::Command if self.get_string(self.line_start()).trim() == "" => {
+                self.escape_at_completion = false;
+                completion::compgen_h(core).to_vec().into_iter().filter(|h| ! h.is_empty()).collect()
+            },
+            _ if ! self.completion_matcher.is_exact() => self.matched_candidates(core, &ctx, &word),
+            Kind::Variable => variable_names(core, &ctx.word),
+            Kind::User => {
+                let users = completion::action_list(core, "user", &ctx.word[1..]);
+                users.iter().map(|u| "~".to_owned() + u).collect()
             },
-            false => completion::compgen_f(core, &mut args),
+            Kind::Command => {
+                let mut commands = completion::compgen_c(core, &word);
+                commands.sort();
+                commands.dedup();
+                commands
+            },
+            Kind::Argument if ctx.command == "cd" => cd_directories(core, &word),
+            _ => completion::compgen_f(core, &word),
         };
 
         if list.len() == 0 {
@@ -129,133 +266,153 @@ impl Terminal {
         true
     }
 
-    pub fn try_completion(&mut self, core: &mut ShellCore) {
-        let pos = core.data.get_param("COMP_CWORD").to_string();
-        let target = core.data.get_array("COMP_WORDS", &pos);
+    /* candidates compared by the matcher, which are ranked when they are fuzzy matches */
+    fn matched_candidates(&mut self, core: &mut ShellCore, ctx: &Context, word: &str) -> Vec<String> {
+        let matcher = self.completion_matcher;
+        let pairs = |list: Vec<String>| list.into_iter().map(|c| (c.clone(), c)).collect();
 
-        if core.data.get_array_len("COMPREPLY") == 1 {
-            let output = core.data.get_array("COMPREPLY", "0");
-            let tail = match is_dir(&output, core) {
-                true  => "/",
-                false => " ",
-            };
-            self.replace_input(&(output + tail));
-            return;
-        }
+        let (list, scores) = match ctx.kind {
+            Kind::Variable => {
+                let head = if word.starts_with("${") {"${"} else {"$"};
+                matcher.select(pairs(variable_names(core, head)), word)
+            },
+            Kind::User => {
+                let users = completion::action_list(core, "user", "");
+                matcher.select(pairs(users.iter().map(|u| "~".to_owned() + u).collect()), word)
+            },
+            Kind::Command if ! word.contains('/') => {
+                let mut commands = completion::compgen_c(core, "");
+                commands.sort();
+                commands.dedup();
+                matcher.select(pairs(commands), word)
+            },
+            Kind::Argument if ctx.command == "cd" => {
+                let mut found: Vec<(String, i32)> = vec![];
+                let cdpath = core.data.get_param("CDPATH");
+                let mut prefixes = vec![String::new()];
+                if ! word.starts_with(['/', '.', '~']) {
+                    prefixes.extend(cdpath.split(':').filter(|d| ! d.is_empty())
+                                    .map(|d| d.trim_end_matches('/').to_string() + "/"));
+                }
+                for prefix in prefixes {
+                    let (list, scores) = matcher.paths(&(prefix.clone() + word), true);
+                    let list = list.iter().map(|p| p.strip_prefix(&prefix).unwrap_or(p).to_string());
+                    found.extend(list.zip(scores.into_iter().chain(std::iter::repeat(i32::MAX))));
+                }
+                if found.iter().any(|f| f.1 == i32::MAX) {
+                    found.retain(|f| f.1 == i32::MAX); // prefix matches
+                }
+                found.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+                found.dedup_by(|a, b| a.0 == b.0);
+                let (list, scores): (Vec<String>, Vec<i32>) = found.into_iter().unzip();
+                match scores.first() == Some(&i32::MAX) {
+                    true  => (list, vec![]),
+                    false => (list, scores),
+                }
+            },
+            _ => matcher.paths(word, false),
+        };
 
-        let common = common_string(&core.data.get_array_all("COMPREPLY"));
-        if common.len() != target.len() {
-            self.replace_input(&common);
-            return;
-        }
-        self.cloop();
+        self.completion_scores = scores;
+        list
     }
 
-    fn normalize_tab(&mut self, row_num: i32, col_num: i32) {
-        let i = (self.tab_col*row_num + self.tab_row + row_num*col_num)%(row_num*col_num);
-        self.tab_col = i/row_num;
-        self.tab_row = i%row_num;
-    }
+    /* returns true when the fuzzy matches have no best one */
+    pub fn try_completion(&mut self, core: &mut ShellCore) -> bool {
+        let ctx = self.completion_context.clone();
+        let list = core.data.get_array_all("COMPREPLY");
+        let scores = &self.completion_scores;
 
-    fn show_list(&mut self, list: &Vec<String>, tab_num: usize) {
-        if list.len() == 0 {
-            return;
+        if list.len() == 1 || (scores.len() > 1 && scores[0] > scores[1]) {
+            let output = list[0].clone();
+            let tail = self.completion_tail(core, &output);
+            self.replace_input(&(output + tail));
+            return false;
         }
-        let widths: Vec<usize> = list.iter().map(|s| str_width(s)).collect();
-        let max_entry_width = widths.iter().max().unwrap_or(&1000) + 1;
-        let terminal_row_num = Terminal::size().1;
-        let col_num = std::cmp::min(
-                          std::cmp::max(Terminal::size().0 / max_entry_width, 1),
-                          list.len()
-                      );
-        let row_num = std::cmp::min(
-                          (list.len()-1) / col_num + 1,
-                          std::cmp::max(terminal_row_num - 2, 1)
-                      );
-        self.completion_candidate = String::new();
-
-        if tab_num > 2 {
-            self.normalize_tab(row_num as i32, col_num as i32);
+        if ! scores.is_empty() {
+            return true;
         }
 
-        eprintln!("\r");
-        for row in 0..row_num {
-            for col in 0..col_num {
-                let tab = self.tab_row == row as i32 && self.tab_col == col as i32;
-                self.print_an_entry(list, &widths, row, col, 
-                    row_num, max_entry_width, tab);
-            }
-            print!("\r\n");
+        let matcher = self.completion_matcher;
+        let common = common_string(&list, &matcher);
+        let (len, word_len) = (common.chars().count(), ctx.word.chars().count());
+        if (matcher.is_exact() && len != word_len)
+        || (! matcher.is_exact() && len >= word_len && common != ctx.word) {
+            self.replace_input(&common);
+            return false;
         }
 
-        let (cur_col, cur_row) = self.stdout.cursor_pos().unwrap();
-
-        self.check_scroll();
-        match cur_row as usize == terminal_row_num {
-            true => {
-                let back_row = std::cmp::max(cur_row as i16 - row_num as i16, 1);
-                self.write(&termion::cursor::Goto(cur_col, back_row as u16).to_string());
-                print!("\x1b[1A");
-                self.flush();
-            },
-            false => self.rewrite(false),
+        if core.keymap.query("show-all-if-ambiguous") {
+            self.show_list(core);
+            return false;
         }
+        self.cloop();
+        false
     }
 
-    fn print_an_entry(&mut self, list: &Vec<String>, widths: &Vec<usize>,
-        row: usize, col: usize, row_num: usize, width: usize, pointed: bool) {
-        let i = col*row_num + row;
-        let space_num = match i < list.len() {
-            true  => width - widths[i],
-            false => width,
-        };
-        let cand = match i < list.len() {
-            true  => list[i].clone(),
-            false => "".to_string(),
-        };
-
-        let s = String::from_utf8(vec![b' '; space_num]).unwrap();
-        if pointed {
-            print!("\x1b[01;7m{}{}\x1b[00m", &cand, &s);
-            self.completion_candidate = cand;
+    /* what follows a completed word */
+    pub fn completion_tail(&mut self, core: &mut ShellCore, output: &str) -> &'static str {
+        let ctx = &self.completion_context;
+        let options = &self.completion_options;
+        let has = |o: &str| options.as_ref().map(|v| v.iter().any(|x| x == o));
+        let filenames = has("filenames").unwrap_or(true) || has("dirnames") == Some(true);
+        let cd = options.is_none() && ctx.kind == Kind::Argument && ctx.command == "cd";
+
+        if ctx.kind == Kind::Variable && ctx.word.starts_with("${") {
+            "}"
+        }else if ctx.kind == Kind::Variable && ctx.quote.is_some() {
+            ""
+        }else if ctx.kind == Kind::User || filenames && (cd || is_dir(output, core)) {
+            "/"
+        }else if has("nospace") == Some(true) {
+            ""
         }else{
-            print!("{}{}", &cand, &s);
+            " "
         }
     }
 
-    pub fn replace_input(&mut self, to: &String) {
-        while self.head > self.prompt.chars().count() 
-        && ( self.head > 0 && self.chars[self.head-1] != ' ' ||
-           (self.head > 1 && self.chars[self.head-1] == ' ' 
-            && self.chars[self.head-2] == '\\') ) {
-            self.backspace();
-        }
-        while self.head < self.chars.len() 
-        && self.chars[self.head] != ' ' {
-            self.delete();
+    /* whether the candidates are paths, which are shown by their names with colors */
+    pub fn candidates_are_files(&self) -> bool {
+        let kind = self.completion_context.kind;
+        match &self.completion_options {
+            Some(o) => o.iter().any(|x| x == "filenames" || x == "dirnames"),
+            None => kind == Kind::Argument || kind == Kind::Redirect,
         }
+    }
 
-        let to_escaped = if to.ends_with(" ") {
-            let mut tmp = to.to_string();
-            tmp.pop();
-            match self.escape_at_completion {
-                true  => tmp.replace(" ", "\\ ") + " ",
-                false => tmp.replace("↵ \0", "\n") + " ",
-            }
-        }else {
-            match self.escape_at_completion {
-                true  => to.replace(" ", "\\ ").to_string(),
-                false => to.replace("↵ \0", "\n").to_string(),
-            }
+    /* replaces the word at the cursor with a candidate, quoting it in the same way as the word */
+    pub fn replace_input(&mut self, to: &String) {
+        let start = self.line_start() + self.completion_context.start;
+        let end = (self.head..self.chars.len()).find(|i| " \t\n".contains(self.chars[*i]))
+                  .unwrap_or(self.chars.len());
+        self.chars.drain(start..end);
+        self.head = start;
+
+        let (body, space) = match to.strip_suffix(' ') {
+            Some(b) => (b, true),
+            None    => (to.as_str(), false),
+        };
+        let ctx = &self.completion_context;
+        let raw = ! self.escape_at_completion || ctx.kind == Kind::Variable || ctx.kind == Kind::User;
+        let mut text = match (raw, ctx.quote) {
+            (true, _) => body.replace("↵ \0", "\n"),
+            (false, Some(q)) => q.to_string() + &context::quote(body, Some(q)),
+            (false, None) => context::quote(body, None),
         };
+        if space {
+            if let (false, Some(q)) = (raw, ctx.quote) {
+                text.push(q);
+            }
+            text.push(' ');
+        }
 
-        for c in to_escaped.chars() {
+        for c in text.chars() {
             self.insert(c);
             self.check_scroll();
         }
 
-        if to.ends_with(" ") 
-        && self.head < self.chars.len() 
+        if space
+        && self.head < self.chars.len()
         && self.chars[self.head] == ' ' {
             self.backspace();
         }
@@ -281,8 +438,8 @@ impl Terminal {
         (tilde_prefix, tilde_path, last_tilde_expanded)
     }
 
-    fn set_completion_info(&mut self, core: &mut ShellCore){
-        let prompt = self.prompt.chars().count();
+    fn set_completion_info(&mut self, core: &mut ShellCore, tab_num: usize){
+        let prompt = self.line_start();
         let all_string = self.get_string(prompt);
         let mut words_all = utils::split_words(&all_string);
         words_all.retain(|e| e != "");
@@ -292,6 +449,15 @@ impl Terminal {
         words_left.retain(|e| e != "");
         let from = completion_from(&words_left, core);
 
+        /* COMP_LINE and COMP_POINT begin at the command */
+        let start = word_starts(&left_string).get(from).copied()
+                    .unwrap_or(left_string.chars().count());
+        let line: String = all_string.chars().skip(start).collect();
+        core.data.set_param("COMP_LINE", &line);
+        core.data.set_param("COMP_POINT", &(self.head - prompt - start).to_string());
+        core.data.set_param("COMP_TYPE", if tab_num > 1 {"63"} else {"9"});
+        core.data.set_param("COMP_KEY", "9");
+
         words_all = words_all[from..].to_vec();
         words_left = words_left[from..].to_vec();
         core.data.set_array("COMP_WORDS", &words_all);
@@ -316,8 +482,12 @@ fn completion_from(ws: &Vec<String>, core: &mut ShellCore) -> usize {
             continue;
         }
 
+        if i > 0 && ws[i-1].ends_with([';', '|', '&', '(']) {
+            return i;
+        }
+
         let s = ws[i..].join(" ");
-        let mut feeder = Feeder::new(&s);
+        let mut feeder = Feeder::new_probing(&s);
         if let None = SimpleCommand::parse(&mut feeder, core) {
             return i+1;
         }
@@ -0,0 +1,251 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::utils;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Kind {
+    #[default]
+    Command,  // the name of a command
+    Argument,
+    Redirect, // the target of < or >
+    Variable, // $name or ${name
+    User,     // ~name
+}
+
+/* what the word at the cursor is */
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    pub kind: Kind,
+    pub start: usize,         // the position where the completed text begins
+    pub word_start: usize,
+    pub quote: Option<char>,  // the quote left open at the cursor
+    pub word: String,         // the text from start to the cursor without quotes
+    pub command: String,      // the command which takes the word as an argument
+}
+
+/* the state of a command line or of a command substitution in it */
+#[derive(Default)]
+struct Frame {
+    closer: Option<char>,
+    argument: bool,   // the command name has appeared
+    redirect: bool,
+    command: String,
+    word_start: Option<usize>,
+    word: String,
+    quote: Option<char>,
+    dollar: Option<usize>,
+}
+
+fn is_assignment(word: &str) -> bool {
+    match word.split_once('=') {
+        Some((name, _)) => {
+            let name = name.split('[').next().unwrap_or("").trim_end_matches('+');
+            ! name.is_empty() && ! name.starts_with(|c: char| c.is_ascii_digit())
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        },
+        None => false,
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+impl Frame {
+    fn nested(closer: char) -> Frame {
+        Frame { closer: Some(closer), ..Default::default() }
+    }
+
+    fn begin_word(&mut self, pos: usize) {
+        if self.word_start.is_none() {
+            self.word_start = Some(pos);
+        }
+    }
+
+    fn end_word(&mut self) {
+        if self.word_start.is_none() {
+            return;
+        }
+
+        let word = std::mem::take(&mut self.word);
+        if self.redirect {
+            self.redirect = false;
+        }else if ! self.argument && ! utils::reserved(&word)
+        && ! ["!", "time", "elif"].contains(&word.as_str()) && ! is_assignment(&word) {
+            self.argument = true;
+            self.command = word;
+        }
+        self.word_start = None;
+        self.dollar = None;
+    }
+
+    fn separate(&mut self) {
+        self.end_word();
+        self.argument = false;
+        self.redirect = false;
+        self.command.clear();
+    }
+}
+
+/* reads the line before the cursor with the quoting rules and the nesting of
+ * command substitutions, and finds the word at the cursor */
+pub fn scan(chars: &[char]) -> Context {
+    let mut frames = vec![Frame::default()];
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i+1).copied();
+        let f = frames.last_mut().unwrap();
+        i += 1;
+
+        if f.quote == Some('\'') {
+            match c {
+                '\'' => f.quote = None,
+                _    => f.word.push(c),
+            }
+            continue;
+        }
+
+        if f.quote == Some('"') {
+            match c {
+                '"' => f.quote = None,
+                '\\' => {
+                    match next {
+                        Some(n) if "$`\"\\\n".contains(n) => f.word.push(n),
+                        Some(n) => { f.word.push(c); f.word.push(n); },
+                        None => f.word.push(c),
+                    }
+                    i += 1;
+                },
+                '$' if next == Some('(') => {
+                    frames.push(Frame::nested(')'));
+                    i += 1;
+                },
+                '`' => frames.push(Frame::nested('`')),
+                '$' => {
+                    f.dollar = Some(i-1);
+                    f.word.push(c);
+                },
+                _ => f.word.push(c),
+            }
+            continue;
+        }
+
+        if Some(c) == f.closer {
+            f.end_word();
+            frames.pop();
+            continue;
+        }
+
+        match c {
+            ' ' | '\t' => f.end_word(),
+            '&' if next == Some('>') => {
+                f.end_word();
+                f.redirect = true;
+                i += 1;
+            },
+            '\n' | ';' | '&' | '|' | '(' | ')' => f.separate(),
+            '\\' => {
+                f.begin_word(i-1);
+                if let Some(n) = next {
+                    f.word.push(n);
+                    i += 1;
+                }
+            },
+            '\'' | '"' => {
+                f.begin_word(i-1);
+                f.quote = Some(c);
+            },
+            '$' | '<' | '>' if next == Some('(') => {
+                f.begin_word(i-1);
+                frames.push(Frame::nested(')'));
+                i += 1;
+            },
+            '`' => {
+                f.begin_word(i-1);
+                frames.push(Frame::nested('`'));
+            },
+            '<' | '>' => {
+                if f.word_start.is_some() && f.word.chars().all(|c| c.is_ascii_digit()) {
+                    f.word_start = None; // a file descriptor
+                    f.word.clear();
+                }
+                f.end_word();
+                while i < chars.len() && "<>&|-".contains(chars[i]) {
+                    i += 1;
+                }
+                f.redirect = true;
+            },
+            '$' => {
+                f.begin_word(i-1);
+                f.dollar = Some(i-1);
+                f.word.push(c);
+            },
+            _ => {
+                f.begin_word(i-1);
+                f.word.push(c);
+            },
+        }
+    }
+
+    let f = frames.last().unwrap();
+    let word_start = f.word_start.unwrap_or(chars.len());
+    let mut ans = Context {
+        kind: Kind::Command,
+        start: word_start,
+        word_start,
+        quote: f.quote,
+        word: f.word.clone(),
+        command: f.command.clone(),
+    };
+
+    if let Some(d) = f.dollar {
+        let rest: String = chars[d+1..].iter().collect();
+        let name = rest.strip_prefix('{').unwrap_or(&rest);
+        if name.chars().all(is_name_char) {
+            ans.kind = Kind::Variable;
+            ans.start = d;
+            ans.word = "$".to_owned() + &rest;
+            return ans;
+        }
+    }
+
+    let raw: String = chars[word_start..].iter().collect();
+    if raw.starts_with('~') && ! raw.contains('/') && raw == f.word {
+        ans.kind = Kind::User;
+    }else if f.redirect {
+        ans.kind = Kind::Redirect;
+    }else if f.argument {
+        ans.kind = Kind::Argument;
+    }
+    ans
+}
+
+/* quotes a candidate for the place where it is inserted */
+pub fn quote(s: &str, quote: Option<char>) -> String {
+    match quote {
+        Some('\'') => s.replace("'", "'\\''"),
+        Some(_) => {
+            let mut ans = String::new();
+            for c in s.chars() {
+                if "\"\\$`".contains(c) {
+                    ans.push('\\');
+                }
+                ans.push(c);
+            }
+            ans
+        },
+        None => {
+            let mut ans = String::new();
+            for (i, c) in s.chars().enumerate() {
+                if " \t\n\\\"'<>;|&()$`?*[!{}".contains(c) || (i == 0 && c == '#') {
+                    ans.push('\\');
+                }
+                ans.push(c);
+            }
+            ans
+        },
+    }
+}
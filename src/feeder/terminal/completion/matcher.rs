@@ -0,0 +1,141 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{file_check, ShellCore};
+use crate::utils::directory;
+
+/* how candidates are compared with the typed word */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Matcher {
+    ignore_case: bool, // completion-ignore-case, or nocaseglob for files
+    map_case: bool,    // completion-map-case: hyphens and underscores are the same
+    fuzzy: bool,       // completion-fuzzy: subsequences when no candidate has the prefix
+}
+
+impl Matcher {
+    pub fn new(core: &mut ShellCore, files: bool) -> Matcher {
+        let ignore_case = core.keymap.query("completion-ignore-case")
+                          || (files && core.shopts.query("nocaseglob"));
+        Matcher {
+            ignore_case,
+            map_case: ignore_case && core.keymap.query("completion-map-case"),
+            fuzzy: core.keymap.query("completion-fuzzy"),
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        ! self.ignore_case && ! self.fuzzy
+    }
+
+    fn fold(&self, c: char, ignore_case: bool) -> char {
+        let c = match ignore_case {
+            true  => c.to_lowercase().next().unwrap_or(c),
+            false => c,
+        };
+        match self.map_case && c == '_' {
+            true  => '-',
+            false => c,
+        }
+    }
+
+    pub fn same_char(&self, a: char, b: char) -> bool {
+        self.fold(a, self.ignore_case) == self.fold(b, self.ignore_case)
+    }
+
+    pub fn has_prefix(&self, s: &str, prefix: &str) -> bool {
+        let mut chars = s.chars();
+        prefix.chars().all(|p| chars.next().map(|c| self.same_char(c, p)) == Some(true))
+    }
+
+    pub fn matches(&self, s: &str, typed: &str) -> bool {
+        self.has_prefix(s, typed) || (self.fuzzy && self.fuzzy_score(s, typed).is_some())
+    }
+
+    /* a higher score is a better match. the case is ignored unless the typed word has capitals */
+    fn fuzzy_score(&self, s: &str, typed: &str) -> Option<i32> {
+        let ignore_case = self.ignore_case || ! typed.chars().any(|c| c.is_uppercase());
+        let s: Vec<char> = s.chars().map(|c| self.fold(c, ignore_case)).collect();
+        let mut score = 0;
+        let mut pos = 0;
+
+        for t in typed.chars().map(|c| self.fold(c, ignore_case)) {
+            let found = (pos..s.len()).find(|i| s[*i] == t)?;
+            if found > 0 && found == pos && pos > 0 {
+                score += 4; // consecutive letters
+            }
+            if found == 0 || "/-_. ".contains(s[found-1]) {
+                score += 3; // the head of a word
+            }
+            score -= (found - pos) as i32;
+            pos = found + 1;
+        }
+        Some(score - (s.len() - pos) as i32 / 4)
+    }
+
+    /* (key, candidate) pairs whose keys begin with the typed word, or the fuzzy matches
+     * from the best with their scores */
+    pub fn select(&self, pairs: Vec<(String, String)>, typed: &str) -> (Vec<String>, Vec<i32>) {
+        let prefixed: Vec<String> = pairs.iter().filter(|p| self.has_prefix(&p.0, typed))
+                                    .map(|p| p.1.clone()).collect();
+        if ! prefixed.is_empty() || ! self.fuzzy || typed.is_empty() {
+            return (prefixed, vec![]);
+        }
+
+        let mut scored: Vec<(i32, String)> = pairs.into_iter()
+            .filter_map(|(k, c)| self.fuzzy_score(&k, typed).map(|s| (s, c))).collect();
+        scored.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        scored.into_iter().map(|(s, c)| (c, s)).unzip()
+    }
+
+    /* the directories which the part of a path before the last slash may mean */
+    fn directories(&self, dir_part: &str) -> Vec<String> {
+        let mut ans = vec![match dir_part.starts_with('/') {
+            true  => "/".to_string(),
+            false => String::new(),
+        }];
+
+        for comp in dir_part.split('/').filter(|c| ! c.is_empty()) {
+            let mut next = vec![];
+            for base in &ans {
+                let exact = base.clone() + comp + "/";
+                if comp == "." || comp == ".." || file_check::is_dir(&exact) {
+                    next.push(exact);
+                    continue;
+                }
+
+                let subdirs = directory::files(base).into_iter()
+                              .filter(|f| file_check::is_dir(&(base.clone() + f)))
+                              .map(|f| (f.clone(), base.clone() + &f + "/")).collect();
+                next.extend(self.select(subdirs, comp).0);
+            }
+            ans = next;
+            ans.truncate(64);
+        }
+        ans
+    }
+
+    /* paths matching the word component by component */
+    pub fn paths(&self, word: &str, dirs_only: bool) -> (Vec<String>, Vec<i32>) {
+        let (dir_part, name) = match word.rfind('/') {
+            Some(p) => (&word[..p+1], &word[p+1..]),
+            None    => ("", word),
+        };
+
+        let mut pairs = vec![];
+        for dir in self.directories(dir_part) {
+            for f in directory::files(&dir) {
+                let path = dir.clone() + &f;
+                if (f.starts_with('.') && ! name.starts_with('.'))
+                || (dirs_only && ! file_check::is_dir(&path)) {
+                    continue;
+                }
+                pairs.push((f, path));
+            }
+        }
+        let (mut list, scores) = self.select(pairs, name);
+        if scores.is_empty() {
+            list.sort();
+        }
+        (list, scores)
+    }
+}
@@ -0,0 +1,290 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{ShellCore, utils};
+use crate::feeder::terminal::Terminal;
+use std::process::Command;
+use termion::cursor::DetectCursorPos;
+
+const KILL_RING_MAX: usize = 10;
+
+pub fn push_kill_ring(core: &mut ShellCore, text: String) {
+    core.kill_ring.push(text);
+    if core.kill_ring.len() > KILL_RING_MAX {
+        core.kill_ring.remove(0);
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric()
+}
+
+impl Terminal {
+    pub fn line_start(&self) -> usize {
+        self.prompt.chars().count()
+    }
+
+    pub fn insert_str(&mut self, s: &str) {
+        for c in s.chars() {
+            self.chars.insert(self.head, c);
+            self.head += 1;
+        }
+    }
+
+    /* the end of the next word */
+    fn word_end(&self, from: usize) -> usize {
+        let mut pos = from;
+        while pos < self.chars.len() && ! is_word_char(self.chars[pos]) {
+            pos += 1;
+        }
+        while pos < self.chars.len() && is_word_char(self.chars[pos]) {
+            pos += 1;
+        }
+        pos
+    }
+
+    /* the start of the previous word */
+    fn word_start(&self, from: usize) -> usize {
+        let mut pos = from;
+        while pos > self.line_start() && ! is_word_char(self.chars[pos-1]) {
+            pos -= 1;
+        }
+        while pos > self.line_start() && is_word_char(self.chars[pos-1]) {
+            pos -= 1;
+        }
+        pos
+    }
+
+    pub fn forward_word(&mut self) {
+        self.head = self.word_end(self.head);
+        self.goto(self.head);
+        self.flush();
+    }
+
+    pub fn backward_word(&mut self) {
+        self.head = self.word_start(self.head);
+        self.goto(self.head);
+        self.flush();
+    }
+
+    /* successive kills are gathered into one entry of the kill ring */
+    fn kill(&mut self, core: &mut ShellCore, from: usize, to: usize) {
+        if from >= to {
+            return;
+        }
+
+        let killed: String = self.chars.drain(from..to).collect();
+        let backward = to <= self.head;
+        self.head = from;
+
+        match (self.killing, core.kill_ring.last_mut()) {
+            (true, Some(last)) if backward => *last = killed + last,
+            (true, Some(last)) => *last += &killed,
+            _ => push_kill_ring(core, killed),
+        }
+        self.killing = true;
+        self.rewrite(true);
+    }
+
+    pub fn kill_line(&mut self, core: &mut ShellCore) {
+        self.kill(core, self.head, self.chars.len());
+    }
+
+    pub fn unix_line_discard(&mut self, core: &mut ShellCore) {
+        self.kill(core, self.line_start(), self.head);
+    }
+
+    /* Ctrl-W: words are delimited by whitespace */
+    pub fn unix_word_rubout(&mut self, core: &mut ShellCore) {
+        let mut pos = self.head;
+        while pos > self.line_start() && self.chars[pos-1].is_whitespace() {
+            pos -= 1;
+        }
+        while pos > self.line_start() && ! self.chars[pos-1].is_whitespace() {
+            pos -= 1;
+        }
+        self.kill(core, pos, self.head);
+    }
+
+    pub fn kill_word(&mut self, core: &mut ShellCore) {
+        self.kill(core, self.head, self.word_end(self.head));
+    }
+
+    pub fn yank(&mut self, core: &mut ShellCore) {
+        let text = match core.kill_ring.last() {
+            Some(t) => t.clone(),
+            None    => return self.cloop(),
+        };
+        let start = self.head;
+        self.insert_str(&text);
+        self.yanked = Some((start, core.kill_ring.len() - 1));
+        self.rewrite(true);
+    }
+
+    /* Alt-Y: replaces the text just yanked with the previous entry of the kill ring */
+    pub fn yank_pop(&mut self, core: &mut ShellCore) {
+        let (start, index) = match self.yanked {
+            Some(y) if ! core.kill_ring.is_empty() => y,
+            _ => return self.cloop(),
+        };
+
+        self.chars.drain(start..self.head);
+        self.head = start;
+        let index = (index + core.kill_ring.len() - 1) % core.kill_ring.len();
+        let text = core.kill_ring[index].clone();
+        self.insert_str(&text);
+        self.yanked = Some((start, index));
+        self.rewrite(true);
+    }
+
+    pub fn transpose_chars(&mut self) {
+        if self.chars.len() < self.line_start() + 2 || self.head == self.line_start() {
+            return self.cloop();
+        }
+        if self.head == self.chars.len() {
+            self.head -= 1;
+        }
+        self.chars.swap(self.head - 1, self.head);
+        self.head += 1;
+        self.rewrite(true);
+    }
+
+    pub fn transpose_words(&mut self) {
+        let end2 = self.word_end(self.head);
+        let start2 = self.word_start(end2);
+        let start1 = self.word_start(start2);
+        let end1 = self.word_end(start1);
+        if start2 == end2 || start1 == start2 || end1 > start2 {
+            return self.cloop();
+        }
+
+        let word1: Vec<char> = self.chars[start1..end1].to_vec();
+        let word2: Vec<char> = self.chars[start2..end2].to_vec();
+        let between: Vec<char> = self.chars[end1..start2].to_vec();
+        let swapped = [word2, between, word1].concat();
+        self.chars.splice(start1..end2, swapped);
+        self.head = end2;
+        self.rewrite(true);
+    }
+
+    /* Alt-U, Alt-L and Alt-C from the cursor to the end of the word */
+    pub fn change_case(&mut self, key: char) {
+        let end = self.word_end(self.head);
+        let mut first = true;
+        for pos in self.head..end {
+            let c = self.chars[pos];
+            let upper = match key {
+                'u' => true,
+                'c' => first && is_word_char(c),
+                _   => false,
+            };
+            if is_word_char(c) {
+                first = false;
+            }
+            let changed: Vec<char> = match upper {
+                true  => c.to_uppercase().collect(),
+                false => c.to_lowercase().collect(),
+            };
+            if changed.len() == 1 {
+                self.chars[pos] = changed[0];
+            }
+        }
+        self.head = end;
+        self.rewrite(true);
+    }
+
+    pub fn clear_screen(&mut self) {
+        self.write(termion::clear::All.as_ref());
+        self.prompt_row = 1;
+        self.check_scroll();
+        self.rewrite(true);
+    }
+
+    /* Alt-.: the last word of the previous line, and older lines on repetition */
+    pub fn yank_last_arg(&mut self, core: &mut ShellCore) {
+        let entry = self.last_arg.map(|a| a.1 + 1).unwrap_or(1);
+        let arg = match core.history.get(entry) {
+            Some(h) => utils::split_words(&h.line.replace("↵ \0", "\n")).pop().unwrap_or_default(),
+            None    => return self.cloop(),
+        };
+
+        let start = match self.last_arg {
+            Some((start, _)) => {
+                self.chars.drain(start..self.head);
+                start
+            },
+            None => self.head,
+        };
+        self.head = start;
+        self.insert_str(&arg);
+        self.last_arg = Some((start, entry));
+        self.rewrite(true);
+    }
+
+    pub fn undo(&mut self) {
+        match self.undo_stack.pop() {
+            Some((chars, head)) => {
+                self.chars = chars;
+                self.head = head;
+                self.rewrite(true);
+            },
+            None => self.cloop(),
+        }
+    }
+
+    pub fn backward_kill_word(&mut self, core: &mut ShellCore) {
+        self.kill(core, self.word_start(self.head), self.head);
+    }
+
+    /* the line is edited with VISUAL or EDITOR and then accepted. false: the file is not available */
+    pub fn edit_and_execute_command(&mut self, core: &mut ShellCore) -> bool {
+        let path = format!("/tmp/sush-edit-{}", std::process::id());
+        let line = self.get_string(self.line_start());
+        if std::fs::write(&path, line + "\n").is_err() {
+            self.cloop();
+            return false;
+        }
+
+        let editor = match (core.data.get_param("VISUAL"), core.data.get_param("EDITOR")) {
+            (e, _) if ! e.is_empty() => e,
+            (_, e) if ! e.is_empty() => e,
+            _ => "vi".to_string(),
+        };
+        let mut words = editor.split_whitespace();
+        let program = words.next().unwrap_or("vi");
+
+        self.write("\r\n");
+        self.flush();
+        let _ = self.stdout.suspend_raw_mode();
+        let status = Command::new(program).args(words).arg(&path).status();
+        let _ = self.stdout.activate_raw_mode();
+        self.prompt_row = self.stdout.cursor_pos().unwrap_or((1, 1)).1 as usize;
+
+        let text = match status {
+            Ok(s) if s.success() => std::fs::read_to_string(&path).unwrap_or_default(),
+            _ => String::new(),
+        };
+        let _ = std::fs::remove_file(&path);
+
+        self.chars = self.prompt.chars().collect();
+        self.chars.extend(text.trim_end_matches('\n').chars());
+        self.head = self.chars.len();
+        true
+    }
+
+    /* clears the states of repeated commands that are not continued with the function */
+    pub fn reset_repetition(&mut self, func: &str) {
+        match func {
+            "kill-line" | "unix-line-discard" | "unix-word-rubout"
+            | "kill-word" | "backward-kill-word" => {},
+            _ => self.killing = false,
+        }
+        match func {
+            "yank" | "yank-pop" => {},
+            _ => self.yanked = None,
+        }
+        if func != "yank-last-arg" {
+            self.last_arg = None;
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{file_check, Feeder, ShellCore};
+use crate::feeder::terminal::Terminal;
+use unicode_width::UnicodeWidthChar;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Color {
+    Plain,
+    Keyword,
+    Builtin,
+    Function,
+    Alias,
+    Executable,
+    NotFound,
+    Quoted,
+    Variable,
+    Redirect,
+    Comment,
+}
+
+const SUGGESTION: &str = "\x1b[90m";
+
+fn escape_sequence(color: Color) -> &'static str {
+    match color {
+        Color::Plain      => "\x1b[m",
+        Color::Keyword    => "\x1b[1;34m",
+        Color::Builtin    => "\x1b[1;32m",
+        Color::Function   => "\x1b[1;36m",
+        Color::Alias      => "\x1b[1;35m",
+        Color::Executable => "\x1b[32m",
+        Color::NotFound   => "\x1b[31m",
+        Color::Quoted     => "\x1b[33m",
+        Color::Variable   => "\x1b[36m",
+        Color::Redirect   => "\x1b[35m",
+        Color::Comment    => "\x1b[90m",
+    }
+}
+
+fn in_path(core: &mut ShellCore, name: &str) -> bool {
+    if name.contains('/') {
+        return file_check::is_executable(name);
+    }
+    core.data.get_param("PATH").split(':')
+        .any(|dir| ! dir.is_empty() && file_check::is_executable(&format!("{}/{}", dir, name)))
+}
+
+pub fn command_color(core: &mut ShellCore, name: &str) -> Color {
+    if core.data.aliases.contains_key(name) {
+        Color::Alias
+    }else if core.data.functions.contains_key(name) {
+        Color::Function
+    }else if core.builtins.contains_key(name) {
+        Color::Builtin
+    }else if in_path(core, name) {
+        Color::Executable
+    }else{
+        Color::NotFound
+    }
+}
+
+/* the parsers mark the parts of the line without running it. A part inside
+ * another is shorter and painted later */
+fn colorize(core: &mut ShellCore, chars: &[char]) -> Vec<Color> {
+    let text: String = chars.iter().collect();
+    let mut spans = Feeder::highlight(core, &text);
+    spans.sort_by_key(|(from, to, _)| std::cmp::Reverse(to.saturating_sub(*from)));
+
+    let bytes: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
+    let index = |pos: usize| bytes.partition_point(|b| *b < pos);
+
+    let mut colors = vec![Color::Plain; chars.len()];
+    for (from, to, color) in spans.into_iter().filter(|s| s.0 < s.1) {
+        for c in colors[index(from)..index(to)].iter_mut() {
+            *c = color;
+        }
+    }
+    colors
+}
+
+/* the rest of the newest entry in the history that begins with the line */
+fn find_suggestion(core: &ShellCore, line: &str) -> String {
+    if line.is_empty() || line.contains('\n') {
+        return String::new();
+    }
+
+    core.history.iter().skip(1)
+        .map(|h| h.line.replace("↵ \0", "\n"))
+        .find(|h| h.starts_with(line) && h.len() > line.len() && ! h.contains('\n'))
+        .map(|h| h[line.len()..].to_string())
+        .unwrap_or_default()
+}
+
+impl Terminal {
+    /* the text to be written from the origin with the escape sequences of the colors */
+    pub fn decorated_string(&self) -> String {
+        let start = self.line_start();
+        if self.colors.len() != self.chars.len() - start {
+            return self.get_string(0);
+        }
+
+        let mut ans: String = self.chars[..start].iter().collect();
+        let mut prev = Color::Plain;
+        for (c, color) in self.chars[start..].iter().zip(self.colors.iter()) {
+            if *color != prev {
+                ans += escape_sequence(*color);
+                prev = *color;
+            }
+            ans.push(*c);
+        }
+        if prev != Color::Plain {
+            ans += escape_sequence(Color::Plain);
+        }
+
+        if ! self.suggestion.is_empty() && self.head == self.chars.len() {
+            let x = self.head_to_cursor_pos(self.chars.len(), 0).0;
+            let mut room = Terminal::size().0.saturating_sub(x);
+            let mut shown = String::new();
+            for c in self.suggestion.chars() {
+                let w = UnicodeWidthChar::width(c).unwrap_or(0);
+                if w > room {
+                    break;
+                }
+                room -= w;
+                shown.push(c);
+            }
+            ans += &(SUGGESTION.to_owned() + &shown + escape_sequence(Color::Plain));
+        }
+        ans
+    }
+
+    pub fn highlight(&mut self, core: &mut ShellCore) {
+        if ! core.shopts.query("highlight") {
+            return;
+        }
+
+        let line = self.get_string(self.line_start());
+        self.colors = colorize(core, &self.chars[self.line_start()..]);
+        self.suggestion = match self.head == self.chars.len() {
+            true  => find_suggestion(core, &line),
+            false => String::new(),
+        };
+        self.rewrite(true);
+    }
+
+    /* Right and Ctrl-F at the end of the line take the suggestion */
+    pub fn accept_suggestion(&mut self) -> bool {
+        if self.suggestion.is_empty() || self.head != self.chars.len() {
+            return false;
+        }
+
+        let suggestion = std::mem::take(&mut self.suggestion);
+        self.insert_str(&suggestion);
+        self.rewrite(true);
+        true
+    }
+
+    pub fn clear_suggestion(&mut self) {
+        if ! self.suggestion.is_empty() {
+            self.suggestion.clear();
+            self.rewrite(true);
+        }
+    }
+}
@@ -0,0 +1,324 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{file_check, ShellCore};
+use crate::feeder::terminal::Terminal;
+use crate::feeder::terminal::completion::matcher::Matcher;
+use std::fs;
+use std::io;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use termion::event;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const DEFAULT_LS_COLORS: &str = "di=01;34:ln=01;36:ex=01;32";
+
+struct Item {
+    word: String,  // the candidate put on the line
+    label: String, // the text in the menu
+    description: String,
+    color: String, // SGR parameters from LS_COLORS
+}
+
+/* candidates in columns with a selection */
+struct Menu {
+    items: Vec<Item>,
+    shown: Vec<usize>, // the items which begin with the typed text
+    selected: Option<usize>,
+    top: usize,        // the first row on the screen
+    page: usize,       // the number of rows on the screen
+    descriptions: bool,
+}
+
+fn str_width(s: &str) -> usize {
+    UnicodeWidthStr::width(s)
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    let mut w = 0;
+    s.chars().take_while(|c| {
+        w += UnicodeWidthChar::width(*c).unwrap_or(0);
+        w <= max
+    }).collect()
+}
+
+/* the color of a file in the manner of ls */
+fn ls_color(colors: &str, path: &str) -> String {
+    let meta = match fs::symlink_metadata(path) {
+        Ok(m) => m,
+        _ => return String::new(),
+    };
+    let tp = meta.file_type();
+    let key = if tp.is_symlink() {
+        "ln"
+    }else if tp.is_dir() {
+        "di"
+    }else if tp.is_fifo() {
+        "pi"
+    }else if tp.is_socket() {
+        "so"
+    }else if tp.is_block_device() {
+        "bd"
+    }else if tp.is_char_device() {
+        "cd"
+    }else if meta.permissions().mode() & 0o111 != 0 {
+        "ex"
+    }else{
+        "fi"
+    };
+
+    let mut ans = String::new();
+    for (k, v) in colors.split(':').filter_map(|e| e.split_once('=')) {
+        let ext = key == "fi" && k.starts_with('*') && path.ends_with(&k[1..]);
+        if k == key || ext {
+            ans = v.to_string();
+        }
+    }
+    ans
+}
+
+impl Menu {
+    fn new(items: Vec<Item>, selected: Option<usize>) -> Menu {
+        Menu {
+            shown: (0..items.len()).collect(),
+            descriptions: items.iter().any(|i| ! i.description.is_empty()),
+            items,
+            selected,
+            top: 0,
+            page: 0,
+        }
+    }
+
+    /* (columns, rows, the width of a column) */
+    fn layout(&self, cols: usize) -> (usize, usize, usize) {
+        let len = std::cmp::max(self.shown.len(), 1);
+        let width = self.shown.iter().map(|i| str_width(&self.items[*i].label)).max().unwrap_or(0) + 2;
+        if self.descriptions {
+            return (1, len, width);
+        }
+
+        let col_num = std::cmp::min(std::cmp::max(cols / width, 1), len);
+        let row_num = (len - 1) / col_num + 1;
+        ((len - 1) / row_num + 1, row_num, width)
+    }
+
+    fn current(&self) -> String {
+        match self.selected {
+            Some(s) => self.items[self.shown[s]].word.clone(),
+            None    => String::new(),
+        }
+    }
+
+    fn shift(&mut self, n: isize) {
+        let len = self.shown.len() as isize;
+        let pos = self.selected.unwrap_or(0) as isize;
+        self.selected = Some((pos + n).rem_euclid(len) as usize);
+    }
+
+    fn shift_column(&mut self, n: isize, cols: usize) {
+        let (_, row_num, _) = self.layout(cols);
+        let pos = self.selected.unwrap_or(0);
+        let row = pos % row_num;
+        let col_num = (self.shown.len() - 1 - row) / row_num + 1; // columns with the row
+        let col = (pos / row_num) as isize + n;
+        self.selected = Some(col.rem_euclid(col_num as isize) as usize * row_num + row);
+    }
+
+    fn shift_page(&mut self, n: isize) {
+        let pos = self.selected.unwrap_or(0) as isize + n * self.page as isize;
+        self.selected = Some(pos.clamp(0, self.shown.len() as isize - 1) as usize);
+    }
+
+    /* leaves the candidates matching the text */
+    fn narrow(&mut self, text: &str, matcher: &Matcher) -> bool {
+        let shown: Vec<usize> = (0..self.items.len())
+                                .filter(|i| matcher.matches(&self.items[*i].word, text)).collect();
+        if shown.is_empty() {
+            return false;
+        }
+        self.shown = shown;
+        self.selected = Some(0);
+        self.top = 0;
+        true
+    }
+
+    fn entry(&self, i: usize, width: usize, cols: usize) -> String {
+        let item = &self.items[self.shown[i]];
+        let width = std::cmp::min(width, cols.saturating_sub(1));
+        let label = truncate(&item.label, width);
+        let pad = " ".repeat(width - str_width(&label));
+        let desc = match self.descriptions && ! item.description.is_empty() {
+            true  => truncate(&format!("-- {}", item.description), cols.saturating_sub(width + 1)),
+            false => String::new(),
+        };
+
+        if self.selected == Some(i) {
+            format!("\x1b[01;7m{}{}{}\x1b[00m", label, pad, desc)
+        }else if ! item.color.is_empty() {
+            format!("\x1b[{}m{}\x1b[00m{}{}", item.color, label, pad, desc)
+        }else{
+            format!("{}{}{}", label, pad, desc)
+        }
+    }
+
+    /* the lines on the screen, with the status line when the candidates are paged */
+    fn lines(&mut self, cols: usize, max_rows: usize) -> Vec<String> {
+        let (col_num, row_num, width) = self.layout(cols);
+        self.page = match row_num > max_rows {
+            true  => std::cmp::max(max_rows, 2) - 1,
+            false => row_num,
+        };
+        if let Some(s) = self.selected {
+            let row = s % row_num;
+            if row < self.top {
+                self.top = row;
+            }else if row >= self.top + self.page {
+                self.top = row + 1 - self.page;
+            }
+        }
+        self.top = std::cmp::min(self.top, row_num - self.page);
+
+        let mut ans = vec![];
+        for row in self.top..self.top + self.page {
+            let line: String = (0..col_num).map(|col| col*row_num + row)
+                               .filter(|i| *i < self.shown.len())
+                               .map(|i| self.entry(i, width, cols)).collect();
+            ans.push(line);
+        }
+        if self.page < row_num {
+            ans.push(format!("\x1b[7mrows {}-{} of {}\x1b[00m",
+                             self.top + 1, self.top + self.page, row_num));
+        }
+        ans
+    }
+}
+
+impl Terminal {
+    fn menu_items(&self, core: &mut ShellCore) -> Vec<Item> {
+        let files = self.candidates_are_files();
+        let colors = match core.data.get_param("LS_COLORS") {
+            c if ! c.is_empty() => c,
+            _ => DEFAULT_LS_COLORS.to_string(),
+        };
+        let home = core.data.get_param("HOME");
+
+        let mut ans = vec![];
+        for (i, word) in core.data.get_array_all("COMPREPLY").into_iter().enumerate() {
+            let path = match word.strip_prefix("~/") {
+                Some(rest) => format!("{}/{}", home, rest),
+                None       => word.clone(),
+            };
+            let (label, color) = match files {
+                true  => {
+                    let name = match word.trim_end_matches('/').rsplit('/').next() {
+                        Some(n) if ! n.is_empty() => n.to_string(),
+                        _ => word.clone(),
+                    };
+                    let slash = if file_check::is_dir(&path) && ! name.ends_with('/') {"/"} else {""};
+                    (name + slash, ls_color(&colors, &path))
+                },
+                false => (word.replace("↵ \0", "↵"), String::new()),
+            };
+            let description = self.completion_descriptions.get(i).cloned().unwrap_or_default();
+            ans.push(Item { word, label, description, color });
+        }
+        ans
+    }
+
+    /* draws the menu under the line, scrolling the screen when it has no room */
+    fn draw_menu(&mut self, menu: &mut Menu) {
+        let (cols, rows) = Terminal::size();
+        let input_rows = self.head_to_cursor_pos(self.chars.len(), 0).1 + 1;
+        let lines = menu.lines(cols, std::cmp::max(rows.saturating_sub(input_rows), 1));
+
+        self.rewrite(true);
+        self.goto(self.chars.len());
+        self.write(&"\r\n".repeat(lines.len()));
+        let last = self.head_to_cursor_pos(self.chars.len(), self.prompt_row).1;
+        let scrolled = (last + lines.len()).saturating_sub(rows);
+        self.prompt_row = std::cmp::max(self.prompt_row.saturating_sub(scrolled), 1);
+
+        for (i, line) in lines.iter().enumerate() {
+            let y = (last + 1 + i).saturating_sub(scrolled);
+            self.write(&format!("{}{}{}", termion::cursor::Goto(1, y as u16),
+                                termion::clear::CurrentLine, line));
+        }
+        self.goto(self.head);
+        self.flush();
+    }
+
+    /* for show-all-if-ambiguous */
+    pub fn show_list(&mut self, core: &mut ShellCore) {
+        let mut menu = Menu::new(self.menu_items(core), None);
+        self.draw_menu(&mut menu);
+    }
+
+    /* the second Tab. returns the key that ends the menu to be processed as usual */
+    pub fn menu_completion<I>(&mut self, core: &mut ShellCore, keys: &mut I) -> Option<event::Key>
+    where I: Iterator<Item = io::Result<event::Key>> {
+        if ! self.set_candidates(core, 2) {
+            self.cloop();
+            return None;
+        }
+        if core.data.get_array_len("COMPREPLY") == 1 {
+            self.try_completion(core);
+            return None;
+        }
+        self.menu_select(core, keys)
+    }
+
+    /* selects one of COMPREPLY */
+    pub fn menu_select<I>(&mut self, core: &mut ShellCore, keys: &mut I) -> Option<event::Key>
+    where I: Iterator<Item = io::Result<event::Key>> {
+        let matcher = self.completion_matcher;
+        let original = (self.chars.clone(), self.head);
+        let word = self.completion_context.word.clone();
+        let mut typed = String::new();
+        let mut menu = Menu::new(self.menu_items(core), Some(0));
+
+        let key = loop {
+            let candidate = menu.current();
+            self.replace_input(&candidate);
+            self.draw_menu(&mut menu);
+
+            let key = match keys.next() {
+                Some(Ok(k)) => k,
+                _ => break None,
+            };
+            match key {
+                event::Key::Char('\t') | event::Key::Down => menu.shift(1),
+                event::Key::BackTab | event::Key::Up => menu.shift(-1),
+                event::Key::Right => menu.shift_column(1, Terminal::size().0),
+                event::Key::Left  => menu.shift_column(-1, Terminal::size().0),
+                event::Key::PageDown => menu.shift_page(1),
+                event::Key::PageUp   => menu.shift_page(-1),
+                event::Key::Char('\n') => {
+                    let tail = self.completion_tail(core, &candidate);
+                    self.replace_input(&(candidate + tail));
+                    break None;
+                },
+                event::Key::Esc | event::Key::Ctrl('g') => {
+                    (self.chars, self.head) = original;
+                    break None;
+                },
+                event::Key::Char(c) => {
+                    typed.push(c);
+                    if ! menu.narrow(&(word.clone() + &typed), &matcher) {
+                        typed.pop();
+                        self.cloop();
+                    }
+                },
+                event::Key::Backspace => match typed.pop() {
+                    Some(_) => { menu.narrow(&(word.clone() + &typed), &matcher); },
+                    None => {
+                        (self.chars, self.head) = original;
+                        break None;
+                    },
+                },
+                k => break Some(k),
+            }
+        };
+
+        self.rewrite(true);
+        key
+    }
+}
@@ -0,0 +1,80 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{Feeder, ShellCore};
+use crate::feeder::terminal::Terminal;
+
+impl Terminal {
+    /* the start of the line in the buffer where pos is */
+    fn row_start(&self, pos: usize) -> usize {
+        let mut p = pos;
+        while p > self.line_start() && self.chars[p-1] != '\n' {
+            p -= 1;
+        }
+        p
+    }
+
+    fn row_end(&self, pos: usize) -> usize {
+        let mut p = pos;
+        while p < self.chars.len() && self.chars[p] != '\n' {
+            p += 1;
+        }
+        p
+    }
+
+    pub fn goto_row_start(&mut self) {
+        self.head = self.row_start(self.head);
+        self.goto(self.head);
+        self.flush();
+    }
+
+    pub fn goto_row_end(&mut self) {
+        self.head = self.row_end(self.head);
+        self.goto(self.head);
+        self.flush();
+    }
+
+    /* false: the cursor is on the first line */
+    pub fn row_up(&mut self) -> bool {
+        let start = self.row_start(self.head);
+        if start == self.line_start() {
+            return false;
+        }
+        let col = self.head - start;
+        let prev_start = self.row_start(start - 1);
+        self.head = std::cmp::min(prev_start + col, start - 1);
+        self.goto(self.head);
+        self.flush();
+        true
+    }
+
+    /* false: the cursor is on the last line */
+    pub fn row_down(&mut self) -> bool {
+        let end = self.row_end(self.head);
+        if end == self.chars.len() {
+            return false;
+        }
+        let col = self.head - self.row_start(self.head);
+        self.head = std::cmp::min(end + 1 + col, self.row_end(end + 1));
+        self.goto(self.head);
+        self.flush();
+        true
+    }
+
+    /* Enter on an incomplete command inserts a newline. false: the line is not accepted */
+    pub fn accept(&mut self, core: &mut ShellCore, force: bool) -> bool {
+        let text = self.get_string(self.line_start());
+        if ! force && self.multiline && Feeder::is_incomplete(core, &text) {
+            self.insert_str("\n");
+            self.check_scroll();
+            self.rewrite(true);
+            return false;
+        }
+
+        self.clear_suggestion();
+        self.goto(self.chars.len());
+        self.write("\r\n");
+        self.chars.push('\n');
+        true
+    }
+}
@@ -0,0 +1,220 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::{Feeder, ShellCore};
+use crate::core::builtins::printf;
+use crate::elements::word::Word;
+use crate::utils::git;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use nix::unistd;
+use nix::unistd::User;
+
+/* the markers of \[ and \] for zero-width characters, as readline does */
+pub const START_IGNORE: char = '\x01';
+pub const END_IGNORE: char = '\x02';
+
+fn git_mark(core: &mut ShellCore, name: &str, default: &str) -> String {
+    match core.data.has_value(name) {
+        true  => core.data.get_param(name),
+        false => default.to_string(),
+    }
+}
+
+/* the segment of \b, formatted with SUSH_GIT_FORMAT */
+fn git_segment(core: &mut ShellCore) -> String {
+    let format = git_mark(core, "SUSH_GIT_FORMAT", "%b%r%d%s%u%a🌵");
+    let query = git::Query {
+        staged: format.contains("%s"),
+        dirty: format.contains("%d"),
+        untracked: format.contains("%u"),
+        upstream: format.contains("%a"),
+    };
+
+    let dir = match core.data.get_param("PWD") {
+        p if ! p.is_empty() => PathBuf::from(p),
+        _ => match unistd::getcwd() {
+            Ok(p) => p,
+            _ => return String::new(),
+        },
+    };
+    let status = match git::status(&mut core.git_caches, &dir, &query) {
+        Some(s) => s,
+        None => return String::new(),
+    };
+
+    let mut ans = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            ans.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('b') if status.detached => ans += &format!("({})", status.branch),
+            Some('b') => ans += &status.branch,
+            Some('r') if ! status.state.is_empty() => ans += &format!("|{}", status.state),
+            Some('s') if status.staged => ans += &git_mark(core, "SUSH_GIT_STAGED", "+"),
+            Some('d') if status.dirty => ans += &git_mark(core, "SUSH_GIT_DIRTY", "*"),
+            Some('u') if status.untracked => ans += &git_mark(core, "SUSH_GIT_UNTRACKED", "?"),
+            Some('a') => {
+                let (ahead, behind) = status.ahead_behind.unwrap_or((0, 0));
+                if ahead > 0 {
+                    ans += &(git_mark(core, "SUSH_GIT_AHEAD", "↑") + &ahead.to_string());
+                }
+                if behind > 0 {
+                    ans += &(git_mark(core, "SUSH_GIT_BEHIND", "↓") + &behind.to_string());
+                }
+            },
+            Some('%') => ans.push('%'),
+            Some(c) if ! "brsdu".contains(c) => {
+                ans.push('%');
+                ans.push(c);
+            },
+            _ => {},
+        }
+    }
+    ans
+}
+
+fn now() -> i64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        _     => 0,
+    }
+}
+
+fn user_name() -> String {
+    match User::from_uid(unistd::getuid()) {
+        Ok(Some(u)) => u.name,
+        _ => "".to_string(),
+    }
+}
+
+fn host_name() -> String {
+    match unistd::gethostname() {
+        Ok(h) => h.to_string_lossy().to_string(),
+        _ => "".to_string(),
+    }
+}
+
+fn tty_name() -> String {
+    match unistd::ttyname(io::stdin()) {
+        Ok(p) => p.file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_default(),
+        _ => "tty".to_string(),
+    }
+}
+
+fn current_dir(core: &mut ShellCore) -> String {
+    let cwd = match core.data.get_param("PWD") {
+        p if ! p.is_empty() => p,
+        _ => match unistd::getcwd() {
+            Ok(p) => p.to_string_lossy().to_string(),
+            _ => "".to_string(),
+        },
+    };
+    let home = core.data.get_param("HOME");
+
+    if ! home.is_empty() && home != "/" && (cwd == home || cwd.starts_with(&(home.clone() + "/"))) {
+        return cwd.replacen(&home, "~", 1);
+    }
+    cwd
+}
+
+fn base_dir(core: &mut ShellCore) -> String {
+    match current_dir(core) {
+        d if d == "/" || d == "~" => d,
+        d => d.rsplit('/').next().unwrap_or("").to_string(),
+    }
+}
+
+/* \nnn */
+fn octal(chars: &[char], pos: usize) -> Option<char> {
+    let digits: String = chars[pos..].iter().take(3).collect();
+    if digits.len() < 3 || ! digits.chars().all(|c| c.is_digit(8)) {
+        return None;
+    }
+    u32::from_str_radix(&digits, 8).ok().and_then(char::from_u32)
+}
+
+/* backslash escapes of prompts */
+pub fn decode(core: &mut ShellCore, raw: &str) -> String {
+    let chars: Vec<char> = raw.chars().collect();
+    let version = env!("CARGO_PKG_VERSION");
+    let mut ans = String::new();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        if chars[pos] != '\\' || pos + 1 >= chars.len() {
+            ans.push(chars[pos]);
+            pos += 1;
+            continue;
+        }
+
+        if let Some(c) = octal(&chars, pos + 1) {
+            ans.push(c);
+            pos += 4;
+            continue;
+        }
+
+        pos += 2;
+        match chars[pos-1] {
+            'a' => ans.push('\x07'),
+            'b' => ans += &git_segment(core),
+            'd' => ans += &printf::strftime("%a %b %d", now()),
+            'D' if chars.get(pos) == Some(&'{') => {
+                let end = (pos..chars.len()).find(|i| chars[*i] == '}').unwrap_or(chars.len());
+                let format: String = chars[pos+1..end].iter().collect();
+                ans += &printf::strftime(&format, now());
+                pos = end + 1;
+            },
+            'e' => ans.push('\x1b'),
+            'h' => ans += host_name().split('.').next().unwrap_or(""),
+            'H' => ans += &host_name(),
+            'j' => ans += &core.job_table.len().to_string(),
+            'l' => ans += &tty_name(),
+            'n' => ans.push('\n'),
+            'r' => ans.push('\r'),
+            's' => {
+                let name = core.data.get_param("0");
+                ans += name.rsplit('/').next().unwrap_or("sush");
+            },
+            't' => ans += &printf::strftime("%H:%M:%S", now()),
+            'T' => ans += &printf::strftime("%I:%M:%S", now()),
+            '@' => ans += &printf::strftime("%I:%M %p", now()),
+            'A' => ans += &printf::strftime("%H:%M", now()),
+            'u' => ans += &user_name(),
+            'v' => ans += version.rsplitn(2, '.').last().unwrap_or(version),
+            'V' => ans += version,
+            'w' => ans += &current_dir(core),
+            'W' => ans += &base_dir(core),
+            '!' => ans += &(core.history.len() + 1).to_string(),
+            '#' => ans += &core.command_number.to_string(),
+            '$' => ans.push(if unistd::geteuid().is_root() {'#'} else {'$'}),
+            '\\' => ans.push('\\'),
+            '[' => ans.push(START_IGNORE),
+            ']' => ans.push(END_IGNORE),
+            c => {
+                ans.push('\\');
+                ans.push(c);
+            },
+        }
+    }
+    ans
+}
+
+/* decoding, and parameter expansion, command substitution and arithmetic expansion
+ * under the promptvars shopt. The markers of \[ and \] are left */
+pub fn expand(core: &mut ShellCore, raw: &str) -> String {
+    let decoded = decode(core, raw);
+    if ! core.shopts.query("promptvars") || ! decoded.contains(['$', '`']) {
+        return decoded;
+    }
+
+    let exit_status = core.data.get_param("?");
+    let word = Word::parse_here_document(&mut Feeder::new(&decoded), core);
+    let ans = word.eval_as_here_document(core).unwrap_or(decoded);
+    core.data.set_param("?", &exit_status);
+    ans
+}
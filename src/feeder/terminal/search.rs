@@ -0,0 +1,169 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::ShellCore;
+use crate::feeder::terminal::Terminal;
+use std::io;
+use termion::event;
+
+#[derive(Debug, Clone, Copy)]
+struct Match {
+    entry: usize, // index of core.history
+    pos: usize,   // char position in the entry
+}
+
+fn history_line(core: &ShellCore, entry: usize) -> String {
+    core.history[entry].line.replace("↵ \0", "\n")
+}
+
+/* backward: the last position not after the limit, forward: the first one not before it */
+fn find_in(line: &[char], query: &[char], limit: Option<isize>, backward: bool) -> Option<usize> {
+    if query.len() > line.len() {
+        return None;
+    }
+
+    let hit = |p: &usize| line[*p..*p+query.len()] == *query;
+    let starts = 0..=(line.len() - query.len());
+    match (backward, limit) {
+        (true, Some(l))  => starts.rev().filter(|p| *p as isize <= l).find(hit),
+        (false, Some(l)) => starts.filter(|p| *p as isize >= l).find(hit),
+        (true, None)     => starts.rev().find(hit),
+        (false, None)    => starts.into_iter().find(hit),
+    }
+}
+
+/* skip: searches the next match, not the one at the current position */
+fn find_match(core: &ShellCore, query: &str, from: Match, backward: bool, skip: bool) -> Option<Match> {
+    let query: Vec<char> = query.chars().collect();
+    let entries: Vec<usize> = match backward {
+        true  => (from.entry..core.history.len()).collect(),
+        false => (0..=from.entry).rev().collect(),
+    };
+
+    for entry in entries {
+        let line: Vec<char> = history_line(core, entry).chars().collect();
+        let limit = match (entry == from.entry, skip, backward) {
+            (false, _, _)    => None,
+            (true, false, _) => Some(from.pos as isize),
+            (true, true, true)  => Some(from.pos as isize - 1),
+            (true, true, false) => Some(from.pos as isize + 1),
+        };
+
+        if let Some(pos) = find_in(&line, &query, limit, backward) {
+            return Some(Match { entry, pos });
+        }
+    }
+    None
+}
+
+impl Terminal {
+    fn set_line(&mut self, line: &str, pos: usize) {
+        self.chars = self.prompt.chars().collect();
+        self.chars.extend(line.chars());
+        self.head = self.prompt.chars().count() + pos;
+        self.rewrite(true);
+    }
+
+    fn show_search(&mut self, core: &ShellCore, query: &str, found: Match, backward: bool, failed: bool) {
+        self.prompt = format!("({}{}i-search)`{}': ",
+                              if failed {"failed "} else {""},
+                              if backward {"reverse-"} else {""}, query);
+        self.prompt_width_map = Self::make_width_map(&self.prompt);
+        self.set_line(&history_line(core, found.entry), found.pos);
+    }
+
+    /* Ctrl-R/Ctrl-S. returns the key that ends the search to be processed as usual */
+    pub fn incremental_search<I>(&mut self, core: &mut ShellCore, keys: &mut I, mut backward: bool) -> Option<event::Key>
+    where I: Iterator<Item = io::Result<event::Key>> {
+        let prompt = self.prompt.clone();
+        let width_map = self.prompt_width_map.clone();
+        let prompt_len = prompt.chars().count();
+        let original = (self.get_string(prompt_len), self.head - prompt_len);
+        core.history[self.hist_ptr].line = original.0.clone();
+
+        let mut query = String::new();
+        let mut found = Match { entry: self.hist_ptr, pos: original.1 };
+        let mut failed = false;
+        let mut stack = vec![]; //states before each step, for Backspace
+
+        let key = loop {
+            self.show_search(core, &query, found, backward, failed);
+            let key = match keys.next() {
+                Some(Ok(k)) => k,
+                _ => break None,
+            };
+
+            let skip = match key {
+                event::Key::Ctrl('r') | event::Key::Ctrl('s') => {
+                    backward = key == event::Key::Ctrl('r');
+                    true
+                },
+                event::Key::Char(c) if c != '\n' && c != '\t' => {
+                    stack.push((query.clone(), found, failed));
+                    query.push(c);
+                    false
+                },
+                event::Key::Backspace => {
+                    match stack.pop() {
+                        Some(state) => (query, found, failed) = state,
+                        None        => self.cloop(),
+                    }
+                    continue;
+                },
+                event::Key::Esc | event::Key::Ctrl('g') => {
+                    self.prompt = prompt;
+                    self.prompt_width_map = width_map;
+                    self.set_line(&original.0, original.1);
+                    return None;
+                },
+                k => break Some(k),
+            };
+
+            if query.is_empty() {
+                continue;
+            }
+            if skip {
+                stack.push((query.clone(), found, failed));
+            }
+            match find_match(core, &query, found, backward, skip) {
+                Some(m) => (found, failed) = (m, false),
+                None    => { failed = true; self.cloop() },
+            }
+        };
+
+        self.prompt = prompt;
+        self.prompt_width_map = width_map;
+        self.hist_ptr = found.entry;
+        self.set_line(&history_line(core, found.entry), found.pos);
+        key
+    }
+
+    /* Up/Down on a non-empty line only visit the entries beginning with the text before the cursor */
+    pub fn call_history_with_prefix(&mut self, inc: i32, core: &mut ShellCore) {
+        let prompt_len = self.prompt.chars().count();
+        let prefix = self.hist_prefix.get_or_insert(self.chars[prompt_len..self.head].iter().collect()).clone();
+        if prefix.is_empty() {
+            self.call_history(inc, core);
+            return;
+        }
+
+        let current = self.get_string(prompt_len);
+        core.history[self.hist_ptr].line = current.clone();
+        let judge = |i: &usize| {
+            let line = history_line(core, *i);
+            line.starts_with(&prefix) && line != current
+        };
+        let found = match inc > 0 {
+            true  => (self.hist_ptr+1..core.history.len()).find(judge),
+            false => (0..self.hist_ptr).rev().find(judge),
+        };
+
+        match found {
+            Some(i) => {
+                self.hist_ptr = i;
+                self.set_line(&history_line(core, i), prefix.chars().count());
+            },
+            None => self.cloop(),
+        }
+    }
+}
@@ -0,0 +1,462 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda ryuichiueda@gmail.com
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::ShellCore;
+use crate::feeder::terminal::Terminal;
+use crate::feeder::terminal::edit;
+use termion::event;
+
+pub const INSERT_MODE_STRING: &str = "(ins)";
+pub const COMMAND_MODE_STRING: &str = "(cmd)";
+
+#[derive(Debug, Default)]
+pub struct ViState {
+    command_mode: bool,
+    count: String,
+    operator: Option<char>,
+    pending: Option<char>, // f, t, F, T or r waiting for a char
+    last_find: Option<(char, char)>,
+    keys: Vec<event::Key>, // the command under way
+    recording: bool, // the keys in insert mode belong to the command
+    last_change: Vec<event::Key>, // for .
+    search: Option<String>, // the pattern after /
+    saved_line: (Vec<char>, usize),
+    last_search: String,
+}
+
+pub enum ViResult {
+    Done,
+    Pass, // processed as in emacs mode
+    Accept,
+}
+
+/* 0: blanks, 1: letters, digits and underscores, 2: others */
+fn class(c: char) -> u8 {
+    match c {
+        _ if c.is_whitespace() => 0,
+        _ if c.is_alphanumeric() || c == '_' => 1,
+        _ => 2,
+    }
+}
+
+impl Terminal {
+    pub fn vi_key(&mut self, core: &mut ShellCore, key: &event::Key) -> ViResult {
+        let mut vi = match self.vi.take() {
+            Some(vi) => vi,
+            None     => return ViResult::Pass,
+        };
+        let ans = match key {
+            event::Key::Alt(c) => { //Esc and a key typed quickly
+                let c = if *c == '\r' {'\n'} else {*c};
+                if ! vi.command_mode {
+                    self.vi_dispatch(core, &mut vi, &event::Key::Esc);
+                }
+                match self.vi_dispatch(core, &mut vi, &event::Key::Char(c)) {
+                    ViResult::Pass if c == '\n' => ViResult::Accept,
+                    ans => ans,
+                }
+            },
+            _ => self.vi_dispatch(core, &mut vi, key),
+        };
+        self.vi = Some(vi);
+        ans
+    }
+
+    fn vi_dispatch(&mut self, core: &mut ShellCore, vi: &mut ViState, key: &event::Key) -> ViResult {
+        if vi.search.is_some() {
+            return self.vi_search_key(core, vi, key);
+        }
+
+        if ! vi.command_mode {
+            if vi.recording {
+                vi.keys.push(*key);
+            }
+            if *key != event::Key::Esc {
+                return ViResult::Pass;
+            }
+            if vi.recording {
+                vi.last_change = vi.keys.clone();
+            }
+            vi.keys.clear();
+            vi.recording = false;
+            self.vi_set_mode(vi, true);
+            self.head = self.head.saturating_sub(1).max(self.line_start());
+            self.rewrite(true);
+            return ViResult::Done;
+        }
+
+        let c = match key {
+            event::Key::Char(c) if *c != '\n' => *c,
+            event::Key::Esc => {
+                self.vi_finish(vi, false);
+                return ViResult::Done;
+            },
+            _ => return ViResult::Pass,
+        };
+        vi.keys.push(*key);
+
+        if let Some(p) = vi.pending.take() {
+            if p == 'r' {
+                self.vi_replace(vi, c);
+                return ViResult::Done;
+            }
+            vi.last_find = Some((p, c));
+            return self.vi_exec(core, vi, ';');
+        }
+
+        if c.is_ascii_digit() && (c != '0' || ! vi.count.is_empty()) {
+            vi.count.push(c);
+            return ViResult::Done;
+        }
+        if "fFtTr".contains(c) {
+            vi.pending = Some(c);
+            return ViResult::Done;
+        }
+        self.vi_exec(core, vi, c)
+    }
+
+    /* change: the command is kept for . */
+    fn vi_finish(&mut self, vi: &mut ViState, change: bool) {
+        if change {
+            vi.last_change = vi.keys.clone();
+        }
+        vi.keys.clear();
+        vi.count.clear();
+        vi.operator = None;
+        vi.pending = None;
+        self.vi_clamp();
+        self.rewrite(true);
+    }
+
+    fn vi_set_mode(&mut self, vi: &mut ViState, command: bool) {
+        vi.command_mode = command;
+        let mode = match command {
+            true  => COMMAND_MODE_STRING,
+            false => INSERT_MODE_STRING,
+        };
+        let len = mode.chars().count();
+        if self.prompt.starts_with(INSERT_MODE_STRING) || self.prompt.starts_with(COMMAND_MODE_STRING) {
+            self.prompt.replace_range(..mode.len(), mode);
+            self.chars.splice(..len, mode.chars());
+        }
+    }
+
+    /* the cursor in command mode is on a char */
+    fn vi_clamp(&mut self) {
+        if self.head >= self.chars.len() && self.chars.len() > self.line_start() {
+            self.head = self.chars.len() - 1;
+        }
+    }
+
+    fn vi_save_undo(&mut self) {
+        self.undo_stack.push((self.chars.clone(), self.head));
+    }
+
+    fn vi_insert_mode(&mut self, vi: &mut ViState, c: char) {
+        self.vi_save_undo();
+        match c {
+            'a' if self.head < self.chars.len() => self.head += 1,
+            'A' => self.head = self.chars.len(),
+            'I' => self.head = self.line_start(),
+            _ => {},
+        }
+        vi.count.clear();
+        vi.recording = true;
+        self.vi_set_mode(vi, false);
+        self.rewrite(true);
+    }
+
+    fn vi_next_word(&self, pos: usize) -> usize {
+        let len = self.chars.len();
+        let mut p = pos;
+        if p < len {
+            let k = class(self.chars[p]);
+            while p < len && k != 0 && class(self.chars[p]) == k {
+                p += 1;
+            }
+        }
+        while p < len && class(self.chars[p]) == 0 {
+            p += 1;
+        }
+        p
+    }
+
+    fn vi_prev_word(&self, pos: usize) -> usize {
+        let start = self.line_start();
+        let mut p = pos;
+        while p > start && class(self.chars[p-1]) == 0 {
+            p -= 1;
+        }
+        if p > start {
+            let k = class(self.chars[p-1]);
+            while p > start && class(self.chars[p-1]) == k {
+                p -= 1;
+            }
+        }
+        p
+    }
+
+    fn vi_word_end(&self, pos: usize) -> usize {
+        let len = self.chars.len();
+        let mut p = pos + 1;
+        while p < len && class(self.chars[p]) == 0 {
+            p += 1;
+        }
+        if p < len {
+            let k = class(self.chars[p]);
+            while p + 1 < len && class(self.chars[p+1]) == k {
+                p += 1;
+            }
+        }
+        p.min(len.saturating_sub(1)).max(pos)
+    }
+
+    fn vi_find(&self, pos: usize, kind: char, target: char) -> Option<usize> {
+        let hit = |i: &usize| self.chars[*i] == target;
+        match kind {
+            'f' => (pos+1..self.chars.len()).find(hit),
+            't' => (pos+2..self.chars.len()).find(hit).map(|i| i - 1),
+            'F' => (self.line_start()..pos).rev().find(hit),
+            _   => (self.line_start()..pos.saturating_sub(1)).rev().find(hit).map(|i| i + 1),
+        }
+    }
+
+    /* the destination and whether operators include the char on it */
+    fn vi_motion(&self, vi: &ViState, c: char, count: usize) -> Option<(usize, bool)> {
+        let (start, len) = (self.line_start(), self.chars.len());
+        let mut pos = self.head;
+        let mut inclusive = "e$".contains(c);
+
+        for _ in 0..count {
+            pos = match c {
+                'h' => pos.saturating_sub(1).max(start),
+                'l' | ' ' => (pos + 1).min(len),
+                'w' => self.vi_next_word(pos),
+                'b' => self.vi_prev_word(pos),
+                'e' => self.vi_word_end(pos),
+                '0' => start,
+                '^' => (start..len).find(|i| class(self.chars[*i]) != 0).unwrap_or(len),
+                '$' => len.saturating_sub(1).max(start),
+                ';' => {
+                    let (kind, target) = vi.last_find?;
+                    inclusive = "ft".contains(kind);
+                    self.vi_find(pos, kind, target)?
+                },
+                _ => return None,
+            };
+        }
+        Some((pos, inclusive))
+    }
+
+    fn vi_operate(&mut self, core: &mut ShellCore, vi: &mut ViState, op: char, from: usize, to: usize) {
+        let to = to.min(self.chars.len());
+        if from >= to {
+            self.cloop();
+            return self.vi_finish(vi, false);
+        }
+
+        let text: String = self.chars[from..to].iter().collect();
+        edit::push_kill_ring(core, text);
+
+        if op != 'y' {
+            self.vi_save_undo();
+            self.chars.drain(from..to);
+        }
+        self.head = from;
+
+        match op {
+            'c' => {
+                vi.operator = None;
+                self.vi_insert_mode(vi, 'i');
+                self.undo_stack.pop(); //saved before the deletion
+            },
+            _ => self.vi_finish(vi, op != 'y'),
+        }
+    }
+
+    fn vi_replace(&mut self, vi: &mut ViState, c: char) {
+        let count = vi.count.parse::<usize>().unwrap_or(1).max(1);
+        if self.head + count > self.chars.len() {
+            self.cloop();
+            return self.vi_finish(vi, false);
+        }
+
+        self.vi_save_undo();
+        for i in 0..count {
+            self.chars[self.head + i] = c;
+        }
+        self.head += count - 1;
+        self.vi_finish(vi, true);
+    }
+
+    fn vi_put(&mut self, core: &mut ShellCore, vi: &mut ViState, after: bool, count: usize) {
+        let text = match core.kill_ring.last() {
+            Some(t) => t.repeat(count),
+            None    => {
+                self.cloop();
+                return self.vi_finish(vi, false);
+            },
+        };
+
+        self.vi_save_undo();
+        if after && self.head < self.chars.len() {
+            self.head += 1;
+        }
+        self.insert_str(&text);
+        self.head -= 1;
+        self.vi_finish(vi, true);
+    }
+
+    fn vi_exec(&mut self, core: &mut ShellCore, vi: &mut ViState, c: char) -> ViResult {
+        let count = vi.count.parse::<usize>().unwrap_or(1).max(1);
+
+        if let Some(op) = vi.operator {
+            if c == op { //dd, cc, yy
+                self.vi_operate(core, vi, op, self.line_start(), self.chars.len());
+                return ViResult::Done;
+            }
+
+            let on_word = self.head < self.chars.len() && class(self.chars[self.head]) != 0;
+            let motion = match (op, c) {
+                ('c', 'w') if on_word => 'e', //cw works as ce
+                _ => c,
+            };
+            match self.vi_motion(vi, motion, count) {
+                Some((pos, inclusive)) => {
+                    let (from, to) = match pos < self.head {
+                        true  => (pos, self.head),
+                        false => (self.head, pos + if inclusive {1} else {0}),
+                    };
+                    self.vi_operate(core, vi, op, from, to);
+                },
+                None => {
+                    self.cloop();
+                    self.vi_finish(vi, false);
+                },
+            }
+            return ViResult::Done;
+        }
+
+        match c {
+            'd' | 'c' | 'y' => vi.operator = Some(c),
+            'D' | 'C' => self.vi_operate(core, vi, c.to_ascii_lowercase(), self.head, self.chars.len()),
+            'x' => {
+                let to = self.head + count;
+                self.vi_operate(core, vi, 'd', self.head, to);
+            },
+            'p' | 'P' => self.vi_put(core, vi, c == 'p', count),
+            'i' | 'a' | 'I' | 'A' => self.vi_insert_mode(vi, c),
+            'u' => {
+                self.undo();
+                self.vi_finish(vi, false);
+            },
+            '.' => self.vi_repeat(core, vi),
+            'j' | 'k' => {
+                let inc = if c == 'k' {count as i32} else {-(count as i32)};
+                self.call_history(inc, core);
+                self.head = self.line_start();
+                self.vi_finish(vi, false);
+            },
+            '/' => self.vi_start_search(vi),
+            'n' | 'N' => {
+                let pattern = vi.last_search.clone();
+                self.vi_search_history(core, &pattern, c == 'n');
+                self.vi_finish(vi, false);
+            },
+            'v' => return self.vi_edit_and_execute(core, vi),
+            _ => match self.vi_motion(vi, c, count) {
+                Some((pos, _)) => {
+                    self.head = pos;
+                    self.vi_finish(vi, false);
+                },
+                None => {
+                    self.cloop();
+                    self.vi_finish(vi, false);
+                },
+            },
+        }
+        ViResult::Done
+    }
+
+    fn vi_repeat(&mut self, core: &mut ShellCore, vi: &mut ViState) {
+        let keys = vi.last_change.clone();
+        vi.keys.clear();
+        vi.count.clear();
+        for key in &keys {
+            match (vi.command_mode, key) {
+                (true, _) => { self.vi_dispatch(core, vi, key); },
+                (false, event::Key::Esc) => { self.vi_dispatch(core, vi, key); },
+                (false, event::Key::Char(c)) => self.insert(*c),
+                (false, event::Key::Backspace) => self.backspace(),
+                _ => {},
+            }
+        }
+        vi.last_change = keys;
+    }
+
+    fn vi_start_search(&mut self, vi: &mut ViState) {
+        vi.saved_line = (self.chars.clone(), self.head);
+        vi.search = Some(String::new());
+        self.chars = self.prompt.chars().collect();
+        self.chars.push('/');
+        self.head = self.chars.len();
+        self.rewrite(true);
+    }
+
+    fn vi_search_key(&mut self, core: &mut ShellCore, vi: &mut ViState, key: &event::Key) -> ViResult {
+        let mut pattern = vi.search.take().unwrap_or_default();
+        match key {
+            event::Key::Char('\n') => {
+                (self.chars, self.head) = vi.saved_line.clone();
+                if ! pattern.is_empty() {
+                    vi.last_search = pattern;
+                }
+                let pattern = vi.last_search.clone();
+                self.vi_search_history(core, &pattern, true);
+                self.vi_finish(vi, false);
+            },
+            event::Key::Char(c) => {
+                pattern.push(*c);
+                self.insert(*c);
+                vi.search = Some(pattern);
+            },
+            event::Key::Backspace if ! pattern.is_empty() => {
+                pattern.pop();
+                self.backspace();
+                vi.search = Some(pattern);
+            },
+            _ => {
+                (self.chars, self.head) = vi.saved_line.clone();
+                self.vi_finish(vi, false);
+            },
+        }
+        ViResult::Done
+    }
+
+    /* older: searches toward the older entries */
+    fn vi_search_history(&mut self, core: &mut ShellCore, pattern: &str, older: bool) {
+        let judge = |i: &usize| core.history[*i].line.contains(pattern);
+        let found = match older {
+            true  => (self.hist_ptr+1..core.history.len()).find(judge),
+            false => (0..self.hist_ptr).rev().find(judge),
+        };
+
+        match found {
+            Some(i) if ! pattern.is_empty() => {
+                self.call_history(i as i32 - self.hist_ptr as i32, core);
+                self.head = self.line_start();
+            },
+            _ => self.cloop(),
+        }
+    }
+
+    /* v: the line edited with VISUAL or EDITOR is run */
+    fn vi_edit_and_execute(&mut self, core: &mut ShellCore, vi: &mut ViState) -> ViResult {
+        let edited = self.edit_and_execute_command(core);
+        self.vi_finish(vi, false);
+        match edited {
+            true  => ViResult::Accept,
+            false => ViResult::Done,
+        }
+    }
+}
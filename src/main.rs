@@ -16,7 +16,7 @@ use std::sync::atomic::Ordering::Relaxed;
 use crate::core::{builtins, ShellCore};
 use crate::elements::io;
 use crate::elements::script::Script;
-use crate::feeder::{Feeder, InputError};
+use crate::feeder::{terminal, Feeder, InputError};
 use utils::file_check;
 
 fn show_version() {
@@ -113,38 +113,55 @@ fn main() {
     signal::run_signal_check(&mut core);
 
     if c_flag {
+        core.data.flags += "c";
         main_c_option(&mut core, &script);
         core.exit();
     }
 
+    core.read_inputrc(); // before the rc file so that bind in it takes precedence
     read_rc_file(&mut core);
+    core.read_history_on_start();
     main_loop(&mut core);
 }
 
-fn set_history(core: &mut ShellCore, s: &str) {
-    if core.read_stdin || core.history.len() == 0 {
-        return;
+/* noexec is ignored by interactive shells */
+fn no_exec(core: &ShellCore) -> bool {
+    core.data.flags.contains('n') && ! core.data.flags.contains('i')
+}
+
+fn print_ps0(core: &mut ShellCore) {
+    let ps0 = core.data.get_param("PS0");
+    if ! ps0.is_empty() {
+        eprint!("{}", terminal::expand_prompt(core, &ps0));
     }
+}
 
-    core.history[0] = s.trim_end().replace("\n", "↵ \0").to_string();
-    if core.history[0].len() == 0
-    || (core.history.len() > 1 && core.history[0] == core.history[1]) {
-        core.history.remove(0);
+/* the number of consecutive EOFs ignored by an interactive shell */
+fn ignoreeof_limit(core: &mut ShellCore) -> usize {
+    if ! core.options.query("ignoreeof") && ! core.data.has_value("IGNOREEOF") {
+        return 0;
     }
+    core.data.get_param("IGNOREEOF").parse::<usize>().unwrap_or(10)
 }
 
 fn main_loop(core: &mut ShellCore) {
     let mut feeder = Feeder::new("");
+    let mut eof_count = 0;
     loop {
         core.jobtable_check_status();
         core.jobtable_print_status_change();
 
         match feeder.feed_line(core) {
-            Ok(()) => {}, 
+            Ok(()) => eof_count = 0,
             Err(InputError::Interrupt) => {
                 signal::input_interrupt_check(&mut feeder, core);
                 continue;
             },
+            Err(InputError::Eof) if ! core.read_stdin && eof_count < ignoreeof_limit(core) => {
+                eof_count += 1;
+                eprintln!("Use \"exit\" to leave the shell.");
+                continue;
+            },
             _ => break,
         }
 
@@ -152,8 +169,17 @@ fn main_loop(core: &mut ShellCore) {
         core.sigint.store(false, Relaxed);
         match Script::parse(&mut feeder, core, false){
             Some(mut s) => {
-                s.exec(core);
-                set_history(core, &s.get_text());
+                if core.options.query("history") {
+                    core.add_history(&s.get_text());
+                }
+                if ! core.read_stdin {
+                    print_ps0(core);
+                }
+                if ! no_exec(core) {
+                    s.exec(core);
+                }
+                core.command_number += 1;
+                core.check_trapped_signals();
             },
             None => {},
         }
@@ -166,7 +192,9 @@ fn main_loop(core: &mut ShellCore) {
 fn main_c_option(core: &mut ShellCore, script: &String) {
     let mut feeder = Feeder::new(script);
     if let Some(mut s) = Script::parse(&mut feeder, core, false){
-        s.exec(core);
+        if ! no_exec(core) {
+            s.exec(core);
+        }
     }
     core.exit();
 }
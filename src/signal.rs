@@ -3,9 +3,13 @@
 
 use std::{thread, time};
 use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering::Relaxed;
 use crate::core::ShellCore;
 use crate::feeder::Feeder;
+use nix::libc;
+use nix::sys::signal;
+use nix::sys::signal::{SaFlags, SigAction, SigHandler, SigSet, Signal};
 use signal_hook::consts;
 use signal_hook::iterator::Signals;
 
@@ -45,3 +49,31 @@ pub fn input_interrupt_check(feeder: &mut Feeder, core: &mut ShellCore) -> bool
     feeder.consume(feeder.len());
     true
 }
+
+static TRAPPED: [AtomicBool; 65] = [const { AtomicBool::new(false) }; 65];
+
+extern "C" fn trap_handler(sig: libc::c_int) {
+    if let Some(flag) = TRAPPED.get(sig as usize) {
+        flag.store(true, Relaxed);
+    }
+}
+
+pub fn set_handler(sig: Signal, handler: SigHandler) -> Option<SigAction> {
+    let action = SigAction::new(handler, SaFlags::SA_RESTART, SigSet::empty());
+    unsafe { signal::sigaction(sig, &action) }.ok()
+}
+
+pub fn restore_action(sig: Signal, action: &SigAction) {
+    let _ = unsafe { signal::sigaction(sig, action) };
+}
+
+pub fn catch(sig: Signal) -> Option<SigAction> {
+    set_handler(sig, SigHandler::Handler(trap_handler))
+}
+
+pub fn take_trapped(sig: i32) -> bool {
+    match TRAPPED.get(sig as usize) {
+        Some(flag) => flag.swap(false, Relaxed),
+        None       => false,
+    }
+}
@@ -4,10 +4,12 @@
 pub mod file_check;
 pub mod glob;
 pub mod directory;
+pub mod escape;
+pub mod git;
 
 pub fn reserved(w: &str) -> bool {
     match w {
-        "[[" | "]]" | "{" | "}" | "while" | "for" | "do" | "done" | "if" | "then" | "elif" | "else" | "fi" | "case" => true,
+        "[[" | "]]" | "{" | "}" | "while" | "until" | "for" | "select" | "do" | "done" | "if" | "then" | "elif" | "else" | "fi" | "case" => true,
         _ => false,
     }
 }
@@ -0,0 +1,114 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+fn take_digits(chars: &[char], from: usize, radix: u32, max: usize) -> (Option<u32>, usize) {
+    let digits: String = chars[from..].iter()
+                         .take(max)
+                         .take_while(|c| c.is_digit(radix))
+                         .collect();
+
+    match digits.len() {
+        0 => (None, 0),
+        n => (u32::from_str_radix(&digits, radix).ok(), n),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    AnsiC,  // $'...'
+    Format, // format strings of printf
+    Echo,   // echo -e
+    B,      // %b of printf
+}
+
+/* expands backslash escapes in the same way as $'...' */
+pub fn ansi_c(s: &str) -> String {
+    expand(s, Mode::AnsiC).0
+}
+
+/* the flag in the return value is true when \c cuts the output */
+pub fn expand(s: &str, mode: Mode) -> (String, bool) {
+    let chars: Vec<char> = s.chars().collect();
+    let mut ans: Vec<u8> = vec![];
+    let mut i = 0;
+    let push = |ans: &mut Vec<u8>, c: char| ans.extend(c.to_string().as_bytes());
+
+    while i < chars.len() {
+        if chars[i] != '\\' || i+1 == chars.len() {
+            push(&mut ans, chars[i]);
+            i += 1;
+            continue;
+        }
+
+        i += 2;
+        match chars[i-1] {
+            'a'  => ans.push(0x07),
+            'b'  => ans.push(0x08),
+            'e' | 'E' => ans.push(0x1b),
+            'f'  => ans.push(0x0c),
+            'n'  => ans.push(b'\n'),
+            'r'  => ans.push(b'\r'),
+            't'  => ans.push(b'\t'),
+            'v'  => ans.push(0x0b),
+            '\\' => ans.push(b'\\'),
+            '\'' | '"' | '?' if mode == Mode::AnsiC || mode == Mode::Format => {
+                push(&mut ans, chars[i-1]);
+            },
+            'c' if mode == Mode::Echo || mode == Mode::B => {
+                return (String::from_utf8_lossy(&ans).to_string(), true);
+            },
+            'c' if mode == Mode::AnsiC && i < chars.len() => {
+                ans.push((chars[i] as u8) & 0x1f);
+                i += 1;
+            },
+            c @ '1'..='7' if mode == Mode::Echo => {
+                ans.push(b'\\');
+                push(&mut ans, c);
+            },
+            c @ ('0'..='7' | 'x' | 'u' | 'U') => {
+                let zero_prefix = c == '0' && (mode == Mode::Echo || mode == Mode::B);
+                let (radix, max, from) = match c {
+                    'x' => (16, 2, i),
+                    'u' => (16, 4, i),
+                    'U' => (16, 8, i),
+                    _ if zero_prefix => (8, 3, i),
+                    _   => (8, 3, i-1),
+                };
+                match take_digits(&chars, from, radix, max) {
+                    (Some(n), len) if radix == 8 || c == 'x' => {
+                        ans.push(n as u8);
+                        i = from + len;
+                    },
+                    (Some(n), len) => {
+                        ans.extend(char::from_u32(n).unwrap_or('\u{fffd}').to_string().as_bytes());
+                        i = from + len;
+                    },
+                    _ if zero_prefix => ans.push(0),
+                    _ => {
+                        ans.push(b'\\');
+                        push(&mut ans, c);
+                    },
+                }
+            },
+            c => {
+                ans.push(b'\\');
+                push(&mut ans, c);
+            },
+        }
+    }
+
+    (String::from_utf8_lossy(&ans).to_string(), false)
+}
+
+pub fn single_quote(s: &str) -> String {
+    format!("'{}'", s.replace("'", "'\\''"))
+}
+
+pub fn double_quote(s: &str) -> String {
+    let escaped: String = s.chars().map(|c| match c {
+        '"' | '\\' | '$' | '`' => format!("\\{}", c),
+        _ => c.to_string(),
+    }).collect();
+
+    format!("\"{}\"", escaped)
+}
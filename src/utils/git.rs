@@ -0,0 +1,449 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+mod ignore;
+mod index;
+mod object;
+
+use self::ignore::Ignore;
+use self::index::Index;
+use self::object::Objects;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const GITLINK: u32 = 0o160000;
+const SYMLINK: u32 = 0o120000;
+const MAX_COMMITS: usize = 100000; // give up counting ahead/behind
+
+/* what the prompt shows; the items not asked are left false or None */
+#[derive(Debug, Clone, Default)]
+pub struct Status {
+    pub branch: String, // a branch name, or a short hash on a detached HEAD
+    pub detached: bool,
+    pub state: String, // REBASE 1/3, MERGING, ...
+    pub staged: bool,
+    pub dirty: bool,
+    pub untracked: bool,
+    pub ahead_behind: Option<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Query {
+    pub staged: bool,
+    pub dirty: bool,
+    pub untracked: bool,
+    pub upstream: bool,
+}
+
+/* the results that need objects, kept for each work tree while the refs and the index stay */
+#[derive(Default)]
+pub struct Cache {
+    signature: Vec<Option<SystemTime>>,
+    index: Index,
+    staged: Option<bool>,
+    ahead_behind: Option<Option<(usize, usize)>>,
+}
+
+struct Repo {
+    work_tree: PathBuf,
+    git_dir: PathBuf,
+    common_dir: PathBuf, // shared by worktrees
+    hash_len: usize,
+}
+
+pub fn hex(hash: &[u8]) -> String {
+    hash.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn unhex(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim();
+    if s.len() % 2 == 1 {
+        return None;
+    }
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(s.get(i..i+2)?, 16).ok()).collect()
+}
+
+fn read_text(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim_end().to_string())
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/* .git is a directory, or a file with "gitdir: path" in worktrees and submodules */
+fn git_dir(dir: &Path) -> Option<PathBuf> {
+    let dotgit = dir.join(".git");
+    if dotgit.is_dir() {
+        return Some(dotgit).filter(|d| d.join("HEAD").is_file());
+    }
+    let text = read_text(&dotgit)?;
+    let path = dir.join(text.strip_prefix("gitdir:")?.trim());
+    Some(path).filter(|d| d.join("HEAD").is_file())
+}
+
+fn find(dir: &Path) -> Option<Repo> {
+    for d in dir.ancestors() {
+        if d.file_name().map(|n| n == ".git").unwrap_or(false) {
+            return None;
+        }
+        let git_dir = match git_dir(d) {
+            Some(g) => g,
+            None => continue,
+        };
+
+        let common_dir = match read_text(&git_dir.join("commondir")) {
+            Some(c) => git_dir.join(c),
+            None => git_dir.clone(),
+        };
+        let mut repo = Repo { work_tree: d.to_path_buf(), git_dir,
+                              common_dir, hash_len: 20 };
+        if repo.config("extensions", None, "objectformat").as_deref() == Some("sha256") {
+            repo.hash_len = 32;
+        }
+        return Some(repo);
+    }
+    None
+}
+
+/* SHA-1, or SHA-256 in a repository with extensions.objectformat = sha256 */
+fn blob_hash(path: &Path, meta: &fs::Metadata, hash_len: usize) -> Option<Vec<u8>> {
+    let data = match meta.file_type().is_symlink() {
+        true  => fs::read_link(path).ok()?.to_string_lossy().as_bytes().to_vec(),
+        false => fs::read(path).ok()?,
+    };
+    let mut obj = format!("blob {}\0", data.len()).into_bytes();
+    obj.extend(data);
+    match hash_len {
+        32 => Some(Sha256::digest(&obj).to_vec()),
+        _  => Some(Sha1::digest(&obj).to_vec()),
+    }
+}
+
+impl Repo {
+    /* a value in the config file: section.subsection.key */
+    fn config(&self, section: &str, subsection: Option<&str>, key: &str) -> Option<String> {
+        let text = fs::read_to_string(self.common_dir.join("config")).ok()?;
+        let mut current = false;
+        let mut ans = None;
+
+        for line in text.lines().map(|l| l.trim()) {
+            if line.starts_with("[") {
+                let header = line.trim_matches(|c| c == '[' || c == ']');
+                let (sec, sub) = match header.split_once(' ') {
+                    Some((s, sub)) => (s, Some(sub.trim().trim_matches('"'))),
+                    None => (header, None),
+                };
+                current = sec.eq_ignore_ascii_case(section) && sub == subsection;
+                continue;
+            }
+
+            if let Some((k, v)) = line.split_once('=') {
+                if current && k.trim().eq_ignore_ascii_case(key) {
+                    let v = v.split(['#', ';']).next().unwrap_or("");
+                    ans = Some(v.trim().trim_matches('"').to_string());
+                }
+            }
+        }
+        ans
+    }
+
+    fn ref_path(&self, name: &str) -> PathBuf {
+        match self.git_dir.join(name).is_file() {
+            true  => self.git_dir.join(name),
+            false => self.common_dir.join(name),
+        }
+    }
+
+    /* the hash a ref points, following symbolic refs and packed-refs */
+    fn resolve(&self, name: &str, depth: usize) -> Option<Vec<u8>> {
+        if depth > 5 {
+            return None;
+        }
+        if let Some(text) = read_text(&self.ref_path(name)) {
+            return match text.strip_prefix("ref:") {
+                Some(r) => self.resolve(r.trim(), depth + 1),
+                None => unhex(&text),
+            };
+        }
+
+        let packed = fs::read_to_string(self.common_dir.join("packed-refs")).ok()?;
+        packed.lines()
+            .filter(|l| ! l.starts_with('#') && ! l.starts_with('^'))
+            .find_map(|l| match l.split_once(' ') {
+                Some((h, n)) if n == name => unhex(h),
+                _ => None,
+            })
+    }
+
+    /* the branch, or a short hash with the detached flag */
+    fn head(&self) -> Option<(String, bool)> {
+        let text = read_text(&self.git_dir.join("HEAD"))?;
+        if let Some(r) = text.strip_prefix("ref:") {
+            let r = r.trim();
+            return Some((r.strip_prefix("refs/heads/").unwrap_or(r).to_string(), false));
+        }
+
+        /* a rebase detaches HEAD but remembers the branch */
+        for dir in ["rebase-merge", "rebase-apply"] {
+            if let Some(r) = read_text(&self.git_dir.join(dir).join("head-name")) {
+                if let Some(b) = r.strip_prefix("refs/heads/") {
+                    return Some((b.to_string(), false));
+                }
+            }
+        }
+        Some((text.chars().take(7).collect(), true))
+    }
+
+    fn state(&self) -> String {
+        let g = &self.git_dir;
+        let step = |dir: &str, num: &str, end: &str| {
+            match (read_text(&g.join(dir).join(num)), read_text(&g.join(dir).join(end))) {
+                (Some(n), Some(e)) => format!(" {}/{}", n, e),
+                _ => String::new(),
+            }
+        };
+
+        if g.join("rebase-merge").is_dir() {
+            let name = match g.join("rebase-merge/interactive").is_file() {
+                true  => "REBASE-i",
+                false => "REBASE-m",
+            };
+            name.to_string() + &step("rebase-merge", "msgnum", "end")
+        }else if g.join("rebase-apply").is_dir() {
+            let name = if g.join("rebase-apply/rebasing").is_file() {
+                "REBASE"
+            }else if g.join("rebase-apply/applying").is_file() {
+                "AM"
+            }else{
+                "AM/REBASE"
+            };
+            name.to_string() + &step("rebase-apply", "next", "last")
+        }else if g.join("MERGE_HEAD").is_file() {
+            "MERGING".to_string()
+        }else if g.join("CHERRY_PICK_HEAD").is_file() {
+            "CHERRY-PICKING".to_string()
+        }else if g.join("REVERT_HEAD").is_file() {
+            "REVERTING".to_string()
+        }else if g.join("BISECT_LOG").is_file() {
+            "BISECTING".to_string()
+        }else{
+            String::new()
+        }
+    }
+
+    /* refs/remotes/<remote>/<branch> from branch.<name>.remote and .merge */
+    fn upstream(&self, branch: &str) -> Option<String> {
+        let remote = self.config("branch", Some(branch), "remote")?;
+        let merge = self.config("branch", Some(branch), "merge")?;
+        match remote.as_str() {
+            "." => Some(merge),
+            _ => Some(format!("refs/remotes/{}/{}", remote,
+                              merge.strip_prefix("refs/heads/").unwrap_or(&merge))),
+        }
+    }
+
+    fn signature(&self, branch: &str) -> Vec<Option<SystemTime>> {
+        let mut files = vec![self.git_dir.join("HEAD"), self.git_dir.join("index"),
+                             self.common_dir.join("packed-refs"),
+                             self.common_dir.join("config"),
+                             self.common_dir.join("refs/heads").join(branch)];
+        if let Some(u) = self.upstream(branch) {
+            files.push(self.common_dir.join(u));
+        }
+        files.iter().map(|f| modified(f)).collect()
+    }
+
+    fn is_staged(&self, index: &Index, head: Option<&Vec<u8>>) -> bool {
+        let entries: Vec<&index::Entry> = index.entries.iter()
+            .filter(|e| ! e.intent_to_add).collect();
+        if entries.iter().any(|e| e.stage != 0) {
+            return true;
+        }
+
+        let head = match head {
+            Some(h) => h,
+            None => return ! entries.is_empty(), // no commit yet
+        };
+        let mut objects = Objects::new(&self.common_dir.join("objects"), self.hash_len);
+        let tree = match objects.read_commit(head) {
+            Some(c) => c.tree,
+            None => return false,
+        };
+        if index.root_tree.as_ref() == Some(&tree) {
+            return false;
+        }
+
+        let mut files = vec![];
+        if objects.read_tree(&tree, "", &mut files).is_none() {
+            return false;
+        }
+        let files: HashMap<&str, (u32, &Vec<u8>)> = files.iter()
+            .map(|(p, m, h)| (p.as_str(), (*m, h))).collect();
+
+        files.len() != entries.len()
+        || entries.iter().any(|e| files.get(e.path.as_str()) != Some(&(e.mode, &e.hash)))
+    }
+
+    fn is_dirty(&self, index: &Index) -> bool {
+        for e in &index.entries {
+            if e.stage != 0 || e.intent_to_add {
+                return true;
+            }
+            if e.skip_worktree || e.mode == GITLINK {
+                continue;
+            }
+
+            let path = self.work_tree.join(&e.path);
+            let meta = match fs::symlink_metadata(&path) {
+                Ok(m) => m,
+                _ => return true,
+            };
+            let executable = meta.permissions().mode() & 0o111 != 0;
+            let mode = match (meta.file_type().is_symlink(), executable) {
+                (true, _)  => SYMLINK,
+                (_, true)  => 0o100755,
+                (_, false) => 0o100644,
+            };
+            if mode != e.mode || meta.size() as u32 != e.size {
+                return true;
+            }
+            if (meta.mtime() as u32, meta.mtime_nsec() as u32) == e.mtime {
+                continue;
+            }
+            if blob_hash(&path, &meta, self.hash_len).as_ref() != Some(&e.hash) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /* whether a file that is neither tracked nor ignored exists under the directory */
+    fn has_untracked(&self, rel: &str, tracked: &HashSet<&str>, ignore: &mut Ignore) -> bool {
+        let dir = self.work_tree.join(rel);
+        let restore = ignore.len();
+        ignore.read(&dir.join(".gitignore"), rel);
+
+        let mut ans = false;
+        if let Ok(entries) = fs::read_dir(&dir) {
+            for e in entries.flatten() {
+                let name = e.file_name().to_string_lossy().to_string();
+                if name == ".git" {
+                    continue;
+                }
+                let path = format!("{}{}", rel, name);
+                let is_dir = e.file_type().map(|t| t.is_dir()).unwrap_or(false);
+                if tracked.contains(path.as_str()) || ignore.is_ignored(&path, is_dir) {
+                    continue;
+                }
+
+                ans = match is_dir {
+                    true  => self.has_untracked(&(path + "/"), tracked, ignore),
+                    false => true,
+                };
+                if ans {
+                    break;
+                }
+            }
+        }
+
+        ignore.truncate(restore);
+        ans
+    }
+
+    fn ahead_behind(&self, left: &[u8], right: &[u8]) -> Option<(usize, usize)> {
+        let mut objects = Objects::new(&self.common_dir.join("objects"), self.hash_len);
+        let mut flags: HashMap<Vec<u8>, u8> = HashMap::new();
+        let mut times: HashMap<Vec<u8>, i64> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+
+        for (hash, flag) in [(left, 1), (right, 2)] {
+            *flags.entry(hash.to_vec()).or_insert(0) |= flag;
+            let time = objects.read_commit(hash)?.time;
+            times.insert(hash.to_vec(), time);
+            queue.push((time, hash.to_vec()));
+        }
+
+        /* newer commits first until only the common history remains. A commit
+         * on one side may still be reached from the queue if it is not older */
+        while let Some((_, hash)) = queue.pop() {
+            if flags.len() > MAX_COMMITS {
+                return None;
+            }
+            let flag = flags[&hash];
+            for parent in objects.read_commit(&hash)?.parents {
+                let old = *flags.get(&parent).unwrap_or(&0);
+                if old | flag != old {
+                    flags.insert(parent.clone(), old | flag);
+                    let time = objects.read_commit(&parent).map(|c| c.time).unwrap_or(0);
+                    times.insert(parent.clone(), time);
+                    queue.push((time, parent));
+                }
+            }
+
+            if queue.iter().any(|(_, h)| flags[h] != 3) {
+                continue;
+            }
+            let newest = queue.peek().map(|(t, _)| *t).unwrap_or(i64::MIN);
+            if flags.iter().all(|(h, f)| *f == 3 || times[h] > newest) {
+                break;
+            }
+        }
+
+        let ahead = flags.values().filter(|f| **f == 1).count();
+        let behind = flags.values().filter(|f| **f == 2).count();
+        Some((ahead, behind))
+    }
+}
+
+pub fn status(caches: &mut HashMap<PathBuf, Cache>, dir: &Path, query: &Query) -> Option<Status> {
+    let repo = find(dir)?;
+    let (branch, detached) = repo.head()?;
+    let mut ans = Status { branch: branch.clone(), detached,
+                           state: repo.state(), ..Default::default() };
+
+    if ! (query.staged || query.dirty || query.untracked || query.upstream) {
+        return Some(ans);
+    }
+
+    let cache = caches.entry(repo.work_tree.clone()).or_default();
+    let signature = repo.signature(&branch);
+    if cache.signature != signature {
+        *cache = Cache::default();
+        cache.index = index::read(&repo.git_dir.join("index"), repo.hash_len).unwrap_or_default();
+        cache.signature = signature;
+    }
+    let head = repo.resolve("HEAD", 0);
+
+    if query.staged {
+        if cache.staged.is_none() {
+            cache.staged = Some(repo.is_staged(&cache.index, head.as_ref()));
+        }
+        ans.staged = cache.staged == Some(true);
+    }
+    if query.dirty {
+        ans.dirty = repo.is_dirty(&cache.index);
+    }
+    if query.untracked {
+        let tracked = cache.index.entries.iter().map(|e| e.path.as_str()).collect();
+        let mut ignore = Ignore::default();
+        ignore.read(&repo.common_dir.join("info/exclude"), "");
+        ans.untracked = repo.has_untracked("", &tracked, &mut ignore);
+    }
+    if query.upstream && ! detached {
+        if cache.ahead_behind.is_none() {
+            let upstream = repo.upstream(&branch).and_then(|u| repo.resolve(&u, 0));
+            cache.ahead_behind = Some(match (&head, upstream) {
+                (Some(h), Some(u)) => repo.ahead_behind(h, &u),
+                _ => None,
+            });
+        }
+        ans.ahead_behind = cache.ahead_behind.flatten();
+    }
+    Some(ans)
+}
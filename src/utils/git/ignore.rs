@@ -0,0 +1,93 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use crate::utils::glob;
+use std::fs;
+use std::path::Path;
+
+struct Pattern {
+    base: String, // the directory of the .gitignore with a slash
+    glob: String,
+    negative: bool,
+    dir_only: bool,
+    anchored: bool, // matched with the path from the base instead of the name
+}
+
+/* patterns of .gitignore files and info/exclude, the latter ones have priority */
+#[derive(Default)]
+pub struct Ignore {
+    patterns: Vec<Pattern>,
+}
+
+impl Pattern {
+    fn matches(&self, path: &str, is_dir: bool) -> bool {
+        if self.dir_only && ! is_dir {
+            return false;
+        }
+        let rel = match path.strip_prefix(&self.base) {
+            Some(r) => r.to_string(),
+            None => return false,
+        };
+
+        match self.anchored {
+            true  => glob::compare(&rel, &self.glob, false),
+            false => {
+                let name = rel.rsplit('/').next().unwrap_or("").to_string();
+                glob::compare(&name, &self.glob, false)
+            },
+        }
+    }
+}
+
+impl Ignore {
+    pub fn read(&mut self, file: &Path, base: &str) {
+        let text = match fs::read_to_string(file) {
+            Ok(t) => t,
+            _ => return,
+        };
+
+        for line in text.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with("#") {
+                continue;
+            }
+
+            let negative = line.starts_with("!");
+            let mut glob = line.trim_start_matches('!').trim_start_matches('\\');
+            let dir_only = glob.ends_with("/");
+            glob = glob.trim_end_matches('/');
+
+            if let Some(rest) = glob.strip_prefix("**/") {
+                if ! rest.contains('/') {
+                    glob = rest;
+                }
+            }
+            let anchored = glob.contains('/');
+            let glob = glob.trim_start_matches('/').to_string();
+            if glob.is_empty() {
+                continue;
+            }
+
+            self.patterns.push(Pattern {
+                base: base.to_string(), glob,
+                negative, dir_only, anchored,
+            });
+        }
+    }
+
+    /* the number of patterns to restore after leaving a directory */
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.patterns.truncate(len);
+    }
+
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        match self.patterns.iter().rev().find(|p| p.matches(path, is_dir)) {
+            Some(p) => ! p.negative,
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub path: String,
+    pub mode: u32,
+    pub hash: Vec<u8>,
+    pub mtime: (u32, u32),
+    pub size: u32,
+    pub stage: u16,
+    pub skip_worktree: bool,
+    pub intent_to_add: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Index {
+    pub entries: Vec<Entry>,
+    pub root_tree: Option<Vec<u8>>, // from the cache tree extension
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Option<u32> {
+    let b = data.get(pos..pos+4)?;
+    Some(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn u16_at(data: &[u8], pos: usize) -> Option<u16> {
+    let b = data.get(pos..pos+2)?;
+    Some(u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn until_nul(data: &[u8], pos: usize) -> Option<&[u8]> {
+    let len = data.get(pos..)?.iter().position(|b| *b == 0)?;
+    Some(&data[pos..pos+len])
+}
+
+/* the length of the common part with the previous path in version 4 */
+fn varint(data: &[u8], pos: &mut usize) -> Option<usize> {
+    let mut c = *data.get(*pos)?;
+    *pos += 1;
+    let mut ans = (c & 0x7f) as usize;
+    while c & 0x80 != 0 {
+        c = *data.get(*pos)?;
+        *pos += 1;
+        ans = ((ans + 1) << 7) | (c & 0x7f) as usize;
+    }
+    Some(ans)
+}
+
+fn read_entry(data: &[u8], pos: &mut usize, version: u32,
+              hash_len: usize, prev: &str) -> Option<Entry> {
+    let start = *pos;
+    let flags = u16_at(data, start + 40 + hash_len)?;
+    let mut p = start + 42 + hash_len;
+    let mut extended = 0;
+    if version >= 3 && flags & 0x4000 != 0 {
+        extended = u16_at(data, p)?;
+        p += 2;
+    }
+
+    let path = match version {
+        4 => {
+            let strip = varint(data, &mut p)?;
+            let suffix = until_nul(data, p)?;
+            p += suffix.len() + 1;
+            let keep = prev.len().checked_sub(strip)?;
+            prev[..keep].to_string() + &String::from_utf8_lossy(suffix)
+        },
+        _ => {
+            let name = until_nul(data, p)?;
+            p = start + ((p - start + name.len() + 8) & !7);
+            String::from_utf8_lossy(name).to_string()
+        },
+    };
+    *pos = p;
+
+    Some(Entry {
+        path,
+        mode: u32_at(data, start + 24)?,
+        hash: data.get(start+40..start+40+hash_len)?.to_vec(),
+        mtime: (u32_at(data, start + 8)?, u32_at(data, start + 12)?),
+        size: u32_at(data, start + 36)?,
+        stage: (flags >> 12) & 0x3,
+        skip_worktree: extended & 0x4000 != 0,
+        intent_to_add: extended & 0x2000 != 0,
+    })
+}
+
+/* the hash of the whole tree in the TREE extension, if it is valid */
+fn root_tree(ext: &[u8], hash_len: usize) -> Option<Vec<u8>> {
+    let path = until_nul(ext, 0)?;
+    if ! path.is_empty() {
+        return None;
+    }
+    let nl = ext.iter().position(|b| *b == b'\n')?;
+    let counts = String::from_utf8_lossy(&ext[1..nl]).to_string();
+    if counts.starts_with("-") {
+        return None;
+    }
+    ext.get(nl+1..nl+1+hash_len).map(|h| h.to_vec())
+}
+
+pub fn read(path: &Path, hash_len: usize) -> Option<Index> {
+    let data = fs::read(path).ok()?;
+    if data.get(0..4)? != b"DIRC" {
+        return None;
+    }
+    let version = u32_at(&data, 4)?;
+    let num = u32_at(&data, 8)?;
+
+    let mut ans = Index::default();
+    let mut pos = 12;
+    for _ in 0..num {
+        let prev = ans.entries.last().map(|e| e.path.clone()).unwrap_or_default();
+        let entry = read_entry(&data, &mut pos, version, hash_len, &prev)?;
+        ans.entries.push(entry);
+    }
+
+    let end = data.len().saturating_sub(hash_len);
+    while pos + 8 <= end {
+        let size = u32_at(&data, pos + 4)? as usize;
+        let ext = data.get(pos+8..pos+8+size)?;
+        if &data[pos..pos+4] == b"TREE" {
+            ans.root_tree = root_tree(ext, hash_len);
+        }
+        pos += 8 + size;
+    }
+    Some(ans)
+}
@@ -0,0 +1,301 @@
+//SPDX-FileCopyrightText: 2024 Ryuichi Ueda <ryuichiueda@gmail.com>
+//SPDX-License-Identifier: BSD-3-Clause
+
+use flate2::bufread::ZlibDecoder;
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use super::hex;
+
+const COMMIT: u8 = 1;
+const TREE: u8 = 2;
+const OFS_DELTA: u8 = 6;
+const REF_DELTA: u8 = 7;
+const TYPES: [&str; 5] = ["", "commit", "tree", "blob", "tag"];
+
+struct Pack {
+    idx: Vec<u8>,
+    path: PathBuf,
+}
+
+pub struct Commit {
+    pub tree: Vec<u8>,
+    pub parents: Vec<Vec<u8>>,
+    pub time: i64,
+}
+
+/* reader of loose objects and packs without spawning git */
+pub struct Objects {
+    dirs: Vec<PathBuf>, // the object directory and its alternates
+    packs: Vec<Pack>,
+    hash_len: usize,
+    packed: HashMap<(usize, u64), (u8, Vec<u8>)>, // bases of deltas
+}
+
+fn read_packs(dir: &Path) -> Vec<Pack> {
+    let mut ans = vec![];
+    let entries = match fs::read_dir(dir.join("pack")) {
+        Ok(es) => es,
+        _ => return ans,
+    };
+
+    for e in entries.flatten() {
+        let path = e.path();
+        if path.extension().map(|x| x != "idx").unwrap_or(true) {
+            continue;
+        }
+        match fs::read(&path) {
+            Ok(idx) if idx.starts_with(b"\xfftOc") => {
+                ans.push(Pack{ idx, path: path.with_extension("pack") });
+            },
+            _ => {},
+        }
+    }
+    ans
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Option<u32> {
+    let b = data.get(pos..pos+4)?;
+    Some(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn read_byte(r: &mut impl Read) -> Option<u8> {
+    let mut b = [0u8; 1];
+    r.read_exact(&mut b).ok()?;
+    Some(b[0])
+}
+
+fn inflate(r: impl BufRead, size: usize) -> Option<Vec<u8>> {
+    let mut ans = Vec::with_capacity(size);
+    ZlibDecoder::new(r).take(size as u64).read_to_end(&mut ans).ok()?;
+    Some(ans)
+}
+
+fn delta_size(delta: &[u8], pos: &mut usize) -> Option<usize> {
+    let mut ans = 0;
+    let mut shift = 0;
+    loop {
+        let c = *delta.get(*pos)?;
+        *pos += 1;
+        ans |= ((c & 0x7f) as usize) << shift;
+        shift += 7;
+        if c & 0x80 == 0 {
+            return Some(ans);
+        }
+    }
+}
+
+fn apply_delta(base: &[u8], delta: &[u8]) -> Option<Vec<u8>> {
+    let mut pos = 0;
+    delta_size(delta, &mut pos)?;
+    let size = delta_size(delta, &mut pos)?;
+    let mut ans = Vec::with_capacity(size);
+
+    while pos < delta.len() {
+        let op = delta[pos];
+        pos += 1;
+        if op & 0x80 == 0 {
+            let len = op as usize;
+            if len == 0 {
+                return None;
+            }
+            ans.extend_from_slice(delta.get(pos..pos+len)?);
+            pos += len;
+            continue;
+        }
+
+        let mut offset = 0;
+        let mut len = 0;
+        for i in 0..7 {
+            if op & (1 << i) == 0 {
+                continue;
+            }
+            let b = *delta.get(pos)? as usize;
+            pos += 1;
+            match i {
+                0..=3 => offset |= b << (8*i),
+                _     => len |= b << (8*(i-4)),
+            }
+        }
+        if len == 0 {
+            len = 0x10000;
+        }
+        ans.extend_from_slice(base.get(offset..offset+len)?);
+    }
+    Some(ans)
+}
+
+impl Objects {
+    pub fn new(dir: &Path, hash_len: usize) -> Objects {
+        let mut dirs = vec![dir.to_path_buf()];
+        if let Ok(s) = fs::read_to_string(dir.join("info/alternates")) {
+            dirs.extend(s.lines().filter(|l| ! l.is_empty() && ! l.starts_with("#"))
+                        .map(|l| dir.join(l)));
+        }
+        let packs = dirs.iter().flat_map(|d| read_packs(d)).collect();
+
+        Objects { dirs, packs, hash_len, packed: HashMap::new() }
+    }
+
+    fn read_loose(&self, hash: &[u8]) -> Option<(u8, Vec<u8>)> {
+        let name = hex(hash);
+        for dir in &self.dirs {
+            let file = match File::open(dir.join(&name[..2]).join(&name[2..])) {
+                Ok(f) => f,
+                _ => continue,
+            };
+            let mut data = vec![];
+            ZlibDecoder::new(BufReader::new(file)).read_to_end(&mut data).ok()?;
+            let nul = data.iter().position(|b| *b == 0)?;
+            let header = String::from_utf8_lossy(&data[..nul]).to_string();
+            let kind = header.split(' ').next().unwrap_or("");
+            let kind = TYPES.iter().position(|t| *t == kind)? as u8;
+            return Some((kind, data[nul+1..].to_vec()));
+        }
+        None
+    }
+
+    /* the offset in the pack from the version 2 index */
+    fn find_in_pack(&self, pack: &Pack, hash: &[u8]) -> Option<u64> {
+        let hl = self.hash_len;
+        let idx = &pack.idx;
+        let first = hash[0] as usize;
+        let lo = match first {
+            0 => 0,
+            _ => u32_at(idx, 8 + (first-1)*4)? as usize,
+        };
+        let hi = u32_at(idx, 8 + first*4)? as usize;
+        let num = u32_at(idx, 8 + 255*4)? as usize;
+        let names = 8 + 256*4;
+
+        let (mut lo, mut hi) = (lo, hi);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let name = idx.get(names + mid*hl..names + (mid+1)*hl)?;
+            match name.cmp(hash) {
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+                std::cmp::Ordering::Equal => {
+                    let offsets = names + num*hl + num*4;
+                    let ofs = u32_at(idx, offsets + mid*4)?;
+                    if ofs & 0x80000000 == 0 {
+                        return Some(ofs as u64);
+                    }
+                    let large = offsets + num*4 + (ofs & 0x7fffffff) as usize * 8;
+                    let b = idx.get(large..large+8)?;
+                    return Some(u64::from_be_bytes(b.try_into().ok()?));
+                },
+            }
+        }
+        None
+    }
+
+    fn read_packed(&mut self, pack: usize, offset: u64) -> Option<(u8, Vec<u8>)> {
+        if let Some(obj) = self.packed.get(&(pack, offset)) {
+            return Some(obj.clone());
+        }
+
+        let mut file = File::open(&self.packs[pack].path).ok()?;
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        let mut r = BufReader::new(file);
+
+        let mut c = read_byte(&mut r)?;
+        let kind = (c >> 4) & 7;
+        let mut size = (c & 15) as usize;
+        let mut shift = 4;
+        while c & 0x80 != 0 {
+            c = read_byte(&mut r)?;
+            size |= ((c & 0x7f) as usize) << shift;
+            shift += 7;
+        }
+
+        let ans = match kind {
+            OFS_DELTA => {
+                c = read_byte(&mut r)?;
+                let mut ofs = (c & 0x7f) as u64;
+                while c & 0x80 != 0 {
+                    c = read_byte(&mut r)?;
+                    ofs = ((ofs + 1) << 7) | (c & 0x7f) as u64;
+                }
+                let delta = inflate(r, size)?;
+                let (kind, base) = self.read_packed(pack, offset.checked_sub(ofs)?)?;
+                (kind, apply_delta(&base, &delta)?)
+            },
+            REF_DELTA => {
+                let mut base = vec![0u8; self.hash_len];
+                r.read_exact(&mut base).ok()?;
+                let delta = inflate(r, size)?;
+                let (kind, base) = self.read(&base)?;
+                (kind, apply_delta(&base, &delta)?)
+            },
+            _ => (kind, inflate(r, size)?),
+        };
+
+        if self.packed.len() < 256 {
+            self.packed.insert((pack, offset), ans.clone());
+        }
+        Some(ans)
+    }
+
+    fn read(&mut self, hash: &[u8]) -> Option<(u8, Vec<u8>)> {
+        if let Some(obj) = self.read_loose(hash) {
+            return Some(obj);
+        }
+        for i in 0..self.packs.len() {
+            if let Some(offset) = self.find_in_pack(&self.packs[i], hash) {
+                return self.read_packed(i, offset);
+            }
+        }
+        None
+    }
+
+    pub fn read_commit(&mut self, hash: &[u8]) -> Option<Commit> {
+        let (kind, data) = self.read(hash)?;
+        if kind != COMMIT {
+            return None;
+        }
+
+        let text = String::from_utf8_lossy(&data).to_string();
+        let mut ans = Commit { tree: vec![], parents: vec![], time: 0 };
+        for line in text.lines().take_while(|l| ! l.is_empty()) {
+            match line.split_once(' ') {
+                Some(("tree", h)) => ans.tree = super::unhex(h)?,
+                Some(("parent", h)) => ans.parents.push(super::unhex(h)?),
+                Some(("committer", c)) => {
+                    let mut words = c.rsplit(' ');
+                    words.next();
+                    ans.time = words.next()?.parse().unwrap_or(0);
+                },
+                _ => {},
+            }
+        }
+        Some(ans)
+    }
+
+    /* the entries of a tree and its subtrees as (path, mode, hash) */
+    pub fn read_tree(&mut self, hash: &[u8], prefix: &str,
+                     ans: &mut Vec<(String, u32, Vec<u8>)>) -> Option<()> {
+        let (kind, data) = self.read(hash)?;
+        if kind != TREE {
+            return None;
+        }
+
+        let mut pos = 0;
+        while pos < data.len() {
+            let sp = pos + data[pos..].iter().position(|b| *b == b' ')?;
+            let nul = sp + data[sp..].iter().position(|b| *b == 0)?;
+            let mode = u32::from_str_radix(&String::from_utf8_lossy(&data[pos..sp]), 8).ok()?;
+            let name = prefix.to_string() + &String::from_utf8_lossy(&data[sp+1..nul]);
+            let h = data.get(nul+1..nul+1+self.hash_len)?.to_vec();
+            pos = nul + 1 + self.hash_len;
+
+            match mode {
+                0o40000 => self.read_tree(&h, &(name + "/"), ans)?,
+                _ => ans.push((name, mode, h)),
+            }
+        }
+        Some(())
+    }
+}
@@ -277,11 +277,13 @@ fn scanner_bracket(remaining: &str) -> (usize, Wildcard) {
         len = 2;
     }
 
+    let mut range = false;
+
     for c in remaining[len..].chars() {
         len += c.len_utf8();
 
         if escaped {
-            chars.push(c); 
+            push_bracket_char(&mut chars, c, &mut range);
             escaped = false;
             continue;
         }
@@ -291,18 +293,39 @@ fn scanner_bracket(remaining: &str) -> (usize, Wildcard) {
         }
 
         if c == ']' {
+            if range {
+                chars.push('-');
+            }
             match not {
                 false => return (len, Wildcard::OneOf(chars) ),
                 true  => return (len, Wildcard::NotOneOf(chars) ),
             }
         }
 
-        chars.push(c);
+        if c == '-' && ! range && ! chars.is_empty() {
+            range = true;
+            continue;
+        }
+
+        push_bracket_char(&mut chars, c, &mut range);
     }
 
     (0, Wildcard::OneOf(vec![]) )
 }
 
+fn push_bracket_char(chars: &mut Vec<char>, c: char, range: &mut bool) {
+    if ! *range {
+        chars.push(c);
+        return;
+    }
+
+    *range = false;
+    let from = chars.pop().unwrap();
+    if from <= c {
+        chars.extend(from..=c);
+    }
+}
+
 fn scanner_ext_paren(remaining: &str) -> (usize, Option<Wildcard>) {
     let prefix = match remaining.chars().nth(0) {
         Some(c) => c, 
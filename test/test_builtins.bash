@@ -22,6 +22,18 @@ res=$($com <<< 'rm -f /tmp/link; cd /tmp; mkdir -p hoge; ln -s hoge link; cd lin
 [ "$res" = "/tmp/link
 /private/tmp/hoge" ] || err $LINENO
 
+res=$($com <<< 'mkdir -p /tmp/hoge/fuga; CDPATH=/etc:/tmp/hoge; cd /; cd fuga; pwd; cd /; cd tmp; pwd')
+[ "$res" = "/tmp/hoge/fuga
+/tmp/hoge/fuga
+/tmp" ] || err $LINENO
+
+res=$($com <<< 'mkdir -p /tmp/cdpath/x /tmp/cdpath/d/x; cd /tmp/cdpath; CDPATH=:/tmp/cdpath/d; cd x; pwd; cd ..
+CDPATH=/tmp/cdpath/d:; cd x; pwd; cd /tmp/cdpath; cd ./x; pwd; rm -rf /tmp/cdpath')
+[ "$res" = "/tmp/cdpath/x
+/tmp/cdpath/d/x
+/tmp/cdpath/d/x
+/tmp/cdpath/x" ] || err $LINENO
+
 res=$($com <<< 'pwd -a 2>/tmp/rusty_bash; cat /tmp/rusty_bash')
 [ "$res" = "sush: pwd: -a: invalid option
 pwd: usage: pwd [-LP]" ] || err $LINENO
@@ -136,5 +148,321 @@ echo @(a)')
 [ "$?" == "2" ] || err $LINENO
 [ "$res" == "" ] || err $LINENO
 
+# declare command
+
+res=$($com <<< 'declare -A A=([a]=1 [b]="x y"); A[c]=3; k=b; echo ${A[a]} ${A[$k]} ${#A[@]} ${!A[@]}')
+[ "$res" == "1 x y 3 a b c" ] || err $LINENO
+
+res=$($com <<< 'declare -A A=([a]=1 [b]="x y"); unset "A[a]"; declare -p A')
+[ "$res" == 'declare -A A=([b]="x y" )' ] || err $LINENO
+
+res=$($com <<< 'declare -A e; declare -p e')
+[ "$res" == 'declare -A e' ] || err $LINENO
+
+res=$($com <<< 'a=(1 2 3); unset "a[1]"; echo ${#a[@]} ${!a[@]} ${a[@]}; declare -p a')
+[ "$res" == '2 0 2 1 3
+declare -a a=([0]="1" [2]="3")' ] || err $LINENO
+
+res=$($com <<< 'declare -i n=1+2; echo $n; n=3*4; echo $n; declare -p n')
+[ "$res" == '3
+12
+declare -i n="12"' ] || err $LINENO
+
+res=$($com <<< 'declare -r R=5; R=6; echo $R' 2>&1)
+[ "$res" == "sush: R: readonly variable" ] || err $LINENO
+
+res=$($com <<< 'declare -r R=5; R=6; echo NG
+echo $? $R')
+[ "$res" == "1 5" ] || err $LINENO
+
+res=$($com <<< 'declare -r R=5; unset R')
+[ "$?" == "1" ] || err $LINENO
+
+res=$($com <<< 'declare -l L=ABC; declare -u U=abc; echo $L $U; L=XyZ; echo $L')
+[ "$res" == "abc ABC
+xyz" ] || err $LINENO
+
+res=$($com <<< 'x=1; declare -n r=x; echo $r; r=7; echo $x; declare -p r')
+[ "$res" == '1
+7
+declare -n r="x"' ] || err $LINENO
+
+res=$($com <<< 'declare -n r=x; x=5; echo ${!r} $r; y=x; echo ${!y}')
+[ "$res" == 'x 5
+5' ] || err $LINENO
+
+res=$($com <<< 'declare -x SUSH_EX=hello; bash -c "echo \$SUSH_EX"')
+[ "$res" == "hello" ] || err $LINENO
+
+res=$($com <<< 'f(){ echo hi; }; g(){ :; }; declare -F')
+[ "$res" == "declare -f f
+declare -f g" ] || err $LINENO
+
+res=$($com <<< 'f(){ declare -i y=2+2; local -a a=(x y); echo $y ${a[1]}; }; f; echo "y=$y"')
+[ "$res" == "4 y
+y=" ] || err $LINENO
+
+res=$($com <<< 'declare -a B=(1 2); eval "$(declare -p B)"; typeset -p B; echo ${B@a}')
+[ "$res" == 'declare -a B=([0]="1" [1]="2")
+a' ] || err $LINENO
+
+res=$($com <<< 's='"'"'a "b" $c\d'"'"'; a=(1 "x y"); unset "a[0]"; declare -A m=([k]=v ["x y"]=z)
+d="$(declare -p s a m)"; unset s a m; eval "$d"; declare -p s a m; [ "$d" == "$(declare -p s a m)" ]')
+[ "$?" == "0" ] || err $LINENO
+[ "$res" == 'declare -- s="a \"b\" \$c\\d"
+declare -a a=([1]="x y")
+declare -A m=(["x y"]="z" [k]="v" )' ] || err $LINENO
+
+# echo, printf and test command
+
+res=$($com <<< 'echo x > /dev/full; echo $?; printf x > /dev/full; echo $?' 2>&1)
+[ "$res" == "sush: echo: write error: No space left on device
+1
+sush: printf: write error: No space left on device
+1" ] || err $LINENO
+
+res=$($com <<< 'echo -n a; echo b; echo -e "x\ty\c"; echo; echo -E "a\tb"; shopt -s xpg_echo; echo "p\tq"')
+[ "$res" == "ab
+x	y
+a\tb
+p	q" ] || err $LINENO
+
+res=$($com <<< 'printf "%s-%d|%5.2f|%-4s|%x %o\n" a 42 3.14159 ab 255 8')
+[ "$res" == "a-42| 3.14|ab  |ff 10" ] || err $LINENO
+
+res=$($com <<< 'printf "%s %s\n" a b c; printf "[%b]\n" "x\ty" "a\cb" z')
+[ "$res" == "a b
+c 
+[x	y]
+[a" ] || err $LINENO
+
+res=$($com <<< 'printf "%q|%q|%q\n" "a b" "" "a
+b"; printf -v V "%03d" 7; echo $V; printf "%(%Y)T %*d|%.*f\n" 15000000 3 1 2 1.234')
+[ "$res" == "a\ b|''|\$'a\nb'
+007
+1970   1|1.23" ] || err $LINENO
+
+res=$($com <<< 'printf "%d\n" abc')
+[ "$?" == "1" ] || err $LINENO
+
+res=$($com <<< '[ 1 -lt 2 -a 3 -gt 2 ] && echo and; [ ! 1 -eq 2 -o x = y ] && echo or; test "(" x ")" && echo par; [ ]; echo $?')
+[ "$res" == "and
+or
+par
+1" ] || err $LINENO
+
+res=$($com <<< '[ x -eq 1 ]')
+[ "$?" == "2" ] || err $LINENO
+
+res=$($com <<< '[ a = a')
+[ "$?" == "2" ] || err $LINENO
+
+# export, readonly, shift, getopts and exec
+
+res=$($com <<< 'export A=1 ; B=2 ; export B ; bash -c "echo \$A\$B" ; export -n B ; bash -c "echo \$A\$B"')
+[ "$res" == "12
+1" ] || err $LINENO
+
+res=$($com <<< 'export C="x y" D ; export -p C D')
+[ "$res" == 'declare -x C="x y"
+declare -x D' ] || err $LINENO
+
+res=$($com <<< 'f () { local L=1 ; export L G=2 ; bash -c "echo \$L\$G" ; } ; f ; echo $L$G')
+[ "$res" == "12
+2" ] || err $LINENO
+
+res=$($com <<< 'readonly R=1 ; R=2
+echo $R ; unset R ; readonly -p R' 2> /dev/null)
+[ "$res" == '1
+declare -r R="1"' ] || err $LINENO
+
+res=$($com <<< 'set a b c d ; shift ; echo $0 $@ ; shift 2 ; echo $@ ; shift 2 ; echo $? $@')
+[ "$res" == "$com b c d
+d
+1 d" ] || err $LINENO
+
+res=$($com <<< 'while getopts "ab:c" o -a -b v -cbX r ; do echo "$o ${OPTARG-unset} $OPTIND" ; done ; echo $o $OPTIND')
+[ "$res" == "a unset 2
+b v 4
+c unset 4
+b X 5
+? 5" ] || err $LINENO
+
+res=$($com <<< 'while getopts ":x:" o -z -x ; do echo "$o $OPTARG" ; done')
+[ "$res" == "? z
+: x" ] || err $LINENO
+
+res=$($com <<< 'set -- -q -- -w ; while getopts q o ; do echo $o ; done ; echo $OPTIND')
+[ "$res" == "q
+3" ] || err $LINENO
+
+res=$($com <<< 'set -- -ab ; getopts ab o ; OPTIND=1 ; set -- -x ; getopts x o ; echo $o $OPTIND')
+[ "$res" == "x 2" ] || err $LINENO
+
+res=$($com <<< 'exec 3> /tmp/rusty_bash_exec ; echo a >&3 ; echo b ; cat /tmp/rusty_bash_exec')
+[ "$res" == "b
+a" ] || err $LINENO
+
+res=$($com <<< 'X=1 exec bash -c "echo \$X" ; echo NG')
+[ "$res" == "1" ] || err $LINENO
+
+res=$($com <<< 'exec nonexistent_command ; echo NG')
+[ "$?" == "127" ] || err $LINENO
+[ "$res" == "" ] || err $LINENO
+
+# trap command
+
+res=$($com <<< 'trap "echo bye" EXIT; echo hi')
+[ "$res" == "hi
+bye" ] || err $LINENO
+
+res=$($com <<< 'trap "echo bye; exit 3" EXIT; true')
+[ "$?" == "3" ] || err $LINENO
+
+res=$($com <<< 'trap "echo trapped" TERM; kill -TERM $$; echo after')
+[ "$res" == "trapped
+after" ] || err $LINENO
+
+res=$($com <<< 'trap "" TERM; kill -TERM $$; echo survived')
+[ "$res" == "survived" ] || err $LINENO
+
+res=$($com <<< 'trap "echo a" INT TERM; trap "" HUP; trap - TERM; trap -p')
+[ "$res" == "trap -- '' SIGHUP
+trap -- 'echo a' SIGINT" ] || err $LINENO
+
+res=$($com <<< 'trap "echo err \$?" ERR; false; false && true; echo done')
+[ "$res" == "err 1
+done" ] || err $LINENO
+
+res=$($com <<< 'f(){ trap "echo ret" RETURN; echo in; }; g(){ echo g; }; f; trap -p; trap - RETURN; g')
+[ "$res" == "in
+ret
+trap -- 'echo ret' RETURN
+g" ] || err $LINENO
+
+res=$($com <<< 'trap "echo ret" RETURN; f(){ echo in; }; f')
+[ "$res" == "in" ] || err $LINENO
+
+res=$($com <<< 'trap "echo \$BASH_COMMAND" DEBUG; echo x')
+[ "$res" == "echo x
+x" ] || err $LINENO
+
+res=$($com <<< 'trap "echo parent" EXIT; (echo sub)')
+[ "$res" == "sub
+parent" ] || err $LINENO
+
+res=$($com <<< "trap 'echo x' INT; trap '' QUIT; ( trap -p ); ( trap 'echo u' USR1; trap -p )")
+[ "$res" == "trap -- 'echo x' SIGINT
+trap -- '' SIGQUIT
+trap -- '' SIGQUIT
+trap -- 'echo u' SIGUSR1" ] || err $LINENO
+
+res=$($com <<< 'trap "echo a" FOO')
+[ "$?" == "1" ] || err $LINENO
+
+res=$($com <<< 'printf "echo a\necho b\n" > /tmp/$$-h ; HISTFILE=/tmp/$$-h ; history -r ; fc -ln ; fc -s a=c 1 2> /dev/null ; rm /tmp/$$-h')
+[ "$res" == "$(printf '\t echo a\n\t echo b\nc')" ] || err $LINENO
+
+res=$($com <<< 'echo !$ !!')
+[ "$res" == '!$ !!' ] || err $LINENO
+
+res=$($com <<< 'history -s echo a; history -s echo b; history -s echo c; history -d 2; history')
+[ "$res" == "$(printf '    1  echo a\n    2  echo c')" ] || err $LINENO
+
+res=$($com <<< 'history -s a; history -s b; history -c; history -s c; history')
+[ "$res" == "    1  c" ] || err $LINENO
+
+res=$($com <<< 'history -s echo a; history -d 2')
+[ "$?" == "1" ] || err $LINENO
+
+res=$($com <<< 'history -s echo abc; history -p "!!:s/b/x/" "!\$" x')
+[ "$res" == "$(printf 'echo axc\nabc\nx')" ] || err $LINENO
+
+res=$($com <<< $'set -o history\necho a\nhistory -p "!!"\nhistory -p "!!"\nhistory | wc -l')
+[ "$res" == "a
+echo a
+echo a
+2" ] || err $LINENO
+
+res=$($com <<< 'HISTFILE=/tmp/$$-h; history -s a; history -s b; history -w; history -c; history -r; history -s c; history -a; history -c; history -r; history; rm /tmp/$$-h')
+[ "$res" == "$(printf '    1  a\n    2  b\n    3  c')" ] || err $LINENO
+
+res=$($com <<< 'HISTFILE=/tmp/$$-h; HISTFILESIZE=2; history -s a; history -s b; history -s c; history -w; cat /tmp/$$-h; rm /tmp/$$-h')
+[ "$res" == "$(printf 'b\nc')" ] || err $LINENO
+
+res=$($com <<< 'HISTFILE=/tmp/$$-h; HISTTIMEFORMAT="%s "; printf "#100\necho a\n" > $HISTFILE; history -r; history; rm /tmp/$$-h')
+[ "$res" == "    1  100 echo a" ] || err $LINENO
+
+res=$($com <<< 'HISTSIZE=2; history -s a; history -s b; history -s c; history')
+[ "$res" == "$(printf '    1  b\n    2  c')" ] || err $LINENO
+
+### BIND ###
+
+res=$($com <<< 'bind '"'"'"\C-x\C-t": kill-line'"'"'; bind -p | grep kill-line')
+[ "$res" == "$(printf '"\\C-k": kill-line\n"\\C-x\\C-t": kill-line')" ] || err $LINENO
+
+res=$($com <<< 'bind -r "\C-k"; bind -p | grep kill-line')
+[ "$res" == "# kill-line (not bound)" ] || err $LINENO
+
+res=$($com <<< 'bind -l | grep -c "^edit-and-execute-command$"')
+[ "$res" == "1" ] || err $LINENO
+
+res=$($com <<< 'bind -x '"'"'"\C-xa": echo hi'"'"'; bind -X')
+[ "$res" == '"\C-xa": "echo hi"' ] || err $LINENO
+
+res=$($com <<< 'bind "set bell-style none"; bind -v | grep bell-style')
+[ "$res" == "set bell-style none" ] || err $LINENO
+
+res=$($com <<< 'bind "set completion-fuzzy on"; bind -v | grep fuzzy; shopt nocaseglob')
+[ "$res" == "set completion-fuzzy on
+nocaseglob      off" ] || err $LINENO
+
+res=$($com <<< 'bind "\C-x: no-such-function"')
+[ "$?" == "1" ] || err $LINENO
+
+### COMPLETE ###
+
+res=$($com <<< 'opts="--foo --bar"; compgen -W '"'"'$opts --baz'"'"' -- --b')
+[ "$res" = "--bar
+--baz" ] || err $LINENO
+
+res=$($com <<< 'compgen -W "a.c b.o c.c" -X "*.o" -P "<" -S ">"')
+[ "$res" = "<a.c>
+<c.c>" ] || err $LINENO
+
+res=$($com <<< 'compgen -W "a.c b.o c.c" -X "!*.o"')
+[ "$res" = "b.o" ] || err $LINENO
+
+res=$($com <<< 'compgen -W "ab x" -X "&" ab')
+[ "$?" = "1" ] || err $LINENO
+
+res=$($com <<< 'compgen -A shopt extg; compgen -A signal SIGKIL; compgen -k whil')
+[ "$res" = "extglob
+SIGKILL
+while" ] || err $LINENO
+
+res=$($com <<< 'f () { COMPREPLY=(x y); }; g () { echo z; }; compgen -F f; compgen -C g')
+[ "$res" = "x
+y
+z" ] || err $LINENO
+
+res=$($com <<< 'complete -o nospace -F _f a b; complete -A signal -W "x y" -P p c; complete -D -F _g; complete')
+[ "$res" = "complete -F _g -D
+complete -o nospace -F _f a
+complete -o nospace -F _f b
+complete -A signal -W 'x y' -P 'p' c" ] || err $LINENO
+
+res=$($com <<< 'complete -d cd; complete -r cd; complete -p cd')
+[ "$?" = "1" ] || err $LINENO
+
+res=$($com <<< 'complete -A nosuch a')
+[ "$?" = "2" ] || err $LINENO
+
+res=$($com <<< 'complete -F _f a; compopt -o nospace -o filenames a; compopt +o filenames a; compopt a')
+[ "$res" = "compopt +o bashdefault +o default +o dirnames +o filenames +o noquote +o nosort -o nospace +o plusdirs a" ] || err $LINENO
+
+res=$($com <<< 'compopt -o nospace')
+[ "$?" = "1" ] || err $LINENO
+
 echo $0 >> ./ok
 
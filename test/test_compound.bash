@@ -139,6 +139,10 @@ res=$($com <<< 'function f () { local A=BBB ; echo $A ; } ; echo $A')
 res=$($com <<< 'function f () { local A=( a b c ) ; echo ${A[1]}; } ; f')
 [ "$res" = b ] || err $LINENO
 
+res=$($com <<< 'function f () { eval "local A=1" ; echo $A ; } ; A=0 ; f ; echo $A')
+[ "$res" = "1
+0" ] || err $LINENO
+
 res=$($com <<< 'function f () { return; echo NG; } ; f')
 [ "$res" = "" ] || err $LINENO
 
@@ -165,6 +169,71 @@ res=$($com <<< 'touch /tmp/rusty_bash_x ; while [ -f /tmp/rusty_bash_x ] ; do ec
 [ "$res" == "wait
 wait" ] || err $LINENO
 
+### UNTIL TEST ###
+
+res=$($com <<< 'i=0 ; until [ $i -ge 3 ] ; do echo $i ; i=$((i+1)) ; done')
+[ "$res" == "0
+1
+2" ] || err $LINENO
+
+res=$($com <<< 'until true ; do echo do not come here ; done')
+[ "$?" == 0 ] || err $LINENO
+[ "$res" == "" ] || err $LINENO
+
+### CONTINUE TEST ###
+
+res=$($com <<< 'for i in 1 2 3 ; do [ $i = 2 ] && continue ; echo $i ; done')
+[ "$res" == "1
+3" ] || err $LINENO
+
+res=$($com <<< 'for i in 1 2 ; do for j in a b c ; do [ $j = b ] && continue 2 ; echo $i$j ; done ; echo NG ; done')
+[ "$res" == "1a
+2a" ] || err $LINENO
+
+res=$($com <<< 'i=0 ; while [ $i -lt 4 ] ; do i=$((i+1)) ; [ $i = 2 ] && continue ; echo $i ; done')
+[ "$res" == "1
+3
+4" ] || err $LINENO
+
+res=$($com <<< 'for ((i=0;i<3;i++)) ; do [ $i = 1 ] && continue ; echo $i ; done')
+[ "$res" == "0
+2" ] || err $LINENO
+
+res=$($com <<< 'continue ; echo $?' 2> /dev/null)
+[ "$res" == "0" ] || err $LINENO
+
+res=$($com <<< 'for i in 1 2 ; do continue ; ((y++)) ; done ; echo y=$y')
+[ "$res" == "y=" ] || err $LINENO
+
+res=$($com <<< 'for i in 1 2 3 ; do if [ $i = 2 ] ; then continue ; fi ; ((x++)) ; done ; echo $x')
+[ "$res" == "2" ] || err $LINENO
+
+res=$($com <<< 'for i in 1 2 ; do continue ; [[ a ]] && echo NG ; done ; echo ok')
+[ "$res" == "ok" ] || err $LINENO
+
+res=$($com <<< 'for i in 1 2 ; do continue ; { echo NG ; } ; done ; echo ok')
+[ "$res" == "ok" ] || err $LINENO
+
+res=$($com <<< 'for i in 1 2 ; do break ; ((y++)) ; done ; echo y=$y')
+[ "$res" == "y=" ] || err $LINENO
+
+### SELECT TEST ###
+
+res=$($com <<< 'printf "2\n\n9\n" | { PS3="> " ; select a in x yy zzz ; do echo "[$a][$REPLY]" ; done ; echo $? ; }' 2>&1)
+[ "$res" == "1) x
+2) yy
+3) zzz
+> [yy][2]
+> 1) x
+2) yy
+3) zzz
+> [][9]
+> 
+1" ] || err $LINENO
+
+res=$($com <<< 'set p q ; echo 2 | { select a ; do echo $a ; break ; done ; }' 2> /dev/null)
+[ "$res" == "q" ] || err $LINENO
+
 ### FOR TEST ###
 
 res=$($com <<< 'set a b c ; for x ; do echo $x ; done')
@@ -97,6 +97,60 @@ def"}' )
 [ "$res" = "あ
 def" ] || err $LINENO
 
+res=$($com <<< 'A=aaa ; echo ${A:-abc} ${A:=abc}' )
+[ "$res" = "aaa aaa" ] || err $LINENO
+
+res=$($com <<< 'B= ; echo ${A-unset} ${B-unset} ${B:-empty} ${A+set} ${B+set} ${B:+set}' )
+[ "$res" = "unset empty set" ] || err $LINENO
+
+res=$($com <<< 'A=あいう; B=( a bc def ); echo ${#A} ${#B[@]} ${#B[2]}' )
+[ "$res" = "3 3 3" ] || err $LINENO
+
+res=$($com <<< 'A=(); echo ${#A[@]} "[${A[@]}]"' )
+[ "$res" = "0 []" ] || err $LINENO
+
+res=$($com <<< 'declare -A M; echo ${#M[@]} ${#M[*]}' )
+[ "$res" = "0 0" ] || err $LINENO
+
+res=$($com <<< 'A=(x); unset "A[0]"; echo ${#A[@]} "[${A[*]}]"' )
+[ "$res" = "0 []" ] || err $LINENO
+
+res=$($com <<< 'A=/usr/local/lib/file.tar.gz; echo ${A#*/} ${A##*/} ${A%.*} ${A%%.*}' )
+[ "$res" = "usr/local/lib/file.tar.gz file.tar.gz /usr/local/lib/file.tar /usr/local/lib/file" ] || err $LINENO
+
+res=$($com <<< 'A=abcabc; echo ${A/b/X} ${A//b/X} ${A/#a/X} ${A/%c/X} ${A/#b/X} ${A//[ac]}' )
+[ "$res" = "aXcabc aXcaXc Xbcabc abcabX abcabc bb" ] || err $LINENO
+
+res=$($com <<< 'A=abcdefg; echo ${A:2} ${A:2:3} ${A: -3} ${A: -3:2} ${A:1:-2} ${A:(-2)}' )
+[ "$res" = "cdefg cde efg ef bcde fg" ] || err $LINENO
+
+res=$($com <<< 'A=abc; B=ABC; echo ${A^} ${A^^} ${A^^[ac]} ${B,} ${B,,}' )
+[ "$res" = "Abc ABC AbC aBC abc" ] || err $LINENO
+
+res=$($com <<< 'A=abc; B=A; echo ${!B} ${!B^^}; SUSH_X=1; SUSH_Y=2; echo ${!SUSH_*}' )
+[ "$res" = "abc ABC
+SUSH_X SUSH_Y" ] || err $LINENO
+
+res=$($com <<< 'A=( x y z ); echo ${!A[@]}; echo ${A[@]:1} ${A[@]/y/Y} ${A[@]^}' )
+[ "$res" = "0 1 2
+y z x Y z X Y Z" ] || err $LINENO
+
+res=$($com <<< 'set a b c d; echo ${@:2} ${@:1:2} ${#@} ${@#a}' )
+[ "$res" = "b c d a b 4 b c d" ] || err $LINENO
+
+res=$($com <<< "A=\"it's\"; B='a\\tb'; echo \${A@Q} \${A@U} \${A@A}; echo \"\${B@E}\"" )
+[ "$res" = "'it'\''s' IT'S A='it'\''s'
+a	b" ] || err $LINENO
+
+res=$($com <<< 'A=( a b ); echo ${A[@]@A} ${A@a}' )
+[ "$res" = 'declare -a A=([0]="a" [1]="b") a' ] || err $LINENO
+
+res=$($com <<< 'A=/usr/local/lib; echo ${A##+([a-z/])}l ${A//@(s|c)/_}' )
+[ "$res" = "l /u_r/lo_al/lib" ] || err $LINENO
+
+res=$($com <<< 'A=abc; echo ${A@Z}' )
+[ "$?" = "1" ] || err $LINENO
+
 ### IRREGULAR INPUT TEST ###
 
 res=$($com <<< 'eeeeeecho hoge')
@@ -286,12 +340,60 @@ res=$($com <<< 'pwd 200>&100')
 res=$($com <<< 'ls 200>&100')
 [ "$?" == "1" ] || err $LINENO
 
+res=$($com <<< 'echo a > /tmp/$$-rw ; cat <> /tmp/$$-rw ; rm /tmp/$$-rw')
+[ "$res" == "a" ] || err $LINENO
+
+res=$($com <<< 'exec 3<<< abc ; cat <&3 ; exec 3<&- ; cat <&3')
+[ "$?" == "1" ] || err $LINENO
+[ "$res" == "abc" ] || err $LINENO
+
+res=$($com <<< 'echo a &> /tmp/$$-both ; ls aaaaaaaaaaaaaa &>> /tmp/$$-both ; cat /tmp/$$-both | wc -l ; rm /tmp/$$-both')
+[ "$res" == "2" ] || err $LINENO
+
+res=$($com <<< 'exec {fd}> /tmp/$$-var ; [ $fd -ge 10 ] && echo a >&$fd ; exec {fd}>&- ; cat /tmp/$$-var ; rm /tmp/$$-var')
+[ "$res" == "a" ] || err $LINENO
+
+res=$($com <<< '{ echo a ; echo b >&2 ; } 3>&1 1>&2 2>&3-' 2> /dev/null)
+[ "$res" == "b" ] || err $LINENO
+
 # with expansion
 
 res=$($com <<< 'echo a > {a,b}' 2>&1)
 [ "$?" == "1" ] || err $LINENO
 [ "$res" == "sush: {a,b}: ambiguous redirect" ] || err $LINENO
 
+# here documents and here strings
+
+res=$($com <<< 'A=x; rev <<< "a $A"')
+[ "$res" == "x a" ] || err $LINENO
+
+res=$($com <<< 'read a b <<< "hello world"; echo $b')
+[ "$res" == "world" ] || err $LINENO
+
+res=$($com <<< 'A=abc
+cat << EOF
+$A $((1+2)) \$A $(echo sub)
+EOF')
+[ "$res" == "abc 3 \$A sub" ] || err $LINENO
+
+res=$($com <<< "cat <<'EOF' | rev
+\$HOME
+EOF")
+[ "$res" == 'EMOH$' ] || err $LINENO
+
+res=$($com <<< 'cat <<-EOF
+		indent
+	EOF')
+[ "$res" == "indent" ] || err $LINENO
+
+res=$($com <<< 'while read a ; do echo [$a] ; done <<END ; echo end
+1
+2
+END')
+[ "$res" == "[1]
+[2]
+end" ] || err $LINENO
+
 ### JOB PARSE TEST ###
 
 res=$($com <<< '&& echo a')
@@ -360,6 +462,9 @@ res=$($com <<< 'echo "\a"')
 res=$($com <<< 'echo "\\"')
 [ "$res" == '\' ] || err $LINENO
 
+res=$($com <<< 'echo "a\"b\`c"')
+[ "$res" == 'a"b`c' ] || err $LINENO
+
 res=$($com <<< 'echo "a   b"')
 [ "$res" == 'a   b' ] || err $LINENO
 
@@ -1211,6 +1316,41 @@ res=$($com <<< "echo /*'b'*" | grep -F '*')
 res=$($com <<< 'echo /"*"' | grep -F '*')
 [ "$?" == "0" ] || err $LINENO
 
+res=$($com <<< 'cd /tmp; rm -rf $$-g; mkdir -p $$-g/a/b $$-g/.h; cd $$-g; touch a/x.rs a/b/y.rs .h/z.rs B c
+echo **/*.rs; shopt -s globstar; echo **/*.rs; echo a/**; echo **/
+shopt -s dotglob; echo *; shopt -u dotglob
+shopt -s nocaseglob; echo b*; shopt -u nocaseglob
+GLOBIGNORE=c:a; echo *; unset GLOBIGNORE
+LANG=C; echo *
+shopt -s nullglob; echo *.zz "*.zz" [; shopt -u nullglob; echo *.zz
+cd /tmp; rm -rf $$-g')
+[ "$res" == "a/x.rs
+a/b/y.rs a/x.rs
+a/ a/b a/b/y.rs a/x.rs
+a/ a/b/
+.h B a c
+B
+.h B
+B a c
+*.zz [
+*.zz" ] || err $LINENO
+
+res=$($com <<< 'cd /tmp; rm -rf $$-g; mkdir -p $$-g/a/b; cd $$-g; touch a/b/x; ln -s a l; ln -s . self
+shopt -s globstar; echo **/; echo **
+cd /tmp; rm -rf $$-g')
+[ "$res" == "a/ a/b/ l/ self/
+a a/b a/b/x l self" ] || err $LINENO
+
+res=$($com <<< 'cd /tmp; rm -rf $$-g; mkdir $$-g; cd $$-g; touch b B a _c
+LANG=C; echo *; LC_ALL=POSIX; echo *
+cd /tmp; rm -rf $$-g')
+[ "$res" == "B _c a b
+B _c a b" ] || err $LINENO
+
+res=$($com <<< 'shopt -s failglob; echo /*.zz; echo NG
+echo $?')
+[ "$res" == "1" ] || err $LINENO
+
 res=$($com <<< 'echo @(あ|{い,う,})')
 [ "$res" == "@(あ|い) @(あ|う) @(あ|)" ] || err $LINENO
 
@@ -1275,6 +1415,14 @@ res=$($com <<< 'A=( a b ); echo ${A[1]}')
 res=$($com <<< 'A=( a b ); echo ${A[@]}')
 [ "$res" == "a b" ] || err $LINENO
 
+res=$($com <<< 'A=( a b c ); i=1; A[i+1]=C; echo ${A[i]} ${A[-1]} ${A[@]}')
+[ "$res" == "b C a b C" ] || err $LINENO
+
+res=$($com <<< 'A=( [1]=b c ); echo ${A[2]}; f(){ local x=1; x=2; echo $x; }; f; echo "x=$x"')
+[ "$res" == "c
+2
+x=" ] || err $LINENO
+
 # symbol
 
 res=$($com <<< 'echo ]')
@@ -1314,4 +1462,221 @@ res=$($com <<< 'set -o pipefail; ls aaaa | false | true')
 res=$($com <<< 'set -o pipefail; set -e; false | true ; echo NG')
 [ "$res" == "" ] || err $LINENO
 
+res=$($com <<< 'set -e ; ! true ; if false ; then echo NG ; fi ; echo OK')
+[ "$res" == "OK" ] || err $LINENO
+
+res=$($com <<< 'set -u ; echo $NOPE ; echo NG')
+[ "$?" == "127" ] || err $LINENO
+[ "$res" == "" ] || err $LINENO
+
+res=$($com <<< 'set -u ; echo ${NOPE:-OK}')
+[ "$res" == "OK" ] || err $LINENO
+
+res=$($com <<< 'set -C ; echo a > /tmp/$$-nc ; echo b > /tmp/$$-nc ; echo c >| /tmp/$$-nc ; cat /tmp/$$-nc ; rm /tmp/$$-nc')
+[ "$res" == "c" ] || err $LINENO
+
+res=$($com <<< 'set -f ; echo /*')
+[ "$res" == "/*" ] || err $LINENO
+
+res=$($com <<< 'set -a ; A=OK ; bash -c "echo \$A"')
+[ "$res" == "OK" ] || err $LINENO
+
+res=$($com <<< 'set -eu -o noclobber ; echo $- ; set +eu ; echo $-')
+[ "$res" == "ehuBC
+hBC" ] || err $LINENO
+
+res=$($com <<< 'echo {a,b} ; set +B ; echo {a,b}')
+[ "$res" == "a b
+{a,b}" ] || err $LINENO
+
+res=$($com <<< 'set -o noglob ; set -o | grep noglob')
+[ "$res" == "noglob          on" ] || err $LINENO
+
+res=$($com <<< 'set -o nosuchopt')
+[ "$?" == "2" ] || err $LINENO
+
+res=$(HOME=/tmp/$$-home python3 - $com <<'EOF'
+import os, pty, select, sys, time
+pid, fd = pty.fork()
+if pid == 0:
+    os.execv(sys.argv[1], [sys.argv[1]])
+out = b""
+def read(sec):
+    global out
+    end = time.time() + sec
+    while time.time() < end:
+        if select.select([fd], [], [], 0.05)[0]:
+            try:
+                data = os.read(fd, 4096)
+            except OSError:
+                return
+            out += data
+            if b"\x1b[6n" in data:
+                os.write(fd, b"\x1b[1;1R")
+read(1.0)
+os.write(fd, b"IGNOREEOF=2\r")
+for _ in range(3):
+    read(0.5)
+    os.write(fd, b"\x04")
+read(1.0)
+os.kill(pid, 9)
+print(out.decode().count("Use \"exit\" to leave the shell."))
+EOF
+)
+[ "$res" == "2" ] || err $LINENO
+
+res=$($com <<< 'set -o emacs; set -o vi; set +o | grep -E " (vi|emacs)$"')
+[ "$res" == "set +o emacs
+set -o vi" ] || err $LINENO
+
+res=$($com <<< 'shopt highlight; shopt -s highlight; shopt highlight')
+[ "$res" == "highlight       off
+highlight       on" ] || err $LINENO
+
+res=$($com <<< 'diff <(echo a) <(echo a) && echo OK')
+[ "$res" == "OK" ] || err $LINENO
+
+res=$($com <<< 'while read l ; do echo $l ; done < <(echo a ; echo b)')
+[ "$res" == "a
+b" ] || err $LINENO
+
+res=$($com <<< 'echo abc | tee >(tr a-z A-Z > /tmp/$$-ps) > /dev/null ; wait $! ; cat /tmp/$$-ps ; rm /tmp/$$-ps')
+[ "$res" == "ABC" ] || err $LINENO
+
+res=$($com <<< 'echo <(:) | grep -q "^/dev/fd/" && echo OK')
+[ "$res" == "OK" ] || err $LINENO
+
+### HISTORY EXPANSION ###
+
+res=$($com <<< $'set -H -o history\nhistory -s echo a\n!!\n!1 b\necho x\n^x^y\n!ec' 2> /dev/null)
+[ "$res" == "a
+a b
+x
+y
+y" ] || err $LINENO
+
+res=$($com <<< $'set -H -o history\necho a\n!e:p\n!zz\necho b' 2>&1)
+[ "$res" == "a
+echo a
+sush: !zz: event not found
+b" ] || err $LINENO
+
+res=$($com <<< $'set -H\necho a\necho !!')
+[ "$res" == "a
+!!" ] || err $LINENO
+
+res=$($com <<< 'history -s ls /a/b.txt c; history -p "!!:1:e" "!!:2:e" "!!:1:r"')
+[ "$res" == ".txt
+c
+/a/b" ] || err $LINENO
+
+### HIGHLIGHT ###
+
+# the last drawing of a line typed into the interactive shell with the highlight shopt
+highlight () {
+	HOME=/tmp/$$-home python3 - $com "$1" <<'EOF'
+import os, pty, select, sys, time
+pid, fd = pty.fork()
+if pid == 0:
+    os.execv(sys.argv[1], [sys.argv[1]])
+out = b""
+def read(sec):
+    global out
+    end = time.time() + sec
+    while time.time() < end:
+        if select.select([fd], [], [], 0.05)[0]:
+            data = os.read(fd, 4096)
+            out += data
+            if b"\x1b[6n" in data:
+                os.write(fd, b"\x1b[1;1R")
+read(1.0)
+os.write(fd, b"shopt -s highlight\r")
+read(0.5)
+os.write(fd, sys.argv[2].encode())
+read(0.5)
+os.kill(pid, 9)
+last = out.decode().split("\U0001F363 ")[-1]
+print(last[:last.rfind("\x1b[")])
+EOF
+}
+
+res=$(highlight 'while ! false; do :; done # c')
+[ "$res" == $'\e[1;34mwhile\e[m \e[1;34m!\e[m \e[1;32mfalse\e[m; \e[1;34mdo\e[m \e[1;32m:\e[m; \e[1;34mdone\e[m \e[90m# c\e[m' ] || err $LINENO
+
+res=$(highlight "echo 'a' \"b \$HOME\" >/dev/null")
+[ "$res" == $'\e[1;32mecho\e[m \e[33m\'a\'\e[m \e[33m"b \e[36m$HOME\e[33m"\e[m \e[35m>\e[m/dev/null' ] || err $LINENO
+
+res=$(highlight 'echo ${x:-y} $(ls) $1 $((1+2))')
+[ "$res" == $'\e[1;32mecho\e[m \e[36m${x:-y}\e[m \e[36m$(\e[32mls\e[36m)\e[m \e[36m$1\e[m \e[36m$((1+2))\e[m' ] || err $LINENO
+
+res=$(highlight "for i in 1; do nosuchcmd \"a \$i")
+[ "$res" == $'\e[1;34mfor\e[m i \e[1;34min\e[m 1; \e[1;34mdo\e[m \e[31mnosuchcmd\e[m \e[33m"a \e[36m$i\e[m' ] || err $LINENO
+
+res=$(highlight "case \$a in x) echo 'b")
+[ "$res" == $'\e[1;34mcase\e[m \e[36m$a\e[m \e[1;34min\e[m x) \e[1;32mecho\e[m \e[33m\'b\e[m' ] || err $LINENO
+
+### PROMPT ###
+
+res=$($com <<< 'x=abc; PS1="\u \$x"; echo "${PS1@P}"')
+[ "$res" == "$(whoami) abc" ] || err $LINENO
+
+res=$($com <<< 'A="\\\\ \101 \$((1+2))"; echo "${A@P}"')
+[ "$res" == '\ A 3' ] || err $LINENO
+
+res=$($com <<< 'A="\[\e[1m\]x\[\e[m\]"; echo "${A@P}"' | od -c | head -1)
+[[ "$res" =~ "033   [   1   m   x 033   [   m" ]] || err $LINENO
+
+res=$($com <<< 'shopt -u promptvars; x=1; A="\$x \s"; echo "${A@P}"')
+[ "$res" == '$x sush' ] || err $LINENO
+
+res=$($com <<< 'cd /; A="\w \W"; echo "${A@P}"')
+[ "$res" == '/ /' ] || err $LINENO
+
+res=$($com <<< 'set -x; PS4="[\$LINENO] "; echo a' 2>&1)
+[ "$res" == '+ PS4="[\$LINENO] "
+[1] echo a
+a' ] || err $LINENO
+
+res=$($com <<< 'PS3="\\101> "; select a in x; do echo $a; break; done <<< 1' 2>&1)
+[ "$res" == '1) x
+A> x' ] || err $LINENO
+
+### GIT PROMPT ###
+
+rm -rf /tmp/$$-git
+mkdir -p /tmp/$$-git/.git/refs/heads /tmp/$$-git/sub
+echo 'ref: refs/heads/main' > /tmp/$$-git/.git/HEAD
+res=$($com <<< 'cd /tmp/'$$'-git/sub; A="\b"; echo "${A@P}"')
+[ "$res" == "main🌵" ] || err $LINENO
+
+touch /tmp/$$-git/.git/MERGE_HEAD /tmp/$$-git/sub/file
+res=$($com <<< 'cd /tmp/'$$'-git; A="\b"; echo "${A@P}"')
+[ "$res" == "main|MERGING?🌵" ] || err $LINENO
+
+res=$($com <<< 'cd /tmp/'$$'-git; SUSH_GIT_FORMAT="(%b%u) %%"; SUSH_GIT_UNTRACKED=N; A="\b"; echo "${A@P}"')
+[ "$res" == "(mainN) %" ] || err $LINENO
+
+echo 0123456789abcdef0123456789abcdef01234567 > /tmp/$$-git/.git/HEAD
+res=$($com <<< 'cd /tmp/'$$'-git; SUSH_GIT_FORMAT=%b; A="\b"; echo "${A@P}"')
+[ "$res" == "(0123456)" ] || err $LINENO
+
+for format in sha1 sha256 ; do
+	rm -rf /tmp/$$-git
+	git init -q --object-format=$format -b main /tmp/$$-git
+	echo abc > /tmp/$$-git/a
+	git -C /tmp/$$-git add a
+	git -C /tmp/$$-git -c user.name=sush -c user.email=sush@localhost commit -qm a
+	touch -d 2001-01-01 /tmp/$$-git/a
+	res=$($com <<< 'cd /tmp/'$$'-git; SUSH_GIT_FORMAT=%b%d; A="\b"; echo "${A@P}"')
+	[ "$res" == "main" ] || err $LINENO
+
+	echo abd > /tmp/$$-git/a
+	res=$($com <<< 'cd /tmp/'$$'-git; SUSH_GIT_FORMAT=%b%d; A="\b"; echo "${A@P}"')
+	[ "$res" == "main*" ] || err $LINENO
+done
+
+res=$($com <<< 'cd /; A="[\b]"; echo "${A@P}"')
+[ "$res" == "[]" ] || err $LINENO
+rm -rf /tmp/$$-git
+
 echo $0 >> ./ok